@@ -11,7 +11,9 @@
 
 use futures::stream::TryStreamExt;
 use futures_util::pin_mut;
-use rspotify::{prelude::*, scopes, AuthCodeSpotify, Credentials, OAuth};
+use rspotify::{
+    clients::pagination::PaginationState, prelude::*, scopes, AuthCodeSpotify, Credentials, OAuth,
+};
 
 #[tokio::main]
 async fn main() {
@@ -48,4 +50,31 @@ async fn main() {
         })
         .await
         .unwrap();
+
+    // Requesting the pages concurrently, up to 4 at a time. The items are still
+    // yielded in order.
+    let stream = spotify.current_user_saved_tracks(None).concurrency(4);
+    pin_mut!(stream);
+    println!("\nItems (concurrent requests):");
+    while let Some(item) = stream.try_next().await.unwrap() {
+        println!("* {}", item.track.name);
+    }
+
+    // Keeping track of the pagination state, which can be serialized to
+    // resume it later on. Each failed page is retried up to 3 times.
+    let stream = spotify.current_user_saved_tracks_resumable(PaginationState::new(None), 3);
+    pin_mut!(stream);
+    println!("\nItems (resumable):");
+    loop {
+        match stream.try_next().await {
+            Ok(Some((page, state))) => {
+                for item in page.items {
+                    println!("* {}", item.track.name);
+                }
+                println!("Resume from: {:?}", state.position);
+            }
+            Ok(None) => break,
+            Err(err) => println!("Request failed: {}", err),
+        }
+    }
 }
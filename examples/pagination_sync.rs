@@ -33,4 +33,14 @@ fn main() {
     for item in stream {
         println!("* {}", item.unwrap().track.name);
     }
+
+    // Requesting the next page in a worker thread while the current one is
+    // being processed.
+    let stream = spotify.current_user_saved_tracks_prefetch(None, |task| {
+        std::thread::spawn(task);
+    });
+    println!("\nItems (prefetched):");
+    for item in stream {
+        println!("* {}", item.unwrap().track.name);
+    }
 }
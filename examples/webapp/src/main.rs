@@ -2,6 +2,10 @@
 //! real-world web app, you should store it in a database instead. In that case
 //! you can disable `token_cached` in the `Config` struct passed to the client
 //! when initializing it to avoid using cache files.
+//!
+//! For apps with many users, `rspotify::UserSessions` keeps the token of each
+//! user in memory, keyed by their session ID, and hands out clients that share
+//! the same HTTP client and configuration.
 
 use cookie::time::Duration;
 use getrandom::getrandom;
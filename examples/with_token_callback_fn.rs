@@ -2,12 +2,15 @@
 //! will be invoked whenever client succeeds to request or refetch a token.
 //! Therefore, developer could write token into file or save token into database
 //! after fetch the token with their own callback function.
+//!
+//! The callback function may also be asynchronous with `AsyncTokenCallback`,
+//! which is useful to save the token with an asynchronous database client.
 
 use std::sync::Arc;
 
 use rspotify::{
-    clients::OAuthClient, scopes, AuthCodePkceSpotify, AuthCodeSpotify, CallbackError,
-    ClientCredsSpotify, Config, Credentials, OAuth, TokenCallback,
+    clients::OAuthClient, scopes, AsyncTokenCallback, AuthCodePkceSpotify, AuthCodeSpotify,
+    CallbackError, ClientCredsSpotify, Config, Credentials, OAuth, TokenCallback,
 };
 
 async fn _with_pkce(creds: Credentials, oauth: OAuth) {
@@ -81,6 +84,23 @@ async fn with_client_credentials(creds: Credentials) {
     spotify.request_token().await.unwrap();
 }
 
+async fn with_async_callback(creds: Credentials) {
+    let token_callback = AsyncTokenCallback::new(|token| async move {
+        println!(">>> From async token callback function with ClientCredsSpotify");
+        println!(">>> Here it could be saved into a database, for example");
+        println!(">>> token: {:?}", token);
+        Ok(())
+    });
+
+    let config = Config {
+        async_token_callback_fn: Arc::new(Some(token_callback)),
+        ..Default::default()
+    };
+    println!(">>> Fetch token with ClientCredsSpotify and an async callback");
+    let spotify = ClientCredsSpotify::with_config(creds, config);
+    spotify.request_token().await.unwrap();
+}
+
 #[tokio::main]
 async fn main() {
     // You can use any logger for debugging.
@@ -93,5 +113,6 @@ async fn main() {
 
     with_auth(creds.clone(), oauth.clone()).await;
     // with_pkce(creds.clone(), oauth).await;
-    with_client_credentials(creds).await;
+    with_client_credentials(creds.clone()).await;
+    with_async_callback(creds).await;
 }
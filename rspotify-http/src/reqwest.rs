@@ -3,10 +3,7 @@
 
 use super::{BaseHttpClient, Form, Headers, Query};
 
-use std::convert::TryInto;
-
-#[cfg(not(target_arch = "wasm32"))]
-use std::time::Duration;
+use std::{convert::TryInto, time::Duration};
 
 use maybe_async::async_impl;
 use reqwest::{Method, RequestBuilder};
@@ -52,6 +49,41 @@ pub enum ReqwestError {
     StatusCode(reqwest::Response),
 }
 
+impl ReqwestError {
+    /// The status code of the response, if the request was completed but
+    /// unsuccessful.
+    #[must_use]
+    pub fn status_code(&self) -> Option<u16> {
+        match self {
+            Self::StatusCode(response) => Some(response.status().as_u16()),
+            _ => None,
+        }
+    }
+
+    /// How long to wait before trying again, according to the `Retry-After`
+    /// header of an unsuccessful response, e.g. when rate limited.
+    #[must_use]
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            Self::StatusCode(response) => {
+                let seconds = response.headers().get(reqwest::header::RETRY_AFTER)?;
+                let seconds = seconds.to_str().ok()?.trim().parse().ok()?;
+                Some(Duration::from_secs(seconds))
+            }
+            _ => None,
+        }
+    }
+
+    /// Consumes the error, returning the body of the response if the request
+    /// was completed but unsuccessful.
+    pub async fn into_response_text(self) -> Option<String> {
+        match self {
+            Self::StatusCode(response) => response.text().await.ok(),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct ReqwestClient {
     /// reqwest needs an instance of its client to perform requests.
@@ -50,6 +50,40 @@ pub enum UreqError {
     StatusCode(ureq::Response),
 }
 
+impl UreqError {
+    /// The status code of the response, if the request was completed but
+    /// unsuccessful.
+    #[must_use]
+    pub fn status_code(&self) -> Option<u16> {
+        match self {
+            Self::StatusCode(response) => Some(response.status()),
+            _ => None,
+        }
+    }
+
+    /// How long to wait before trying again, according to the `Retry-After`
+    /// header of an unsuccessful response, e.g. when rate limited.
+    #[must_use]
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            Self::StatusCode(response) => {
+                let seconds = response.header("retry-after")?.trim().parse().ok()?;
+                Some(Duration::from_secs(seconds))
+            }
+            _ => None,
+        }
+    }
+
+    /// Consumes the error, returning the body of the response if the request
+    /// was completed but unsuccessful.
+    pub fn into_response_text(self) -> Option<String> {
+        match self {
+            Self::StatusCode(response) => response.into_string().ok(),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct UreqClient {
     agent: ureq::Agent,
@@ -1,3 +1,110 @@
+/// Invokes the macro `$callback` with every scope accepted by [`scopes!`], as
+/// `Variant => "scope"` pairs. Both [`KNOWN_SCOPES`] and the variants of
+/// `rspotify_model::Scope` are generated from this list, so that they can't
+/// get out of sync.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! with_known_scopes {
+    ($callback:ident) => {
+        $callback! {
+            // Images
+            UgcImageUpload => "ugc-image-upload",
+            // Spotify Connect
+            UserReadPlaybackState => "user-read-playback-state",
+            UserModifyPlaybackState => "user-modify-playback-state",
+            UserReadCurrentlyPlaying => "user-read-currently-playing",
+            // Playback
+            AppRemoteControl => "app-remote-control",
+            Streaming => "streaming",
+            // Playlists
+            PlaylistReadPrivate => "playlist-read-private",
+            PlaylistReadCollaborative => "playlist-read-collaborative",
+            PlaylistModifyPrivate => "playlist-modify-private",
+            PlaylistModifyPublic => "playlist-modify-public",
+            // Follow
+            UserFollowModify => "user-follow-modify",
+            UserFollowRead => "user-follow-read",
+            // Listening History
+            UserReadPlaybackPosition => "user-read-playback-position",
+            UserTopRead => "user-top-read",
+            UserReadRecentlyPlayed => "user-read-recently-played",
+            // Library
+            UserLibraryModify => "user-library-modify",
+            UserLibraryRead => "user-library-read",
+            // Users
+            UserReadEmail => "user-read-email",
+            UserReadPrivate => "user-read-private",
+            // Open Access
+            UserSoaLink => "user-soa-link",
+            UserSoaUnlink => "user-soa-unlink",
+            SoaManageEntitlements => "soa-manage-entitlements",
+            SoaManagePartner => "soa-manage-partner",
+            SoaCreatePartner => "soa-create-partner",
+        }
+    };
+}
+
+macro_rules! known_scopes {
+    ($($variant:ident => $scope:literal,)*) => {
+        /// The scopes accepted by [`scopes!`], which are the same as the
+        /// variants of `rspotify_model::Scope`.
+        #[doc(hidden)]
+        pub const KNOWN_SCOPES: &[&str] = &[$($scope),*];
+    };
+}
+
+with_known_scopes!(known_scopes);
+
+/// Returns whether all the whitespace-separated scopes in `scopes` are in
+/// [`KNOWN_SCOPES`]. It's a `const fn` so that [`scopes!`] can check them at
+/// compile time.
+#[doc(hidden)]
+#[must_use]
+pub const fn valid_scopes(scopes: &str) -> bool {
+    let scopes = scopes.as_bytes();
+    let mut start = 0;
+    while start < scopes.len() {
+        if scopes[start].is_ascii_whitespace() {
+            start += 1;
+            continue;
+        }
+
+        let mut end = start;
+        while end < scopes.len() && !scopes[end].is_ascii_whitespace() {
+            end += 1;
+        }
+        if !is_known_scope(scopes, start, end) {
+            return false;
+        }
+        start = end;
+    }
+
+    true
+}
+
+/// Returns whether `scopes[start..end]` is in [`KNOWN_SCOPES`].
+const fn is_known_scope(scopes: &[u8], start: usize, end: usize) -> bool {
+    let mut i = 0;
+    'known: while i < KNOWN_SCOPES.len() {
+        let known = KNOWN_SCOPES[i].as_bytes();
+        i += 1;
+        if known.len() != end - start {
+            continue;
+        }
+
+        let mut j = 0;
+        while j < known.len() {
+            if known[j] != scopes[start + j] {
+                continue 'known;
+            }
+            j += 1;
+        }
+        return true;
+    }
+
+    false
+}
+
 /// Create a [`HashSet`](std::collections::HashSet) from a list of `&str` to
 /// easily create scopes for `Token` or `OAuth`.
 ///
@@ -25,9 +132,41 @@
 /// manually.insert("playlist-read-collaborative".to_owned());
 /// assert_eq!(macro_with_whitespace, manually);
 /// ```
+///
+/// When all the scopes are string literals, they're checked at compile time.
+/// Unknown scopes, like the following typo, fail to compile:
+///
+/// ```compile_fail
+/// use rspotify_macros::scopes;
+///
+/// let scopes = scopes!("user-read-playback-stat");
+/// ```
+///
+/// Other expressions are accepted too, e.g. scopes read from the environment,
+/// but they can't be checked:
+///
+/// ```
+/// use rspotify_macros::scopes;
+///
+/// let extra = String::from("user-read-email");
+/// let scopes = scopes!("user-read-private", &extra);
+/// assert_eq!(scopes.len(), 2);
+/// ```
 #[macro_export]
 macro_rules! scopes {
-    ($($key:expr),*) => {{
+    ($($key:literal),* $(,)?) => {{
+        $(
+            const _: () = assert!(
+                $crate::valid_scopes($key),
+                "unknown scope in `scopes!`, see `rspotify::model::Scope` for the valid ones"
+            );
+        )*
+        $crate::scopes!(@collect $($key),*)
+    }};
+    ($($key:expr),* $(,)?) => {
+        $crate::scopes!(@collect $($key),*)
+    };
+    (@collect $($key:expr),*) => {{
         let mut container = ::std::collections::HashSet::new();
         $(
             for scope in $key.split_whitespace(){
@@ -40,26 +179,50 @@ macro_rules! scopes {
 
 #[cfg(test)]
 mod test {
-    use crate::scopes;
-
     #[test]
     fn test_hashset() {
-        let scopes = scopes!("hello", "world", "foo", "bar");
+        let scopes = scopes!(
+            "streaming",
+            "user-top-read",
+            "user-read-email",
+            "user-library-read"
+        );
         assert_eq!(scopes.len(), 4);
-        assert!(scopes.contains("hello"));
-        assert!(scopes.contains("world"));
-        assert!(scopes.contains("foo"));
-        assert!(scopes.contains("bar"));
+        assert!(scopes.contains("streaming"));
+        assert!(scopes.contains("user-top-read"));
+        assert!(scopes.contains("user-read-email"));
+        assert!(scopes.contains("user-library-read"));
     }
 
     #[test]
     fn test_scopes_with_whitespace() {
-        let scopes = scopes!("      hello world foo bar");
+        let scopes = scopes!("      streaming user-top-read user-read-email  user-library-read");
+
+        assert_eq!(scopes.len(), 4);
+        assert!(scopes.contains("streaming"));
+        assert!(scopes.contains("user-top-read"));
+        assert!(scopes.contains("user-read-email"));
+        assert!(scopes.contains("user-library-read"));
+    }
+
+    #[test]
+    fn test_scopes_expressions() {
+        let extra = String::from("user-top-read user-read-email");
+        let scopes = scopes!("streaming", &extra, "not-checked".to_owned());
 
         assert_eq!(scopes.len(), 4);
-        assert!(scopes.contains("hello"));
-        assert!(scopes.contains("world"));
-        assert!(scopes.contains("foo"));
-        assert!(scopes.contains("bar"));
+        assert!(scopes.contains("streaming"));
+        assert!(scopes.contains("user-top-read"));
+        assert!(scopes.contains("user-read-email"));
+        assert!(scopes.contains("not-checked"));
+    }
+
+    #[test]
+    fn test_valid_scopes() {
+        assert!(super::valid_scopes(""));
+        assert!(super::valid_scopes(" streaming\tuser-top-read "));
+        assert!(!super::valid_scopes("streaming user-top-rea"));
+        assert!(!super::valid_scopes("user-top-read-"));
+        assert!(!super::valid_scopes("Streaming"));
     }
 }
@@ -2,15 +2,20 @@
 
 use crate::{
     custom_serde::{duration_second, space_separated_scopes},
-    ModelResult,
+    ModelResult, Scope,
 };
 
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{hash_map::RandomState, HashMap, HashSet},
+    ffi::OsString,
     fs,
-    io::{Read, Write},
+    hash::{BuildHasher, Hasher},
+    io::{self, Read, Write},
     path::Path,
+    sync::atomic::{AtomicU64, Ordering},
 };
+#[cfg(feature = "encrypted-cache")]
+use {crate::ModelError, std::fmt};
 
 use chrono::{DateTime, Duration, TimeDelta, Utc};
 use serde::{Deserialize, Serialize};
@@ -66,27 +71,65 @@ impl Token {
     }
 
     /// Saves the token information into its cache file.
+    ///
+    /// The file is only readable by its owner on Unix, and it's replaced
+    /// atomically so that it's never left truncated.
     pub fn write_cache<T: AsRef<Path>>(&self, path: T) -> ModelResult<()> {
         let token_info = serde_json::to_string(&self)?;
+        write_private_file(path.as_ref(), token_info.as_bytes())
+    }
 
-        let mut file = fs::OpenOptions::new()
-            .write(true)
-            .create(true)
-            .truncate(true)
-            .open(path)?;
-        file.set_len(0)?;
-        file.write_all(token_info.as_bytes())?;
+    /// Tries to initialize the token from a cache file encrypted with
+    /// [`Self::write_encrypted_cache`].
+    ///
+    /// Note: this method requires the `encrypted-cache` feature.
+    #[cfg(feature = "encrypted-cache")]
+    pub fn from_encrypted_cache<T: AsRef<Path>>(path: T, key: &CacheKey) -> ModelResult<Self> {
+        let contents = fs::read(path)?;
+        let tok_str = key.decrypt(&contents)?;
+        let tok = serde_json::from_slice(&tok_str)?;
 
-        Ok(())
+        Ok(tok)
+    }
+
+    /// Saves the token information into an encrypted cache file, so that it
+    /// can't be read without the given key. Just like with
+    /// [`Self::write_cache`], the file is only readable by its owner on Unix
+    /// and it's replaced atomically.
+    ///
+    /// Note: this method requires the `encrypted-cache` feature.
+    #[cfg(feature = "encrypted-cache")]
+    pub fn write_encrypted_cache<T: AsRef<Path>>(
+        &self,
+        path: T,
+        key: &CacheKey,
+    ) -> ModelResult<()> {
+        let token_info = serde_json::to_vec(&self)?;
+        let contents = key.encrypt(&token_info)?;
+        write_private_file(path.as_ref(), &contents)
     }
 
     /// Check if the token is expired. It includes a margin of 10 seconds (which
     /// is how much a request would take in the worst case scenario).
     #[must_use]
     pub fn is_expired(&self) -> bool {
-        self.expires_at.map_or(true, |expiration| {
-            Utc::now() + TimeDelta::try_seconds(10).unwrap() >= expiration
-        })
+        self.expires_within(TimeDelta::try_seconds(10).unwrap())
+    }
+
+    /// Check if the token is expired or will expire within the given margin.
+    /// Tokens without an expiration date are considered expired.
+    #[must_use]
+    pub fn expires_within(&self, margin: TimeDelta) -> bool {
+        match self.expires_at {
+            Some(expiration) => Utc::now() + margin >= expiration,
+            None => true,
+        }
+    }
+
+    /// Check if the token was granted the given scope.
+    #[must_use]
+    pub fn has_scope(&self, scope: Scope) -> bool {
+        self.scopes.contains(<&str>::from(scope))
     }
 
     /// Generates an HTTP token authorization header with proper formatting
@@ -101,6 +144,212 @@ impl Token {
     }
 }
 
+/// Replaces the contents of a file by writing them to a temporary file in the
+/// same directory first, and then renaming it. On Unix, the file is created
+/// with `0600` permissions.
+///
+/// The temporary file has a random name and it's always newly created, so
+/// that concurrent writes don't collide and leftover files aren't reused with
+/// different permissions.
+fn write_private_file(path: &Path, contents: &[u8]) -> ModelResult<()> {
+    const ATTEMPTS: u32 = 16;
+
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "invalid cache path"))?;
+
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    let mut attempt = 0;
+    let (tmp_path, mut file) = loop {
+        let mut tmp_name = OsString::from(".");
+        tmp_name.push(file_name);
+        tmp_name.push(format!(".{}.tmp", random_suffix()));
+        let tmp_path = path.with_file_name(tmp_name);
+
+        match options.open(&tmp_path) {
+            Ok(file) => break (tmp_path, file),
+            Err(err) if err.kind() == io::ErrorKind::AlreadyExists && attempt < ATTEMPTS => {
+                attempt += 1;
+            }
+            Err(err) => return Err(err.into()),
+        }
+    };
+
+    let result = file
+        .write_all(contents)
+        .and_then(|()| file.sync_all())
+        .and_then(|()| fs::rename(&tmp_path, path));
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp_path);
+    }
+
+    Ok(result?)
+}
+
+/// A random suffix for temporary files, unique within the process.
+fn random_suffix() -> String {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+    // The keys of `RandomState` are random, so hashing a counter with them
+    // is enough to avoid guessable names without extra dependencies.
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u64(COUNTER.fetch_add(1, Ordering::Relaxed));
+    format!("{}.{:016x}", std::process::id(), hasher.finish())
+}
+
+/// The key used to encrypt and decrypt the token cache with
+/// [`Token::write_encrypted_cache`] and [`Token::from_encrypted_cache`].
+///
+/// The cache is encrypted with ChaCha20-Poly1305. When the key is built from
+/// a secret like a password, it's derived with PBKDF2-HMAC-SHA256 and a random
+/// salt, which are stored in the cache file along with the number of rounds.
+///
+/// Note: this type requires the `encrypted-cache` feature.
+#[cfg(feature = "encrypted-cache")]
+#[derive(Clone)]
+pub struct CacheKey(CacheKeyKind);
+
+#[cfg(feature = "encrypted-cache")]
+#[derive(Clone)]
+enum CacheKeyKind {
+    Secret(String),
+    Key([u8; 32]),
+}
+
+#[cfg(feature = "encrypted-cache")]
+impl CacheKey {
+    /// Identifies the format of the encrypted cache files.
+    const MAGIC: &'static [u8] = b"rspotify-encrypted-token-v1\n";
+    const SALT_LEN: usize = 16;
+    const NONCE_LEN: usize = 12;
+    /// The rounds used for new files, which are stored in their header.
+    const PBKDF2_ROUNDS: u32 = if cfg!(test) { 1_000 } else { 600_000 };
+    /// The most rounds accepted when reading a file. They're checked before
+    /// the header is authenticated, so a tampered file could otherwise make
+    /// the key derivation take forever.
+    const MAX_PBKDF2_ROUNDS: u32 = Self::PBKDF2_ROUNDS * 4;
+
+    /// Builds the key from a secret, like a password.
+    #[must_use]
+    pub fn from_secret<S: Into<String>>(secret: S) -> Self {
+        Self(CacheKeyKind::Secret(secret.into()))
+    }
+
+    /// Builds the key from the contents of a file, which should have been
+    /// generated randomly (e.g. 32 bytes from `/dev/urandom`).
+    pub fn from_key_file<T: AsRef<Path>>(path: T) -> ModelResult<Self> {
+        use sha2::{Digest, Sha256};
+
+        let contents = fs::read(path)?;
+        if contents.is_empty() {
+            return Err(ModelError::EncryptedCache(
+                "the key file is empty".to_string(),
+            ));
+        }
+
+        Ok(Self(CacheKeyKind::Key(Sha256::digest(&contents).into())))
+    }
+
+    /// Returns the encryption key for the given salt and PBKDF2 rounds.
+    fn derive(&self, salt: &[u8], rounds: u32) -> [u8; 32] {
+        match &self.0 {
+            CacheKeyKind::Secret(secret) => {
+                let mut key = [0; 32];
+                pbkdf2::pbkdf2::<hmac::Hmac<sha2::Sha256>>(
+                    secret.as_bytes(),
+                    salt,
+                    rounds,
+                    &mut key,
+                );
+                key
+            }
+            CacheKeyKind::Key(key) => *key,
+        }
+    }
+
+    fn encrypt(&self, plaintext: &[u8]) -> ModelResult<Vec<u8>> {
+        use chacha20poly1305::{
+            aead::{Aead, KeyInit, Payload},
+            ChaCha20Poly1305,
+        };
+
+        let mut random = [0; Self::SALT_LEN + Self::NONCE_LEN];
+        getrandom::getrandom(&mut random)
+            .map_err(|err| ModelError::EncryptedCache(err.to_string()))?;
+        let (salt, nonce) = random.split_at(Self::SALT_LEN);
+
+        let rounds = Self::PBKDF2_ROUNDS.to_be_bytes();
+        let mut header = Self::MAGIC.to_vec();
+        header.extend_from_slice(&rounds);
+        header.extend_from_slice(&random);
+
+        let cipher = ChaCha20Poly1305::new(&self.derive(salt, Self::PBKDF2_ROUNDS).into());
+        let payload = Payload {
+            msg: plaintext,
+            aad: &header,
+        };
+        let ciphertext = cipher
+            .encrypt(nonce.into(), payload)
+            .map_err(|_| ModelError::EncryptedCache("unable to encrypt the token".to_string()))?;
+
+        header.extend_from_slice(&ciphertext);
+        Ok(header)
+    }
+
+    fn decrypt(&self, contents: &[u8]) -> ModelResult<Vec<u8>> {
+        use chacha20poly1305::{
+            aead::{Aead, KeyInit, Payload},
+            ChaCha20Poly1305,
+        };
+
+        // The header is made of the magic bytes, the PBKDF2 rounds, the salt
+        // and the nonce, and it's authenticated as well.
+        let header_len = Self::MAGIC.len() + 4 + Self::SALT_LEN + Self::NONCE_LEN;
+        if contents.len() < header_len || !contents.starts_with(Self::MAGIC) {
+            return Err(ModelError::EncryptedCache(
+                "the file isn't an encrypted token cache".to_string(),
+            ));
+        }
+
+        let (header, ciphertext) = contents.split_at(header_len);
+        let (rounds, rest) = header[Self::MAGIC.len()..].split_at(4);
+        let (salt, nonce) = rest.split_at(Self::SALT_LEN);
+        let rounds = u32::from_be_bytes([rounds[0], rounds[1], rounds[2], rounds[3]]);
+        if !(1..=Self::MAX_PBKDF2_ROUNDS).contains(&rounds) {
+            return Err(ModelError::EncryptedCache(format!(
+                "invalid number of PBKDF2 rounds: {}",
+                rounds
+            )));
+        }
+
+        let cipher = ChaCha20Poly1305::new(&self.derive(salt, rounds).into());
+        let payload = Payload {
+            msg: ciphertext,
+            aad: header,
+        };
+        cipher.decrypt(nonce.into(), payload).map_err(|_| {
+            ModelError::EncryptedCache(
+                "unable to decrypt the token, the key may be wrong".to_string(),
+            )
+        })
+    }
+}
+
+#[cfg(feature = "encrypted-cache")]
+impl fmt::Debug for CacheKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // The key itself is never printed
+        f.write_str("CacheKey(..)")
+    }
+}
+
 #[cfg(test)]
 mod test {
     use std::collections::HashSet;
@@ -137,4 +386,106 @@ mod test {
         assert!(token.is_ok());
         assert_eq!(token.unwrap().scopes, tok.scopes);
     }
+
+    #[test]
+    fn test_write_cache() {
+        let path = std::env::temp_dir().join("rspotify_test_write_cache.json");
+        let tok = Token {
+            access_token: "access_token".to_string(),
+            ..Default::default()
+        };
+        tok.write_cache(&path).unwrap();
+        tok.write_cache(&path).unwrap();
+        assert_eq!(Token::from_cache(&path).unwrap(), tok);
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_write_cache_concurrently() {
+        let path = std::env::temp_dir().join("rspotify_test_write_cache_concurrently.json");
+        let threads = (0..8)
+            .map(|i| {
+                let path = path.clone();
+                std::thread::spawn(move || {
+                    let tok = Token {
+                        access_token: format!("access_token_{}", i),
+                        ..Default::default()
+                    };
+                    tok.write_cache(&path).unwrap();
+                })
+            })
+            .collect::<Vec<_>>();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        // The last write wins, and no temporary files are left behind
+        let tok = Token::from_cache(&path).unwrap();
+        assert!(tok.access_token.starts_with("access_token_"));
+        let leftovers = std::fs::read_dir(std::env::temp_dir())
+            .unwrap()
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| {
+                entry
+                    .file_name()
+                    .to_string_lossy()
+                    .starts_with(".rspotify_test_write_cache_concurrently.json.")
+            })
+            .count();
+        assert_eq!(leftovers, 0);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[cfg(feature = "encrypted-cache")]
+    #[test]
+    fn test_encrypted_cache() {
+        use crate::CacheKey;
+
+        let dir = std::env::temp_dir();
+        let path = dir.join("rspotify_test_encrypted_cache");
+        let key_path = dir.join("rspotify_test_encrypted_cache.key");
+        std::fs::write(&key_path, b"0123456789abcdef0123456789abcdef").unwrap();
+
+        let tok = Token {
+            access_token: "access_token".to_string(),
+            refresh_token: Some("refresh_token".to_string()),
+            ..Default::default()
+        };
+        let key = CacheKey::from_key_file(&key_path).unwrap();
+        tok.write_encrypted_cache(&path, &key).unwrap();
+
+        let contents = std::fs::read(&path).unwrap();
+        let contents = String::from_utf8_lossy(&contents);
+        assert!(!contents.contains("access_token"));
+        assert!(!contents.contains("refresh_token"));
+        assert!(Token::from_cache(&path).is_err());
+        assert_eq!(Token::from_encrypted_cache(&path, &key).unwrap(), tok);
+
+        let wrong_key = CacheKey::from_secret("wrong secret");
+        assert!(Token::from_encrypted_cache(&path, &wrong_key).is_err());
+
+        let key = CacheKey::from_secret("secret");
+        tok.write_encrypted_cache(&path, &key).unwrap();
+        assert_eq!(Token::from_encrypted_cache(&path, &key).unwrap(), tok);
+
+        // The rounds are right after the magic bytes
+        let mut contents = std::fs::read(&path).unwrap();
+        let magic_len = b"rspotify-encrypted-token-v1\n".len();
+        contents[magic_len..magic_len + 4].copy_from_slice(&u32::MAX.to_be_bytes());
+        std::fs::write(&path, &contents).unwrap();
+        let err = Token::from_encrypted_cache(&path, &key).unwrap_err();
+        assert!(err.to_string().contains("PBKDF2 rounds"), "{}", err);
+
+        std::fs::remove_file(&path).unwrap();
+        std::fs::remove_file(&key_path).unwrap();
+    }
 }
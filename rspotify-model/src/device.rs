@@ -1,6 +1,8 @@
-use crate::DeviceType;
+use crate::{DeviceType, IdError};
 use serde::{Deserialize, Serialize};
 
+use std::{convert::TryFrom, fmt};
+
 /// Device object
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
 pub struct Device {
@@ -14,6 +16,73 @@ pub struct Device {
     pub volume_percent: Option<u32>,
 }
 
+impl Device {
+    /// Returns the typed ID of the device, or `None` if it doesn't have a
+    /// valid one.
+    #[must_use]
+    pub fn device_id(&self) -> Option<DeviceId> {
+        self.id.as_deref().and_then(|id| DeviceId::from_id(id).ok())
+    }
+}
+
+/// The ID of a [`Device`], to be used in playback requests.
+///
+/// Unlike other IDs, it has no URI. It's only validated to be non-empty and to
+/// have no characters that would need to be escaped in a URL, since it's
+/// included in the query of the requests.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct DeviceId(String);
+
+impl DeviceId {
+    /// Parses the ID of a device, e.g.
+    /// `5fbb3ba6aa454b5534c4ba43a8c7e8e45a63ad0e`.
+    pub fn from_id<S: Into<String>>(id: S) -> Result<Self, IdError> {
+        let id = id.into();
+        let valid = !id.is_empty()
+            && id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | '~'));
+        if valid {
+            Ok(Self(id))
+        } else {
+            Err(IdError::InvalidId)
+        }
+    }
+
+    /// The ID as a string.
+    #[must_use]
+    pub fn id(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for DeviceId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl AsRef<str> for DeviceId {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl TryFrom<String> for DeviceId {
+    type Error = IdError;
+
+    fn try_from(id: String) -> Result<Self, Self::Error> {
+        Self::from_id(id)
+    }
+}
+
+impl From<DeviceId> for String {
+    fn from(id: DeviceId) -> Self {
+        id.0
+    }
+}
+
 /// Intermediate device payload object
 #[derive(Deserialize)]
 pub struct DevicePayload {
@@ -38,3 +107,15 @@ fn test_devices() {
     let payload: DevicePayload = serde_json::from_str(json_str).unwrap();
     assert_eq!(payload.devices[0]._type, DeviceType::Computer)
 }
+
+#[test]
+fn test_device_id() {
+    let id = DeviceId::from_id("5fbb3ba6aa454b5534c4ba43a8c7e8e45a63ad0e").unwrap();
+    assert_eq!(id.id(), "5fbb3ba6aa454b5534c4ba43a8c7e8e45a63ad0e");
+    assert_eq!(DeviceId::from_id(""), Err(IdError::InvalidId));
+    assert_eq!(DeviceId::from_id("abc&play=1"), Err(IdError::InvalidId));
+
+    let json = serde_json::to_string(&id).unwrap();
+    assert_eq!(serde_json::from_str::<DeviceId>(&json).unwrap(), id);
+    assert!(serde_json::from_str::<DeviceId>(r#""a b""#).is_err());
+}
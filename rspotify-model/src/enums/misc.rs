@@ -1,13 +1,15 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use strum::IntoStaticStr;
+use strum::{Display, EnumIter, EnumString, IntoStaticStr};
 
 use super::Country;
 
 /// Disallows object: `interrupting_playback`, `pausing`, `resuming`, `seeking`,
 /// `skipping_next`, `skipping_prev`, `toggling_repeat_context`,
 /// `toggling_shuffle`, `toggling_repeat_track`, `transferring_playback`.
-#[derive(Clone, Serialize, Deserialize, Copy, PartialEq, Eq, Debug, Hash, IntoStaticStr)]
+#[derive(
+    Clone, Serialize, Deserialize, Copy, PartialEq, Eq, Debug, Hash, Display, IntoStaticStr,
+)]
 #[serde(rename_all = "snake_case")]
 #[strum(serialize_all = "snake_case")]
 pub enum DisallowKey {
@@ -107,3 +109,38 @@ pub enum TimeLimits {
     Before(DateTime<Utc>),
     After(DateTime<Utc>),
 }
+
+macro_rules! scope_enum {
+    ($($variant:ident => $scope:literal,)*) => {
+        /// The [authorization scopes](https://developer.spotify.com/documentation/web-api/concepts/scopes)
+        /// that may be requested to the user, like `user-read-playback-state` or
+        /// `playlist-modify-private`.
+        ///
+        /// They can be converted from and into strings, and every variant can be
+        /// obtained with [`IntoEnumIterator`](strum::IntoEnumIterator).
+        #[derive(
+            Clone,
+            Copy,
+            Debug,
+            Serialize,
+            Deserialize,
+            PartialEq,
+            Eq,
+            Hash,
+            Display,
+            EnumIter,
+            EnumString,
+            IntoStaticStr,
+        )]
+        pub enum Scope {
+            $(
+                #[serde(rename = $scope)]
+                #[strum(serialize = $scope)]
+                $variant,
+            )*
+        }
+    };
+}
+
+// The variants are generated from the same list the `scopes!` macro checks
+rspotify_macros::with_known_scopes!(scope_enum);
@@ -1,3 +1,7 @@
+// Both variants of `ApiError` have the `error` alias, which is only reachable
+// in the first one
+#![allow(unreachable_patterns)]
+
 use serde::Deserialize;
 use thiserror::Error;
 
@@ -31,4 +35,8 @@ pub enum ModelError {
 
     #[error("input/output error: {0}")]
     Io(#[from] std::io::Error),
+
+    /// Only returned with the `encrypted-cache` feature.
+    #[error("encrypted cache error: {0}")]
+    EncryptedCache(String),
 }
@@ -1,10 +1,11 @@
 use crate::{
-    auth_urls,
+    alphabets, auth_urls,
     clients::{BaseClient, OAuthClient},
+    generate_random_string,
     http::{Form, HttpClient},
     join_scopes, params,
     sync::Mutex,
-    ClientError, ClientResult, Config, Credentials, OAuth, Token,
+    AuthorizationRequest, ClientError, ClientResult, Config, Credentials, OAuth, Token,
 };
 
 use std::collections::HashMap;
@@ -69,6 +70,7 @@ pub struct AuthCodeSpotify {
     pub config: Config,
     pub token: Arc<Mutex<Option<Token>>>,
     pub(crate) http: HttpClient,
+    pub(crate) refresh_lock: Arc<Mutex<()>>,
 }
 
 /// This client has access to the base methods.
@@ -83,6 +85,10 @@ impl BaseClient for AuthCodeSpotify {
         Arc::clone(&self.token)
     }
 
+    fn get_refresh_lock(&self) -> Arc<Mutex<()>> {
+        Arc::clone(&self.refresh_lock)
+    }
+
     fn get_creds(&self) -> &Credentials {
         &self.creds
     }
@@ -94,34 +100,32 @@ impl BaseClient for AuthCodeSpotify {
     /// Refetch the current access token given a refresh token. May return
     /// `None` if there's no access/refresh token.
     async fn refetch_token(&self) -> ClientResult<Option<Token>> {
-        match self.token.lock().await.unwrap().as_ref() {
+        // The token isn't kept locked while it's being refetched, since the
+        // callbacks might access it.
+        let refresh_token = match self.token.lock().await.unwrap().as_ref() {
             Some(Token {
                 refresh_token: Some(refresh_token),
                 ..
-            }) => {
-                let mut data = Form::new();
-                data.insert(params::REFRESH_TOKEN, refresh_token);
-                data.insert(params::GRANT_TYPE, params::REFRESH_TOKEN);
-
-                let headers = self
-                    .creds
-                    .auth_headers()
-                    .expect("No client secret set in the credentials.");
-                let mut token = self.fetch_access_token(&data, Some(&headers)).await?;
-
-                token.refresh_token = Some(refresh_token.to_string());
-
-                if let Some(callback_fn) = &*self.get_config().token_callback_fn.clone() {
-                    callback_fn.0(token.clone())?;
-                }
-
-                Ok(Some(token))
-            }
+            }) => refresh_token.clone(),
             _ => {
                 log::warn!("Can not refresh token! Token missing!");
-                Err(ClientError::InvalidToken)
+                return Err(ClientError::InvalidToken);
             }
-        }
+        };
+
+        let mut data = Form::new();
+        data.insert(params::REFRESH_TOKEN, &refresh_token);
+        data.insert(params::GRANT_TYPE, params::REFRESH_TOKEN);
+
+        let headers = self
+            .creds
+            .auth_headers()
+            .expect("No client secret set in the credentials.");
+        let token = self
+            .fetch_refreshed_token(&refresh_token, &data, Some(&headers))
+            .await?;
+
+        Ok(Some(token))
     }
 }
 
@@ -137,31 +141,7 @@ impl OAuthClient for AuthCodeSpotify {
     /// Obtains a user access token given a code, as part of the OAuth
     /// authentication. The access token will be saved internally.
     async fn request_token(&self, code: &str) -> ClientResult<()> {
-        log::info!("Requesting Auth Code token");
-
-        let scopes = join_scopes(&self.oauth.scopes);
-
-        let mut data = Form::new();
-        data.insert(params::GRANT_TYPE, params::GRANT_TYPE_AUTH_CODE);
-        data.insert(params::REDIRECT_URI, &self.oauth.redirect_uri);
-        data.insert(params::CODE, code);
-        data.insert(params::SCOPE, &scopes);
-        data.insert(params::STATE, &self.oauth.state);
-
-        let headers = self
-            .creds
-            .auth_headers()
-            .expect("No client secret set in the credentials.");
-
-        let token = self.fetch_access_token(&data, Some(&headers)).await?;
-
-        if let Some(callback_fn) = &*self.get_config().token_callback_fn.clone() {
-            callback_fn.0(token.clone())?;
-        }
-
-        *self.token.lock().await.unwrap() = Some(token);
-
-        self.write_token_cache().await
+        self.request_token_with_state(code, &self.oauth.state).await
     }
 }
 
@@ -221,6 +201,36 @@ impl AuthCodeSpotify {
     /// Returns the URL needed to authorize the current client as the first step
     /// in the authorization flow.
     pub fn get_authorize_url(&self, show_dialog: bool) -> ClientResult<String> {
+        self.build_authorize_url(&self.oauth.state, show_dialog)
+    }
+
+    /// Starts a new authorization, returning its URL along with a newly
+    /// generated state, instead of the one in [`OAuth::state`]. It can be
+    /// stored and used later on to complete the flow with
+    /// [`Self::request_token_with`], even from a different process.
+    pub fn authorization_request(&self, show_dialog: bool) -> ClientResult<AuthorizationRequest> {
+        let state = generate_random_string(16, alphabets::ALPHANUM);
+        let url = self.build_authorize_url(&state, show_dialog)?;
+
+        Ok(AuthorizationRequest {
+            url,
+            state,
+            verifier: None,
+        })
+    }
+
+    /// Same as [`OAuthClient::request_token`], but for an authorization started
+    /// with [`Self::authorization_request`].
+    #[maybe_async]
+    pub async fn request_token_with(
+        &self,
+        code: &str,
+        request: &AuthorizationRequest,
+    ) -> ClientResult<()> {
+        self.request_token_with_state(code, &request.state).await
+    }
+
+    fn build_authorize_url(&self, state: &str, show_dialog: bool) -> ClientResult<String> {
         log::info!("Building auth URL");
 
         let scopes = join_scopes(&self.oauth.scopes);
@@ -230,7 +240,7 @@ impl AuthCodeSpotify {
         payload.insert(params::RESPONSE_TYPE, params::RESPONSE_TYPE_CODE);
         payload.insert(params::REDIRECT_URI, &self.oauth.redirect_uri);
         payload.insert(params::SCOPE, &scopes);
-        payload.insert(params::STATE, &self.oauth.state);
+        payload.insert(params::STATE, state);
 
         if show_dialog {
             payload.insert(params::SHOW_DIALOG, "true");
@@ -240,4 +250,31 @@ impl AuthCodeSpotify {
         let parsed = Url::parse_with_params(&request_url, payload)?;
         Ok(parsed.into())
     }
+
+    #[maybe_async]
+    async fn request_token_with_state(&self, code: &str, state: &str) -> ClientResult<()> {
+        log::info!("Requesting Auth Code token");
+
+        let scopes = join_scopes(&self.oauth.scopes);
+
+        let mut data = Form::new();
+        data.insert(params::GRANT_TYPE, params::GRANT_TYPE_AUTH_CODE);
+        data.insert(params::REDIRECT_URI, &self.oauth.redirect_uri);
+        data.insert(params::CODE, code);
+        data.insert(params::SCOPE, &scopes);
+        data.insert(params::STATE, state);
+
+        let headers = self
+            .creds
+            .auth_headers()
+            .expect("No client secret set in the credentials.");
+
+        let token = self.fetch_access_token(&data, Some(&headers)).await?;
+
+        self.run_token_callbacks(&token).await?;
+
+        *self.token.lock().await.unwrap() = Some(token);
+
+        self.write_token_cache().await
+    }
 }
@@ -5,7 +5,7 @@ use crate::{
     http::{Form, HttpClient},
     join_scopes, params,
     sync::Mutex,
-    ClientResult, Config, Credentials, OAuth, Token,
+    AuthError, AuthorizationRequest, ClientResult, Config, Credentials, OAuth, Token,
 };
 
 use base64::{engine::general_purpose, Engine as _};
@@ -41,6 +41,7 @@ pub struct AuthCodePkceSpotify {
     /// The code verifier for the authentication process
     pub verifier: Option<String>,
     pub(crate) http: HttpClient,
+    pub(crate) refresh_lock: Arc<Mutex<()>>,
 }
 
 /// This client has access to the base methods.
@@ -55,6 +56,10 @@ impl BaseClient for AuthCodePkceSpotify {
         Arc::clone(&self.token)
     }
 
+    fn get_refresh_lock(&self) -> Arc<Mutex<()>> {
+        Arc::clone(&self.refresh_lock)
+    }
+
     fn get_creds(&self) -> &Credentials {
         &self.creds
     }
@@ -64,26 +69,26 @@ impl BaseClient for AuthCodePkceSpotify {
     }
 
     async fn refetch_token(&self) -> ClientResult<Option<Token>> {
-        match self.token.lock().await.unwrap().as_ref() {
+        // The token isn't kept locked while it's being refetched, since the
+        // callbacks might access it.
+        let refresh_token = match self.token.lock().await.unwrap().as_ref() {
             Some(Token {
                 refresh_token: Some(refresh_token),
                 ..
-            }) => {
-                let mut data = Form::new();
-                data.insert(params::GRANT_TYPE, params::GRANT_TYPE_REFRESH_TOKEN);
-                data.insert(params::REFRESH_TOKEN, refresh_token);
-                data.insert(params::CLIENT_ID, &self.creds.id);
+            }) => refresh_token.clone(),
+            _ => return Ok(None),
+        };
 
-                let token = self.fetch_access_token(&data, None).await?;
+        let mut data = Form::new();
+        data.insert(params::GRANT_TYPE, params::GRANT_TYPE_REFRESH_TOKEN);
+        data.insert(params::REFRESH_TOKEN, &refresh_token);
+        data.insert(params::CLIENT_ID, &self.creds.id);
 
-                if let Some(callback_fn) = &*self.get_config().token_callback_fn.clone() {
-                    callback_fn.0(token.clone())?;
-                }
+        let token = self
+            .fetch_refreshed_token(&refresh_token, &data, None)
+            .await?;
 
-                Ok(Some(token))
-            }
-            _ => Ok(None),
-        }
+        Ok(Some(token))
     }
 }
 
@@ -100,30 +105,13 @@ impl OAuthClient for AuthCodePkceSpotify {
     /// or with [`Self::get_authorize_url`]. Otherwise, this function will
     /// panic.
     async fn request_token(&self, code: &str) -> ClientResult<()> {
-        log::info!("Requesting PKCE Auth Code token");
-
         let verifier = self.verifier.as_ref().expect(
             "Unknown code verifier. Try calling \
             `AuthCodePkceSpotify::get_authorize_url` first or setting it \
             yourself.",
         );
 
-        let mut data = Form::new();
-        data.insert(params::CLIENT_ID, &self.creds.id);
-        data.insert(params::GRANT_TYPE, params::GRANT_TYPE_AUTH_CODE);
-        data.insert(params::CODE, code);
-        data.insert(params::REDIRECT_URI, &self.oauth.redirect_uri);
-        data.insert(params::CODE_VERIFIER, verifier);
-
-        let token = self.fetch_access_token(&data, None).await?;
-
-        if let Some(callback_fn) = &*self.get_config().token_callback_fn.clone() {
-            callback_fn.0(token.clone())?;
-        }
-
-        *self.token.lock().await.unwrap() = Some(token);
-
-        self.write_token_cache().await
+        self.request_token_with_verifier(code, verifier).await
     }
 }
 
@@ -197,14 +185,61 @@ impl AuthCodePkceSpotify {
     /// [reference]: https://developer.spotify.com/documentation/general/guides/authorization/code-flow
     /// [rfce]: https://datatracker.ietf.org/doc/html/rfc7636#section-4.1
     pub fn get_authorize_url(&mut self, verifier_bytes: Option<usize>) -> ClientResult<String> {
-        log::info!("Building auth URL");
-
-        let scopes = join_scopes(&self.oauth.scopes);
         let verifier_bytes = verifier_bytes.unwrap_or(43);
         let (verifier, challenge) = Self::generate_codes(verifier_bytes);
         // The verifier will be needed later when requesting the token
         self.verifier = Some(verifier);
 
+        self.build_authorize_url(&self.oauth.state, &challenge)
+    }
+
+    /// Starts a new authorization, returning its URL along with a newly
+    /// generated state and code verifier. Unlike [`Self::get_authorize_url`],
+    /// the client isn't modified: the returned request can be stored and used
+    /// later on to complete the flow with [`Self::request_token_with`], even
+    /// from a different process.
+    ///
+    /// See [`Self::get_authorize_url`] for more information about
+    /// `verifier_bytes`.
+    pub fn authorization_request(
+        &self,
+        verifier_bytes: Option<usize>,
+    ) -> ClientResult<AuthorizationRequest> {
+        let verifier_bytes = verifier_bytes.unwrap_or(43);
+        let (verifier, challenge) = Self::generate_codes(verifier_bytes);
+        let state = generate_random_string(16, alphabets::ALPHANUM);
+        let url = self.build_authorize_url(&state, &challenge)?;
+
+        Ok(AuthorizationRequest {
+            url,
+            state,
+            verifier: Some(verifier),
+        })
+    }
+
+    /// Same as [`OAuthClient::request_token`], but for an authorization started
+    /// with [`Self::authorization_request`], whose code verifier is used. It
+    /// fails with [`AuthError::MissingVerifier`] if the request doesn't
+    /// include it.
+    #[maybe_async]
+    pub async fn request_token_with(
+        &self,
+        code: &str,
+        request: &AuthorizationRequest,
+    ) -> ClientResult<()> {
+        let verifier = request
+            .verifier
+            .as_ref()
+            .ok_or(AuthError::MissingVerifier)?;
+
+        self.request_token_with_verifier(code, verifier).await
+    }
+
+    fn build_authorize_url(&self, state: &str, challenge: &str) -> ClientResult<String> {
+        log::info!("Building auth URL");
+
+        let scopes = join_scopes(&self.oauth.scopes);
+
         let mut payload: HashMap<&str, &str> = HashMap::new();
         payload.insert(params::CLIENT_ID, &self.creds.id);
         payload.insert(params::RESPONSE_TYPE, params::RESPONSE_TYPE_CODE);
@@ -213,12 +248,32 @@ impl AuthCodePkceSpotify {
             params::CODE_CHALLENGE_METHOD,
             params::CODE_CHALLENGE_METHOD_S256,
         );
-        payload.insert(params::CODE_CHALLENGE, &challenge);
-        payload.insert(params::STATE, &self.oauth.state);
+        payload.insert(params::CODE_CHALLENGE, challenge);
+        payload.insert(params::STATE, state);
         payload.insert(params::SCOPE, &scopes);
 
         let request_url = self.auth_url(auth_urls::AUTHORIZE);
         let parsed = Url::parse_with_params(&request_url, payload)?;
         Ok(parsed.into())
     }
+
+    #[maybe_async]
+    async fn request_token_with_verifier(&self, code: &str, verifier: &str) -> ClientResult<()> {
+        log::info!("Requesting PKCE Auth Code token");
+
+        let mut data = Form::new();
+        data.insert(params::CLIENT_ID, &self.creds.id);
+        data.insert(params::GRANT_TYPE, params::GRANT_TYPE_AUTH_CODE);
+        data.insert(params::CODE, code);
+        data.insert(params::REDIRECT_URI, &self.oauth.redirect_uri);
+        data.insert(params::CODE_VERIFIER, verifier);
+
+        let token = self.fetch_access_token(&data, None).await?;
+
+        self.run_token_callbacks(&token).await?;
+
+        *self.token.lock().await.unwrap() = Some(token);
+
+        self.write_token_cache().await
+    }
 }
@@ -13,7 +13,9 @@ use std::sync::Arc;
 ///
 /// This is the most basic flow. It requests a token to Spotify given some
 /// client credentials, without user authorization. The only step to take is to
-/// call [`Self::request_token`]. See [this example][example-main].
+/// call [`Self::request_token`], or to enable [`Config::token_requesting`] so
+/// that it's done automatically before the first request. See [this
+/// example][example-main].
 ///
 /// Note: This flow does not include authorization and therefore cannot be used
 /// to access or to manage the endpoints related to user private data in
@@ -27,6 +29,7 @@ pub struct ClientCredsSpotify {
     pub creds: Credentials,
     pub token: Arc<Mutex<Option<Token>>>,
     pub(crate) http: HttpClient,
+    pub(crate) refresh_lock: Arc<Mutex<()>>,
 }
 
 /// This client has access to the base methods.
@@ -41,6 +44,10 @@ impl BaseClient for ClientCredsSpotify {
         Arc::clone(&self.token)
     }
 
+    fn get_refresh_lock(&self) -> Arc<Mutex<()>> {
+        Arc::clone(&self.refresh_lock)
+    }
+
     fn get_creds(&self) -> &Credentials {
         &self.creds
     }
@@ -49,6 +56,18 @@ impl BaseClient for ClientCredsSpotify {
         &self.config
     }
 
+    /// With [`Config::token_requesting`] enabled, a token is also obtained if
+    /// there isn't one yet.
+    async fn auto_reauth(&self) -> ClientResult<()> {
+        if self.config.token_requesting {
+            self.request_token_if_missing().await?;
+        } else if !self.config.token_refreshing {
+            return Ok(());
+        }
+
+        self.refresh_token_if_expiring().await
+    }
+
     /// Note that refetching a token in the Client Credentials flow is
     /// equivalent to requesting a token from scratch, since there's no refresh
     /// token available.
@@ -91,22 +110,29 @@ impl ClientCredsSpotify {
         }
     }
 
-    /// Tries to read the cache file's token.
+    /// Tries to read the token from the configured token store.
     ///
-    /// This will return an error if the token couldn't be read (e.g. it's not
-    /// available or the JSON is malformed). It may return `Ok(None)` if:
+    /// This will return an error if the token couldn't be read (e.g. the JSON
+    /// is malformed). It may return `Ok(None)` if:
     ///
     /// * The read token is expired
+    /// * There's no token in the store
     /// * The cached token is disabled in the config
     #[maybe_async]
     pub async fn read_token_cache(&self) -> ClientResult<Option<Token>> {
-        if !self.get_config().token_cached {
-            log::info!("Token cache read ignored (not configured)");
-            return Ok(None);
-        }
+        let store = match self.get_config().get_token_store() {
+            Some(store) => store,
+            None => {
+                log::info!("Token cache read ignored (not configured)");
+                return Ok(None);
+            }
+        };
 
         log::info!("Reading token cache");
-        let token = Token::from_cache(&self.get_config().cache_path)?;
+        let token = match store.load().await? {
+            Some(token) => token,
+            None => return Ok(None),
+        };
         if token.is_expired() {
             // Invalid token, since it's expired.
             Ok(None)
@@ -115,6 +141,31 @@ impl ClientCredsSpotify {
         }
     }
 
+    /// Obtains a token if there isn't one yet, reading it from the token
+    /// store if possible. Just like refreshes, concurrent calls are
+    /// deduplicated, so only one token is requested.
+    #[maybe_async]
+    async fn request_token_if_missing(&self) -> ClientResult<()> {
+        if self.token.lock().await.unwrap().is_some() {
+            return Ok(());
+        }
+
+        let lock = self.get_refresh_lock();
+        let _refreshing = lock.lock().await.unwrap();
+
+        // The token may have been obtained while waiting for the lock
+        if self.token.lock().await.unwrap().is_some() {
+            return Ok(());
+        }
+
+        if let Some(token) = self.read_token_cache().await? {
+            *self.token.lock().await.unwrap() = Some(token);
+            return Ok(());
+        }
+
+        self.request_token().await
+    }
+
     /// Fetch access token
     #[maybe_async]
     async fn fetch_token(&self) -> ClientResult<Token> {
@@ -128,9 +179,7 @@ impl ClientCredsSpotify {
 
         let token = self.fetch_access_token(&data, Some(&headers)).await?;
 
-        if let Some(callback_fn) = &*self.get_config().token_callback_fn.clone() {
-            callback_fn.0(token.clone())?;
-        }
+        self.run_token_callbacks(&token).await?;
 
         Ok(token)
     }
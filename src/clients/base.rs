@@ -1,22 +1,40 @@
 use crate::{
     auth_urls,
     clients::{
-        convert_result,
-        pagination::{paginate, paginate_with_ctx, Paginator},
+        append_query, convert_response, convert_result,
+        pagination::{
+            paginate_pages, paginate_pages_with_ctx, paginate_prefetch_with_ctx,
+            paginate_resumable, PaginationState, Paginator, PrefetchTask, ResumablePaginator,
+        },
     },
-    http::{BaseHttpClient, Form, Headers, HttpClient, Query},
+    http::{BaseHttpClient, Form, Headers, HttpClient, Method, Query},
     join_ids,
     model::*,
-    sync::Mutex,
+    params,
+    sync::{sleep, Mutex},
     util::build_map,
-    ClientError, ClientResult, Config, Credentials, Token,
+    AuthError, ClientError, ClientResult, Config, Credentials, Token,
 };
 
-use std::{collections::HashMap, fmt, ops::Not, sync::Arc};
+use std::{collections::HashMap, fmt, ops::Not, sync::Arc, time::Duration};
 
-use chrono::Utc;
+use chrono::{TimeDelta, Utc};
 use maybe_async::maybe_async;
-use serde_json::Value;
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+
+/// The fields that endpoints like `search` wrap their pages in.
+const PAGE_WRAPPERS: &[&str] = &[
+    "albums",
+    "artists",
+    "audiobooks",
+    "categories",
+    "chapters",
+    "episodes",
+    "playlists",
+    "shows",
+    "tracks",
+];
 
 /// This trait implements the basic endpoints from the Spotify API that may be
 /// accessed without user authorization, including parts of the authentication
@@ -36,6 +54,17 @@ where
     /// be mutable (the token is accessed to from every endpoint).
     fn get_token(&self) -> Arc<Mutex<Option<Token>>>;
 
+    /// The lock held while the token is being refreshed automatically, so
+    /// that concurrent requests wait for a single refresh instead of each one
+    /// requesting a new token. It must always return the same lock, shared by
+    /// the clones of the client just like the token; otherwise, the refreshes
+    /// aren't deduplicated.
+    ///
+    /// Note that the token callbacks are invoked while this lock is held, so
+    /// a callback that sends a request with the same client during an
+    /// automatic refresh will deadlock.
+    fn get_refresh_lock(&self) -> Arc<Mutex<()>>;
+
     /// Returns the absolute URL for an endpoint in the API.
     fn api_url(&self, url: &str) -> String {
         let mut base = self.get_config().api_base_url.clone();
@@ -64,23 +93,74 @@ where
             return Ok(());
         }
 
+        self.refresh_token_if_expiring().await
+    }
+
+    /// Returns whether there's a token and it expires within
+    /// [`Config::token_refresh_margin`].
+    #[doc(hidden)]
+    async fn is_token_expiring(&self) -> bool {
+        let margin = TimeDelta::from_std(self.get_config().token_refresh_margin).ok();
+        match (self.get_token().lock().await.unwrap().as_ref(), margin) {
+            (Some(token), Some(margin)) => token.expires_within(margin),
+            (Some(_), None) => true,
+            (None, _) => false,
+        }
+    }
+
+    /// Refreshes the token if it expires within
+    /// [`Config::token_refresh_margin`]. Concurrent calls are deduplicated:
+    /// only one of them refreshes the token, and the rest wait for it to
+    /// finish.
+    async fn refresh_token_if_expiring(&self) -> ClientResult<()> {
         // NOTE: It's important to not leave the token locked, or else a
         // deadlock when calling `refresh_token` will occur.
-        let should_reauth = self
-            .get_token()
-            .lock()
-            .await
-            .unwrap()
-            .as_ref()
-            .map_or(false, Token::is_expired);
+        if !self.is_token_expiring().await {
+            return Ok(());
+        }
+
+        let lock = self.get_refresh_lock();
+        let _refreshing = lock.lock().await.unwrap();
 
-        if should_reauth {
+        // The token may have been refreshed while waiting for the lock
+        if self.is_token_expiring().await {
             self.refresh_token().await
         } else {
             Ok(())
         }
     }
 
+    /// Refreshes the token [`Config::token_refresh_margin`] before it expires,
+    /// over and over, so that requests don't have to wait for it. It only
+    /// returns when there's no token to refresh or when refreshing it fails.
+    ///
+    /// The client never runs it on its own: it has to be spawned in the
+    /// background on a clone of the client, e.g. with `tokio::spawn` or
+    /// `std::thread::spawn`, since it shares the token with the original one.
+    async fn keep_token_fresh(&self) -> ClientResult<()> {
+        loop {
+            let expires_at = match self.get_token().lock().await.unwrap().as_ref() {
+                Some(Token {
+                    expires_at: Some(expires_at),
+                    ..
+                }) => *expires_at,
+                _ => return Ok(()),
+            };
+
+            let margin = TimeDelta::from_std(self.get_config().token_refresh_margin)
+                .unwrap_or_else(|_| TimeDelta::zero());
+            // Waiting at least a second in case Spotify returns tokens that
+            // expire within the margin, to avoid refreshing them in a loop
+            let wait = (expires_at - margin - Utc::now())
+                .to_std()
+                .unwrap_or_default()
+                .max(Duration::from_secs(1));
+            sleep(wait).await;
+
+            self.refresh_token_if_expiring().await?;
+        }
+    }
+
     /// Refreshes the current access token given a refresh token. The obtained
     /// token will be saved internally.
     async fn refresh_token(&self) -> ClientResult<()> {
@@ -107,17 +187,94 @@ where
             .auth_headers())
     }
 
+    /// Refreshes the token after a request with the given headers was
+    /// rejected with `401 Unauthorized`, e.g. because it was revoked or the
+    /// local clock is skewed, and returns the headers with the new token.
+    ///
+    /// The token is only refreshed if it's still the rejected one, since
+    /// concurrent requests may have already refreshed it.
+    #[doc(hidden)]
+    async fn reauth_headers(&self, rejected: &Headers) -> ClientResult<Headers> {
+        let lock = self.get_refresh_lock();
+        let _refreshing = lock.lock().await.unwrap();
+
+        let current = self
+            .get_token()
+            .lock()
+            .await
+            .unwrap()
+            .as_ref()
+            .map(Token::auth_headers);
+        if current.as_ref() == Some(rejected) {
+            // NOTE: the token can't be locked at this point, since
+            // `refetch_token` locks it as well.
+            let token = self
+                .refetch_token()
+                .await?
+                .ok_or(ClientError::InvalidToken)?;
+            let headers = token.auth_headers();
+            *self.get_token().lock().await.unwrap() = Some(token);
+            self.write_token_cache().await?;
+            Ok(headers)
+        } else {
+            current.ok_or(ClientError::InvalidToken)
+        }
+    }
+
     // HTTP-related methods for the Spotify client. They wrap up the basic HTTP
     // client with its specific usage for endpoints or authentication.
 
+    /// Sends an authenticated request to an absolute URL. If it's rejected
+    /// with `401 Unauthorized` and either [`Config::token_refreshing`] or
+    /// [`Config::token_requesting`] is enabled, the token is refreshed and the request is retried once, failing with
+    /// [`ClientError::InvalidToken`] if it's rejected again.
+    ///
+    /// The query is only used for GET requests, and the body for the rest.
+    #[doc(hidden)]
+    async fn send_authenticated(
+        &self,
+        method: Method,
+        url: &str,
+        query: &Query<'_>,
+        body: &Value,
+    ) -> ClientResult<String> {
+        let http = self.get_http();
+        let headers = self.auth_headers().await?;
+        let result = match method {
+            Method::Get => http.get(url, Some(&headers), query).await,
+            Method::Post => http.post(url, Some(&headers), body).await,
+            Method::Put => http.put(url, Some(&headers), body).await,
+            Method::Delete => http.delete(url, Some(&headers), body).await,
+        };
+        let config = self.get_config();
+        let reauth = config.token_refreshing || config.token_requesting;
+        match result {
+            Err(err) if err.status_code() == Some(401) && reauth => {}
+            result => return Ok(result?),
+        }
+
+        log::info!("The token was rejected, refreshing it and retrying the request");
+        let headers = self.reauth_headers(&headers).await?;
+        let result = match method {
+            Method::Get => http.get(url, Some(&headers), query).await,
+            Method::Post => http.post(url, Some(&headers), body).await,
+            Method::Put => http.put(url, Some(&headers), body).await,
+            Method::Delete => http.delete(url, Some(&headers), body).await,
+        };
+        match result {
+            Err(err) if err.status_code() == Some(401) => Err(ClientError::InvalidToken),
+            result => Ok(result?),
+        }
+    }
+
     /// Convenience method to send GET requests related to an endpoint in the
     /// API.
     #[doc(hidden)]
     #[inline]
     async fn api_get(&self, url: &str, payload: &Query<'_>) -> ClientResult<String> {
         let url = self.api_url(url);
-        let headers = self.auth_headers().await?;
-        Ok(self.get_http().get(&url, Some(&headers), payload).await?)
+        self.send_authenticated(Method::Get, &url, payload, &Value::Null)
+            .await
     }
 
     /// Convenience method to send POST requests related to an endpoint in the
@@ -126,8 +283,8 @@ where
     #[inline]
     async fn api_post(&self, url: &str, payload: &Value) -> ClientResult<String> {
         let url = self.api_url(url);
-        let headers = self.auth_headers().await?;
-        Ok(self.get_http().post(&url, Some(&headers), payload).await?)
+        self.send_authenticated(Method::Post, &url, &Query::new(), payload)
+            .await
     }
 
     /// Convenience method to send PUT requests related to an endpoint in the
@@ -136,8 +293,8 @@ where
     #[inline]
     async fn api_put(&self, url: &str, payload: &Value) -> ClientResult<String> {
         let url = self.api_url(url);
-        let headers = self.auth_headers().await?;
-        Ok(self.get_http().put(&url, Some(&headers), payload).await?)
+        self.send_authenticated(Method::Put, &url, &Query::new(), payload)
+            .await
     }
 
     /// Convenience method to send DELETE requests related to an endpoint in the
@@ -146,11 +303,119 @@ where
     #[inline]
     async fn api_delete(&self, url: &str, payload: &Value) -> ClientResult<String> {
         let url = self.api_url(url);
-        let headers = self.auth_headers().await?;
-        Ok(self
-            .get_http()
-            .delete(&url, Some(&headers), payload)
-            .await?)
+        self.send_authenticated(Method::Delete, &url, &Query::new(), payload)
+            .await
+    }
+
+    /// Sends an authenticated request to any endpoint in the API and
+    /// deserializes its response into `T`.
+    ///
+    /// This is useful for endpoints that aren't wrapped by RSpotify yet, since
+    /// it reuses the client's authentication and automatic token refreshing.
+    ///
+    /// Parameters:
+    /// - method - the HTTP method of the request
+    /// - path - the endpoint's path, relative to [`Config::api_base_url`]
+    /// - query - the query parameters, which may be empty
+    /// - body - the JSON body of the request, ignored for GET requests
+    ///
+    /// Empty responses, like `204 No Content`, are deserialized as a JSON
+    /// `null`, so `()` or `Option<T>` should be used for these endpoints.
+    /// Unsuccessful status codes are returned as [`ClientError::Http`], just
+    /// like with the rest of the endpoints.
+    ///
+    /// [`Config::api_base_url`]: crate::Config::api_base_url
+    async fn request<T: DeserializeOwned>(
+        &self,
+        method: Method,
+        path: &str,
+        query: &Query<'_>,
+        body: Option<&Value>,
+    ) -> ClientResult<T> {
+        let empty_body = json!({});
+        let body = body.unwrap_or(&empty_body);
+
+        // The query is already handled by the HTTP client for GET requests,
+        // but it has to be appended to the path for the rest.
+        let result = match method {
+            Method::Get => self.api_get(path, query).await?,
+            Method::Post => self.api_post(&append_query(path, query), body).await?,
+            Method::Put => self.api_put(&append_query(path, query), body).await?,
+            Method::Delete => self.api_delete(&append_query(path, query), body).await?,
+        };
+
+        convert_response(&result)
+    }
+
+    /// Convenience method to follow the absolute `next` or `previous` URLs of
+    /// a page. Returns `None` if there's no URL to follow.
+    ///
+    /// Since the token is sent along, URLs outside of
+    /// [`Config::api_base_url`] are rejected with [`ClientError::ForeignUrl`].
+    #[doc(hidden)]
+    async fn follow_page_url<P: DeserializeOwned>(
+        &self,
+        url: Option<&str>,
+    ) -> ClientResult<Option<P>> {
+        let url = match url {
+            Some(url) => url,
+            None => return Ok(None),
+        };
+        if !url.starts_with(&self.api_url("")) {
+            return Err(ClientError::ForeignUrl(url.to_owned()));
+        }
+
+        let result = self
+            .send_authenticated(Method::Get, url, &Query::new(), &Value::Null)
+            .await?;
+
+        // Some endpoints like `search` or `current_user_followed_artists` wrap
+        // the page inside an object with a single field, and so do their
+        // `next` URLs.
+        let mut value = convert_result::<Value>(&result)?;
+        if let Value::Object(map) = &mut value {
+            let wrapper = PAGE_WRAPPERS.iter().find(|key| map.contains_key(**key));
+            if let (1, Some(wrapper)) = (map.len(), wrapper) {
+                value = map[*wrapper].take();
+            }
+        }
+
+        serde_json::from_value(value).map(Some).map_err(Into::into)
+    }
+
+    /// Fetches the page after the given one by following its `next` URL, so
+    /// that any extra state included in it by Spotify is preserved. Returns
+    /// `None` if it's the last page.
+    ///
+    /// This also works for the pages nested in other objects, like
+    /// [`FullPlaylist::tracks`] or [`FullAlbum::tracks`].
+    async fn next_page<T: DeserializeOwned + Sync>(
+        &self,
+        page: &Page<T>,
+    ) -> ClientResult<Option<Page<T>>> {
+        self.follow_page_url(page.next.as_deref()).await
+    }
+
+    /// Fetches the page before the given one by following its `previous` URL.
+    /// Returns `None` if it's the first page.
+    ///
+    /// See [`Self::next_page`] for more information.
+    async fn previous_page<T: DeserializeOwned + Sync>(
+        &self,
+        page: &Page<T>,
+    ) -> ClientResult<Option<Page<T>>> {
+        self.follow_page_url(page.previous.as_deref()).await
+    }
+
+    /// Same as [`Self::next_page`], but for cursor-based pages like the ones
+    /// in [`OAuthClient::current_user_followed_artists`].
+    ///
+    /// [`OAuthClient::current_user_followed_artists`]: crate::clients::OAuthClient::current_user_followed_artists
+    async fn next_cursor_page<T: DeserializeOwned + Sync>(
+        &self,
+        page: &CursorBasedPage<T>,
+    ) -> ClientResult<Option<CursorBasedPage<T>>> {
+        self.follow_page_url(page.next.as_deref()).await
     }
 
     /// Convenience method to send POST requests related to the authentication
@@ -167,20 +432,86 @@ where
         Ok(self.get_http().post_form(&url, headers, payload).await?)
     }
 
-    /// Updates the cache file at the internal cache path.
+    /// Obtains a new access token with the given refresh token, which is
+    /// included in `payload` as well.
     ///
-    /// This should be used whenever it's possible to, even if the cached token
-    /// isn't configured, because this will already check `Config::token_cached`
-    /// and do nothing in that case already.
-    async fn write_token_cache(&self) -> ClientResult<()> {
-        if !self.get_config().token_cached {
-            log::info!("Token cache write ignored (not configured)");
-            return Ok(());
+    /// Spotify may or may not return a new refresh token. If it does, the
+    /// previous one is replaced and [`Config::refresh_token_callback_fn`] is
+    /// invoked, since the previous one may not work anymore. Errors in that
+    /// callback, and in the token callbacks after a rotation, are logged
+    /// without failing the refresh, so that the rotated refresh token isn't
+    /// lost. Otherwise, the previous one is kept.
+    #[doc(hidden)]
+    async fn fetch_refreshed_token(
+        &self,
+        refresh_token: &str,
+        payload: &Form<'_>,
+        headers: Option<&Headers>,
+    ) -> ClientResult<Token> {
+        let mut token = self.fetch_access_token(payload, headers).await?;
+
+        let rotated = matches!(&token.refresh_token, Some(new) if new != refresh_token);
+        match &token.refresh_token {
+            Some(new_refresh_token) if new_refresh_token != refresh_token => {
+                log::info!("The refresh token has been rotated");
+                // Failing here would discard the rotated refresh token, and
+                // the previous one may not work anymore, so the error is only
+                // logged.
+                if let Some(callback_fn) = &*self.get_config().refresh_token_callback_fn {
+                    if let Err(err) =
+                        callback_fn.0(refresh_token.to_owned(), new_refresh_token.clone())
+                    {
+                        log::error!("The refresh token callback failed: {}", err);
+                    }
+                }
+            }
+            Some(_) => (),
+            None => token.refresh_token = Some(refresh_token.to_owned()),
+        }
+
+        match self.run_token_callbacks(&token).await {
+            Err(err) if rotated => log::error!("The token callback failed: {}", err),
+            result => result?,
+        }
+        Ok(token)
+    }
+
+    /// Invokes the callback configured in [`Config::token_callback_fn`], and
+    /// its asynchronous counterpart, with a newly obtained token.
+    #[doc(hidden)]
+    async fn run_token_callbacks(&self, token: &Token) -> ClientResult<()> {
+        let config = self.get_config();
+        if let Some(callback_fn) = &*config.token_callback_fn {
+            callback_fn.0(token.clone())?;
         }
 
+        #[cfg(feature = "__async")]
+        if let Some(callback_fn) = &*config.async_token_callback_fn {
+            callback_fn.0(token.clone()).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Saves the current token into the configured token store.
+    ///
+    /// This should be used whenever it's possible to, even if the token store
+    /// isn't configured, because this will already check
+    /// [`Config::get_token_store`] and do nothing in that case already.
+    async fn write_token_cache(&self) -> ClientResult<()> {
+        let store = match self.get_config().get_token_store() {
+            Some(store) => store,
+            None => {
+                log::info!("Token cache write ignored (not configured)");
+                return Ok(());
+            }
+        };
+
         log::info!("Writing token cache");
-        if let Some(tok) = self.get_token().lock().await.unwrap().as_ref() {
-            tok.write_cache(&self.get_config().cache_path)?;
+        // The token is cloned so that it isn't locked while it's being saved
+        let token = self.get_token().lock().await.unwrap().clone();
+        if let Some(token) = token {
+            store.save(&token).await?;
         }
 
         Ok(())
@@ -192,7 +523,16 @@ where
         payload: &Form<'_>,
         headers: Option<&Headers>,
     ) -> ClientResult<Token> {
-        let response = self.auth_post(auth_urls::TOKEN, headers, payload).await?;
+        let response = match self.auth_post(auth_urls::TOKEN, headers, payload).await {
+            Ok(response) => response,
+            // The token endpoint describes the OAuth errors in the body
+            Err(ClientError::Http(err)) if matches!(err.status_code(), Some(400 | 401)) => {
+                let body = err.into_response_text().await.unwrap_or_default();
+                let grant_type = payload.get(params::GRANT_TYPE).copied();
+                return Err(AuthError::from_token_response(&body, grant_type).into());
+            }
+            Err(err) => return Err(err),
+        };
 
         let mut tok = serde_json::from_str::<Token>(&response)?;
         tok.expires_at = Utc::now().checked_add_signed(tok.expires_in);
@@ -282,10 +622,22 @@ where
     fn artist_albums<'a>(
         &'a self,
         artist_id: ArtistId<'a>,
-        include_groups: impl IntoIterator<Item = AlbumType> + Send + Copy + 'a,
+        include_groups: impl IntoIterator<Item = AlbumType> + Send + Sync + Copy + 'a,
         market: Option<Market>,
-    ) -> Paginator<'_, ClientResult<SimplifiedAlbum>> {
-        paginate_with_ctx(
+    ) -> Paginator<'a, ClientResult<SimplifiedAlbum>> {
+        self.artist_albums_pages(artist_id, include_groups, market)
+            .into_items()
+    }
+
+    /// The page-level version of [`Self::artist_albums`], which yields the
+    /// whole pages, including metadata like their `total`.
+    fn artist_albums_pages<'a>(
+        &'a self,
+        artist_id: ArtistId<'a>,
+        include_groups: impl IntoIterator<Item = AlbumType> + Send + Sync + Copy + 'a,
+        market: Option<Market>,
+    ) -> Paginator<'a, ClientResult<Page<SimplifiedAlbum>>> {
+        paginate_pages_with_ctx(
             (self, artist_id),
             move |(slf, artist_id), limit, offset| {
                 slf.artist_albums_manual(
@@ -298,6 +650,7 @@ where
             },
             self.get_config().pagination_chunks,
         )
+        .max_page_size(50)
     }
 
     /// The manually paginated version of [`Self::artist_albums`].
@@ -318,8 +671,7 @@ where
         let include_groups_opt = include_groups_vec
             .is_empty()
             .not()
-            .then_some(include_groups_vec)
-            .map(|t| t.join(","));
+            .then(|| include_groups_vec.join(","));
 
         let params = build_map([
             ("include_groups", include_groups_opt.as_deref()),
@@ -461,14 +813,25 @@ where
         &'a self,
         album_id: AlbumId<'a>,
         market: Option<Market>,
-    ) -> Paginator<'_, ClientResult<SimplifiedTrack>> {
-        paginate_with_ctx(
+    ) -> Paginator<'a, ClientResult<SimplifiedTrack>> {
+        self.album_track_pages(album_id, market).into_items()
+    }
+
+    /// The page-level version of [`Self::album_track`], which yields the whole
+    /// pages, including metadata like their `total`.
+    fn album_track_pages<'a>(
+        &'a self,
+        album_id: AlbumId<'a>,
+        market: Option<Market>,
+    ) -> Paginator<'a, ClientResult<Page<SimplifiedTrack>>> {
+        paginate_pages_with_ctx(
             (self, album_id),
             move |(slf, album_id), limit, offset| {
                 slf.album_track_manual(album_id.as_ref(), market, Some(limit), Some(offset))
             },
             self.get_config().pagination_chunks,
         )
+        .max_page_size(50)
     }
 
     /// The manually paginated version of [`Self::album_track`].
@@ -630,14 +993,25 @@ where
         &'a self,
         id: ShowId<'a>,
         market: Option<Market>,
-    ) -> Paginator<'_, ClientResult<SimplifiedEpisode>> {
-        paginate_with_ctx(
+    ) -> Paginator<'a, ClientResult<SimplifiedEpisode>> {
+        self.get_shows_episodes_pages(id, market).into_items()
+    }
+
+    /// The page-level version of [`Self::get_shows_episodes`], which yields the
+    /// whole pages, including metadata like their `total`.
+    fn get_shows_episodes_pages<'a>(
+        &'a self,
+        id: ShowId<'a>,
+        market: Option<Market>,
+    ) -> Paginator<'a, ClientResult<Page<SimplifiedEpisode>>> {
+        paginate_pages_with_ctx(
             (self, id),
             move |(slf, id), limit, offset| {
                 slf.get_shows_episodes_manual(id.as_ref(), market, Some(limit), Some(offset))
             },
             self.get_config().pagination_chunks,
         )
+        .max_page_size(50)
     }
 
     /// The manually paginated version of [`Self::get_shows_episodes`].
@@ -767,11 +1141,22 @@ where
         &'a self,
         locale: Option<&'a str>,
         country: Option<Market>,
-    ) -> Paginator<'_, ClientResult<Category>> {
-        paginate(
+    ) -> Paginator<'a, ClientResult<Category>> {
+        self.categories_pages(locale, country).into_items()
+    }
+
+    /// The page-level version of [`Self::categories`], which yields the whole
+    /// pages, including metadata like their `total`.
+    fn categories_pages<'a>(
+        &'a self,
+        locale: Option<&'a str>,
+        country: Option<Market>,
+    ) -> Paginator<'a, ClientResult<Page<Category>>> {
+        paginate_pages(
             move |limit, offset| self.categories_manual(locale, country, Some(limit), Some(offset)),
             self.get_config().pagination_chunks,
         )
+        .max_page_size(50)
     }
 
     /// The manually paginated version of [`Self::categories`].
@@ -812,13 +1197,25 @@ where
         &'a self,
         category_id: &'a str,
         country: Option<Market>,
-    ) -> Paginator<'_, ClientResult<SimplifiedPlaylist>> {
-        paginate(
+    ) -> Paginator<'a, ClientResult<SimplifiedPlaylist>> {
+        self.category_playlists_pages(category_id, country)
+            .into_items()
+    }
+
+    /// The page-level version of [`Self::category_playlists`], which yields the
+    /// whole pages, including metadata like their `total`.
+    fn category_playlists_pages<'a>(
+        &'a self,
+        category_id: &'a str,
+        country: Option<Market>,
+    ) -> Paginator<'a, ClientResult<Page<SimplifiedPlaylist>>> {
+        paginate_pages(
             move |limit, offset| {
                 self.category_playlists_manual(category_id, country, Some(limit), Some(offset))
             },
             self.get_config().pagination_chunks,
         )
+        .max_page_size(50)
     }
 
     /// The manually paginated version of [`Self::category_playlists`].
@@ -899,10 +1296,20 @@ where
         &self,
         country: Option<Market>,
     ) -> Paginator<'_, ClientResult<SimplifiedAlbum>> {
-        paginate(
+        self.new_releases_pages(country).into_items()
+    }
+
+    /// The page-level version of [`Self::new_releases`], which yields the whole
+    /// pages, including metadata like their `total`.
+    fn new_releases_pages(
+        &self,
+        country: Option<Market>,
+    ) -> Paginator<'_, ClientResult<Page<SimplifiedAlbum>>> {
+        paginate_pages(
             move |limit, offset| self.new_releases_manual(country, Some(limit), Some(offset)),
             self.get_config().pagination_chunks,
         )
+        .max_page_size(50)
     }
 
     /// The manually paginated version of [`Self::new_releases`].
@@ -997,8 +1404,20 @@ where
         playlist_id: PlaylistId<'a>,
         fields: Option<&'a str>,
         market: Option<Market>,
-    ) -> Paginator<'_, ClientResult<PlaylistItem>> {
-        paginate_with_ctx(
+    ) -> Paginator<'a, ClientResult<PlaylistItem>> {
+        self.playlist_items_pages(playlist_id, fields, market)
+            .into_items()
+    }
+
+    /// The page-level version of [`Self::playlist_items`], which yields the
+    /// whole pages, including metadata like their `total`.
+    fn playlist_items_pages<'a>(
+        &'a self,
+        playlist_id: PlaylistId<'a>,
+        fields: Option<&'a str>,
+        market: Option<Market>,
+    ) -> Paginator<'a, ClientResult<Page<PlaylistItem>>> {
+        paginate_pages_with_ctx(
             (self, playlist_id, fields),
             move |(slf, playlist_id, fields), limit, offset| {
                 slf.playlist_items_manual(
@@ -1011,6 +1430,66 @@ where
             },
             self.get_config().pagination_chunks,
         )
+        .max_page_size(100)
+    }
+
+    /// Same as [`Self::playlist_items`], but the next page is requested in the
+    /// background while the current one is consumed, as explained in
+    /// [`paginate_prefetch`](crate::clients::pagination::paginate_prefetch).
+    /// The client is cloned into the task given to `spawn`.
+    fn playlist_items_prefetch<Spawn>(
+        &self,
+        playlist_id: PlaylistId<'static>,
+        market: Option<Market>,
+        spawn: Spawn,
+    ) -> Paginator<'static, ClientResult<PlaylistItem>>
+    where
+        Self: 'static,
+        Spawn: 'static + FnOnce(PrefetchTask) + Send,
+    {
+        paginate_prefetch_with_ctx(
+            (self.clone(), playlist_id),
+            move |(slf, playlist_id), limit, offset| {
+                slf.playlist_items_manual(
+                    playlist_id.as_ref(),
+                    None,
+                    market,
+                    Some(limit),
+                    Some(offset),
+                )
+            },
+            self.get_config().pagination_chunks,
+            spawn,
+        )
+        .max_page_size(100)
+    }
+
+    /// The resumable version of [`Self::playlist_items`], which yields every
+    /// page along with the state to continue after it, as explained in
+    /// [`paginate_resumable`](crate::clients::pagination::paginate_resumable).
+    ///
+    /// The playlist and the market are kept in the state, so a new pagination
+    /// starts with `PaginationState::new((playlist_id, market))`.
+    fn playlist_items_resumable(
+        &self,
+        state: PaginationState<(PlaylistId<'static>, Option<Market>)>,
+        max_retries: u32,
+    ) -> ResumablePaginator<'_, Page<PlaylistItem>, (PlaylistId<'static>, Option<Market>)> {
+        paginate_resumable(
+            state,
+            move |(playlist_id, market), limit, position| {
+                self.playlist_items_manual(
+                    playlist_id,
+                    None,
+                    market,
+                    Some(limit),
+                    position.offset(),
+                )
+            },
+            self.get_config().pagination_chunks,
+            max_retries,
+        )
+        .max_page_size(100)
     }
 
     /// The manually paginated version of [`Self::playlist_items`].
@@ -1050,14 +1529,24 @@ where
     fn user_playlists<'a>(
         &'a self,
         user_id: UserId<'a>,
-    ) -> Paginator<'_, ClientResult<SimplifiedPlaylist>> {
-        paginate_with_ctx(
+    ) -> Paginator<'a, ClientResult<SimplifiedPlaylist>> {
+        self.user_playlists_pages(user_id).into_items()
+    }
+
+    /// The page-level version of [`Self::user_playlists`], which yields the
+    /// whole pages, including metadata like their `total`.
+    fn user_playlists_pages<'a>(
+        &'a self,
+        user_id: UserId<'a>,
+    ) -> Paginator<'a, ClientResult<Page<SimplifiedPlaylist>>> {
+        paginate_pages_with_ctx(
             (self, user_id),
             move |(slf, user_id), limit, offset| {
                 slf.user_playlists_manual(user_id.as_ref(), Some(limit), Some(offset))
             },
             self.get_config().pagination_chunks,
         )
+        .max_page_size(50)
     }
 
     /// The manually paginated version of [`Self::user_playlists`].
@@ -0,0 +1,296 @@
+//! One-shot HTTP listener used to capture the authorization code when the
+//! redirect URI points to the local machine, so that the user doesn't have to
+//! copy it manually.
+
+use crate::{AuthError, ClientError, ClientResult};
+
+use std::{
+    io::{self, BufRead, BufReader, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+    time::{Duration, Instant},
+};
+
+use maybe_async::maybe_async;
+use url::{Host, Url};
+
+/// How often the listener checks for new connections.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// How long a connection may take to send its request. Browsers may open
+/// connections in advance that never send anything, which would otherwise
+/// keep the listener from accepting the actual redirect.
+const CONNECTION_TIMEOUT: Duration = Duration::from_secs(1);
+
+const SUCCESS_PAGE: &str = "<!DOCTYPE html><html><body><h1>Authentication finished</h1>\
+                            <p>You can close this tab and go back to the terminal.</p>\
+                            </body></html>";
+const FAILURE_PAGE: &str = "<!DOCTYPE html><html><body><h1>Authentication failed</h1>\
+                            <p>Please go back to the terminal for more information.</p>\
+                            </body></html>";
+
+/// Listens on the address of a loopback redirect URI, like
+/// `http://127.0.0.1:8888/callback` or `http://localhost:8888/callback`.
+///
+/// Since `localhost` may resolve to either `127.0.0.1` or `[::1]`, both are
+/// listened on in that case, as long as IPv6 is available.
+pub(crate) struct RedirectListener {
+    listeners: Vec<TcpListener>,
+    redirect_uri: Url,
+}
+
+impl RedirectListener {
+    /// Starts listening on the address of the redirect URI. Returns `None` if
+    /// it doesn't point to the loopback interface over plain HTTP.
+    pub fn bind(redirect_uri: &str) -> Option<io::Result<Self>> {
+        let redirect_uri = Url::parse(redirect_uri).ok()?;
+        if redirect_uri.scheme() != "http" {
+            return None;
+        }
+
+        let port = redirect_uri.port_or_known_default()?;
+        let (addr, localhost) = match redirect_uri.host()? {
+            Host::Domain("localhost") => (SocketAddr::from(([127, 0, 0, 1], port)), true),
+            Host::Ipv4(ip) if ip.is_loopback() => (SocketAddr::from((ip, port)), false),
+            Host::Ipv6(ip) if ip.is_loopback() => (SocketAddr::from((ip, port)), false),
+            _ => return None,
+        };
+
+        let listener = match bind_nonblocking(addr) {
+            Ok(listener) => listener,
+            Err(err) => return Some(Err(err)),
+        };
+        let mut listeners = Vec::new();
+        if localhost {
+            // On the same port as the IPv4 one, in case it was chosen by the
+            // system. Failing here isn't fatal, as IPv6 may be disabled.
+            let port = listener.local_addr().map_or(port, |addr| addr.port());
+            match bind_nonblocking(SocketAddr::from(([0, 0, 0, 0, 0, 0, 0, 1], port))) {
+                Ok(listener) => listeners.push(listener),
+                Err(err) => log::info!("Unable to listen for the redirect on [::1]: {}", err),
+            }
+        }
+        listeners.insert(0, listener);
+
+        Some(Ok(Self {
+            listeners,
+            redirect_uri,
+        }))
+    }
+
+    /// The addresses the listener is bound to.
+    #[cfg(test)]
+    pub fn local_addrs(&self) -> io::Result<Vec<SocketAddr>> {
+        self.listeners.iter().map(TcpListener::local_addr).collect()
+    }
+
+    /// Accepts a connection from any of the listeners, if there's one.
+    fn accept(&self) -> io::Result<Option<TcpStream>> {
+        for listener in &self.listeners {
+            match listener.accept() {
+                Ok((stream, _)) => return Ok(Some(stream)),
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => continue,
+                Err(err) => return Err(err),
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Waits for the browser to be redirected, and returns the code obtained
+    /// from the URL with `parse`. Requests to other paths, like the favicon,
+    /// are ignored.
+    #[maybe_async]
+    pub async fn wait_for_code<F>(&self, timeout: Duration, parse: F) -> ClientResult<String>
+    where
+        F: Fn(&str) -> Result<String, AuthError>,
+    {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let stream = match self.accept() {
+                Ok(Some(stream)) => stream,
+                Ok(None) => {
+                    if Instant::now() >= deadline {
+                        return Err(ClientError::Cli(
+                            "timed out waiting for the authorization redirect".to_string(),
+                        ));
+                    }
+                    crate::sync::sleep(POLL_INTERVAL).await;
+                    continue;
+                }
+                Err(err) => return Err(err.into()),
+            };
+
+            match self.handle(stream, deadline, &parse) {
+                Ok(Some(code)) => return Ok(code),
+                Ok(None) => continue,
+                Err(ClientError::Io(err)) => {
+                    log::warn!("Failed to read the authorization redirect: {}", err);
+                    continue;
+                }
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    /// Handles a single connection. Returns `None` if it wasn't the redirect.
+    fn handle<F>(
+        &self,
+        mut stream: TcpStream,
+        deadline: Instant,
+        parse: F,
+    ) -> ClientResult<Option<String>>
+    where
+        F: Fn(&str) -> Result<String, AuthError>,
+    {
+        stream.set_nonblocking(false)?;
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        let timeout = remaining.min(CONNECTION_TIMEOUT).max(POLL_INTERVAL);
+        stream.set_read_timeout(Some(timeout))?;
+
+        // Only the request line is needed, e.g. `GET /callback?code=... HTTP/1.1`
+        let mut request_line = String::new();
+        BufReader::new(&stream).read_line(&mut request_line)?;
+        let target = match request_line.split_whitespace().collect::<Vec<_>>()[..] {
+            ["GET", target, _] => target,
+            _ => {
+                respond(&mut stream, "405 Method Not Allowed", "")?;
+                return Ok(None);
+            }
+        };
+
+        let url = self.redirect_uri.join(target)?;
+        if url.path() != self.redirect_uri.path() {
+            respond(&mut stream, "404 Not Found", "")?;
+            return Ok(None);
+        }
+
+        match parse(url.as_str()) {
+            Ok(code) => {
+                respond(&mut stream, "200 OK", SUCCESS_PAGE)?;
+                Ok(Some(code))
+            }
+            Err(err) => {
+                respond(&mut stream, "400 Bad Request", FAILURE_PAGE)?;
+                Err(err.into())
+            }
+        }
+    }
+}
+
+fn bind_nonblocking(addr: SocketAddr) -> io::Result<TcpListener> {
+    let listener = TcpListener::bind(addr)?;
+    listener.set_nonblocking(true)?;
+    Ok(listener)
+}
+
+fn respond(stream: &mut TcpStream, status: &str, body: &str) -> io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\n\
+         Connection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    )?;
+    stream.flush()
+}
+
+#[cfg(test)]
+mod test {
+    use super::RedirectListener;
+    use crate::{AuthError, ClientError};
+
+    use std::{
+        io::{Read, Write},
+        net::{SocketAddr, TcpStream},
+        thread,
+        time::Duration,
+    };
+
+    fn get(addr: SocketAddr, target: &str) -> String {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write!(stream, "GET {} HTTP/1.1\r\nHost: {}\r\n\r\n", target, addr).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    fn parse(url: &str) -> Result<String, AuthError> {
+        crate::clients::parse_response_code(url, "xyz")
+    }
+
+    #[test]
+    fn test_bind() {
+        assert!(RedirectListener::bind("https://127.0.0.1:0/callback").is_none());
+        assert!(RedirectListener::bind("http://example.com:0/callback").is_none());
+        assert!(RedirectListener::bind("http://localhost:0/callback")
+            .unwrap()
+            .is_ok());
+        assert!(RedirectListener::bind("http://[::1]:0/callback").is_some());
+    }
+
+    #[maybe_async::test(feature = "__sync", async(feature = "__async", tokio::test))]
+    async fn test_wait_for_code() {
+        let listener = RedirectListener::bind("http://127.0.0.1:0/callback")
+            .unwrap()
+            .unwrap();
+        let addr = listener.local_addrs().unwrap()[0];
+
+        let browser = thread::spawn(move || {
+            let favicon = get(addr, "/favicon.ico");
+            assert!(favicon.starts_with("HTTP/1.1 404"));
+            let redirect = get(addr, "/callback?code=abc&state=xyz");
+            assert!(redirect.starts_with("HTTP/1.1 200"));
+            assert!(redirect.contains("close this tab"));
+        });
+
+        let code = listener.wait_for_code(Duration::from_secs(10), parse).await;
+        assert_eq!(code.unwrap(), "abc");
+        browser.join().unwrap();
+    }
+
+    #[maybe_async::test(feature = "__sync", async(feature = "__async", tokio::test))]
+    async fn test_wait_for_code_idle_connection() {
+        let listener = RedirectListener::bind("http://localhost:0/callback")
+            .unwrap()
+            .unwrap();
+        let addrs = listener.local_addrs().unwrap();
+
+        let browser = thread::spawn(move || {
+            // A connection opened in advance that never sends anything
+            let _idle = TcpStream::connect(addrs[0]).unwrap();
+            thread::sleep(Duration::from_millis(100));
+            // Both IPv4 and IPv6 are listened on if possible
+            let redirect = get(*addrs.last().unwrap(), "/callback?code=abc&state=xyz");
+            assert!(redirect.starts_with("HTTP/1.1 200"));
+        });
+
+        let code = listener.wait_for_code(Duration::from_secs(10), parse).await;
+        assert_eq!(code.unwrap(), "abc");
+        browser.join().unwrap();
+    }
+
+    #[maybe_async::test(feature = "__sync", async(feature = "__async", tokio::test))]
+    async fn test_wait_for_code_errors() {
+        let listener = RedirectListener::bind("http://127.0.0.1:0/callback")
+            .unwrap()
+            .unwrap();
+        let addr = listener.local_addrs().unwrap()[0];
+
+        let browser = thread::spawn(move || {
+            let redirect = get(addr, "/callback?error=access_denied&state=xyz");
+            assert!(redirect.starts_with("HTTP/1.1 400"));
+        });
+        let code = listener.wait_for_code(Duration::from_secs(10), parse).await;
+        assert!(matches!(
+            code,
+            Err(ClientError::Auth(AuthError::AccessDenied))
+        ));
+        browser.join().unwrap();
+
+        let code = listener
+            .wait_for_code(Duration::from_millis(100), parse)
+            .await;
+        assert!(matches!(code, Err(ClientError::Cli(_))));
+    }
+}
@@ -1,23 +1,50 @@
 mod base;
+#[cfg(feature = "cli")]
+mod loopback;
 mod oauth;
 pub mod pagination;
+pub mod playback;
 
 pub use base::BaseClient;
 pub use oauth::OAuthClient;
 
-use crate::ClientResult;
+use crate::{http::Query, model::DeviceId, AuthError, ClientResult};
 
-use std::fmt::Write as _;
+use std::{collections::HashMap, fmt::Write as _};
 
-use serde::Deserialize;
+use serde::{de::DeserializeOwned, Deserialize};
+use url::{form_urlencoded, Url};
 
 /// Converts a JSON response from Spotify into its model.
 pub(crate) fn convert_result<'a, T: Deserialize<'a>>(input: &'a str) -> ClientResult<T> {
     serde_json::from_str::<T>(input).map_err(Into::into)
 }
 
+/// Same as [`convert_result`], but empty responses (e.g. `204 No Content`) are
+/// parsed as a JSON `null`.
+pub(crate) fn convert_response<T: DeserializeOwned>(input: &str) -> ClientResult<T> {
+    if input.trim().is_empty() {
+        convert_result("null")
+    } else {
+        convert_result(input)
+    }
+}
+
+/// Append query parameters to an API path.
+pub(crate) fn append_query(path: &str, query: &Query<'_>) -> String {
+    let mut new_path = path.to_string();
+    if !query.is_empty() {
+        let query = form_urlencoded::Serializer::new(String::new())
+            .extend_pairs(query)
+            .finish();
+        new_path.push(if path.contains('?') { '&' } else { '?' });
+        new_path.push_str(&query);
+    }
+    new_path
+}
+
 /// Append device ID to an API path.
-pub(crate) fn append_device_id(path: &str, device_id: Option<&str>) -> String {
+pub(crate) fn append_device_id(path: &str, device_id: Option<&DeviceId>) -> String {
     let mut new_path = path.to_string();
     if let Some(device_id) = device_id {
         if path.contains('?') {
@@ -29,31 +56,81 @@ pub(crate) fn append_device_id(path: &str, device_id: Option<&str>) -> String {
     new_path
 }
 
+/// Parses the code from the URL the user was redirected to after the
+/// authorization, making sure that its state matches the expected one.
+pub(crate) fn parse_response_code(url: &str, expected_state: &str) -> Result<String, AuthError> {
+    let url = Url::parse(url).map_err(|_| AuthError::MissingCode)?;
+    let params = url.query_pairs().collect::<HashMap<_, _>>();
+
+    // As the [RFC indicates](https://datatracker.ietf.org/doc/html/rfc6749#section-4.1.2.1),
+    // the state is included in the error response as well
+    let state = params.get("state").map(AsRef::as_ref);
+    if state != Some(expected_state) {
+        log::error!("Request state doesn't match the callback state");
+        return Err(AuthError::StateMismatch);
+    }
+
+    match (params.get("code"), params.get("error")) {
+        (Some(code), _) => Ok(code.to_string()),
+        (None, Some(error)) if error == "access_denied" => Err(AuthError::AccessDenied),
+        (None, Some(error)) => Err(AuthError::Authorization(error.to_string())),
+        (None, None) => Err(AuthError::MissingCode),
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
-    use crate::{model::Token, scopes, ClientCredsSpotify, Config};
+    use crate::{
+        model::{DeviceId, Token},
+        scopes, ClientCredsSpotify, Config,
+    };
     use chrono::{prelude::*, Duration};
 
     #[test]
     fn test_append_device_id_without_question_mark() {
         let path = "me/player/play";
-        let device_id = Some("fdafdsadfa");
-        let new_path = append_device_id(path, device_id);
+        let device_id = DeviceId::from_id("fdafdsadfa").unwrap();
+        let new_path = append_device_id(path, Some(&device_id));
         assert_eq!(new_path, "me/player/play?device_id=fdafdsadfa");
     }
 
     #[test]
     fn test_append_device_id_with_question_mark() {
         let path = "me/player/shuffle?state=true";
-        let device_id = Some("fdafdsadfa");
-        let new_path = append_device_id(path, device_id);
+        let device_id = DeviceId::from_id("fdafdsadfa").unwrap();
+        let new_path = append_device_id(path, Some(&device_id));
         assert_eq!(
             new_path,
             "me/player/shuffle?state=true&device_id=fdafdsadfa"
         );
     }
 
+    #[test]
+    fn test_append_query() {
+        let path = "me/tracks";
+        assert_eq!(append_query(path, &Query::new()), "me/tracks");
+
+        let query = Query::from([("ids", "a,b")]);
+        assert_eq!(append_query(path, &query), "me/tracks?ids=a%2Cb");
+
+        let path = "me/player/shuffle?state=true";
+        let query = Query::from([("device_id", "fdafdsadfa")]);
+        assert_eq!(
+            append_query(path, &query),
+            "me/player/shuffle?state=true&device_id=fdafdsadfa"
+        );
+    }
+
+    #[test]
+    fn test_convert_response() {
+        // Empty responses are parsed as `null`
+        convert_response::<()>("").unwrap();
+        assert_eq!(convert_response::<Option<u32>>("  ").unwrap(), None);
+        assert_eq!(convert_response::<Option<u32>>("1").unwrap(), Some(1));
+        assert!(convert_response::<u32>("").is_err());
+    }
+
     #[test]
     fn test_api_url() {
         let mut spotify = ClientCredsSpotify::default();
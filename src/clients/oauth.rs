@@ -1,22 +1,24 @@
 use crate::{
     clients::{
         append_device_id, convert_result,
-        pagination::{paginate, Paginator},
+        pagination::{
+            paginate_pages, paginate_prefetch_with_ctx, paginate_resumable, PaginationState,
+            Paginator, PrefetchTask, ResumablePaginator,
+        },
+        parse_response_code,
+        playback::{watch_playback, PlaybackEvents, PlaybackEventsOptions},
         BaseClient,
     },
     http::Query,
     join_ids,
     model::*,
     util::{build_map, JsonBuilder},
-    ClientResult, OAuth, Token,
+    AuthError, ClientError, ClientResult, OAuth, Token,
 };
 
-use std::collections::HashMap;
-
 use maybe_async::maybe_async;
 use rspotify_model::idtypes::{PlayContextId, PlayableId};
 use serde_json::{json, Map};
-use url::Url;
 
 /// This trait implements the methods available strictly to clients with user
 /// authorization, including some parts of the authentication flow that are
@@ -36,14 +38,15 @@ pub trait OAuthClient: BaseClient {
     /// authentication. The access token will be saved internally.
     async fn request_token(&self, code: &str) -> ClientResult<()>;
 
-    /// Tries to read the cache file's token.
+    /// Tries to read the token from the configured token store.
     ///
-    /// This will return an error if the token couldn't be read (e.g. it's not
-    /// available or the JSON is malformed). It may return `Ok(None)` if:
+    /// This will return an error if the token couldn't be read (e.g. the JSON
+    /// is malformed). It may return `Ok(None)` if:
     ///
     /// * The read token is expired and `allow_expired` is false
     /// * Its scopes don't match with the current client (you will need to
     ///   re-authenticate to gain access to more scopes)
+    /// * There's no token in the store
     /// * The cached token is disabled in the config
     ///
     /// # Note
@@ -58,13 +61,19 @@ pub trait OAuthClient: BaseClient {
     ///
     /// [`ClientCredsSpotify::read_token_cache`]: crate::client_creds::ClientCredsSpotify::read_token_cache
     async fn read_token_cache(&self, allow_expired: bool) -> ClientResult<Option<Token>> {
-        if !self.get_config().token_cached {
-            log::info!("Auth token cache read ignored (not configured)");
-            return Ok(None);
-        }
+        let store = match self.get_config().get_token_store() {
+            Some(store) => store,
+            None => {
+                log::info!("Auth token cache read ignored (not configured)");
+                return Ok(None);
+            }
+        };
 
         log::info!("Reading auth token cache");
-        let token = Token::from_cache(&self.get_config().cache_path)?;
+        let token = match store.load().await? {
+            Some(token) => token,
+            None => return Ok(None),
+        };
         if !self.get_oauth().scopes.is_subset(&token.scopes)
             || (!allow_expired && token.is_expired())
         {
@@ -76,37 +85,45 @@ pub trait OAuthClient: BaseClient {
         }
     }
 
-    /// Parse the response code in the given response url. If the URL cannot be
-    /// parsed or the `code` parameter is not present, this will return `None`.
-    ///
-    // As the [RFC
-    // indicates](https://datatracker.ietf.org/doc/html/rfc6749#section-4.1),
-    // the state should be the same between the request and the callback. This
-    // will also return `None` if this is not true.
-    fn parse_response_code(&self, url: &str) -> Option<String> {
-        let url = Url::parse(url).ok()?;
-        let params = url.query_pairs().collect::<HashMap<_, _>>();
-
-        let code = params.get("code")?;
-
-        // Making sure the state is the same
-        let expected_state = &self.get_oauth().state;
-        let state = params.get("state").map(AsRef::as_ref);
-        if state != Some(expected_state) {
-            log::error!("Request state doesn't match the callback state");
-            return None;
-        }
-
-        Some(code.to_string())
+    /// Parse the response code in the given response url. It fails with
+    /// [`AuthError::AccessDenied`] if the user didn't authorize the app, and
+    /// with [`AuthError::MissingCode`] if the URL cannot be parsed or the
+    /// `code` parameter is not present.
+    ///
+    /// As the [RFC
+    /// indicates](https://datatracker.ietf.org/doc/html/rfc6749#section-4.1),
+    /// the state should be the same between the request and the callback.
+    /// Otherwise, this fails with [`AuthError::StateMismatch`].
+    fn parse_response_code(&self, url: &str) -> Result<String, AuthError> {
+        parse_response_code(url, &self.get_oauth().state)
     }
 
     /// Tries to open the authorization URL in the user's browser, and returns
     /// the obtained code.
     ///
+    /// If the redirect URI points to the local machine over HTTP, like
+    /// `http://127.0.0.1:8888/callback` or `http://localhost:8888/callback`,
+    /// a temporary HTTP listener is started on its port in order to capture
+    /// the code. It gives up after [`Config::redirect_timeout`]. Otherwise, or
+    /// if the port is unavailable, the user is asked to paste the URL they
+    /// were redirected to.
+    ///
     /// Note: this method requires the `cli` feature.
+    ///
+    /// [`Config::redirect_timeout`]: crate::Config::redirect_timeout
     #[cfg(feature = "cli")]
-    fn get_code_from_user(&self, url: &str) -> ClientResult<String> {
-        use crate::ClientError;
+    async fn get_code_from_user(&self, url: &str) -> ClientResult<String> {
+        use super::loopback::RedirectListener;
+
+        // The listener has to be ready before the browser is redirected
+        let listener = match RedirectListener::bind(&self.get_oauth().redirect_uri) {
+            Some(Ok(listener)) => Some(listener),
+            Some(Err(err)) => {
+                log::warn!("Unable to listen for the redirect: {}", err);
+                None
+            }
+            None => None,
+        };
 
         log::info!("Opening brower with auth URL");
         match webbrowser::open(url) {
@@ -118,21 +135,28 @@ pub trait OAuthClient: BaseClient {
             ),
         }
 
+        if let Some(listener) = listener {
+            log::info!("Waiting for the browser to be redirected");
+            println!("Waiting for the authorization to finish in your browser...");
+            let timeout = self.get_config().redirect_timeout;
+            return listener
+                .wait_for_code(timeout, |url| self.parse_response_code(url))
+                .await;
+        }
+
         log::info!("Prompting user for code");
         println!("Please enter the URL you were redirected to: ");
         let mut input = String::new();
         std::io::stdin().read_line(&mut input)?;
-        let code = self
-            .parse_response_code(&input)
-            .ok_or_else(|| ClientError::Cli("unable to parse the response code".to_string()))?;
-
-        Ok(code)
+        Ok(self.parse_response_code(input.trim())?)
     }
 
     /// Opens up the authorization URL in the user's browser so that it can
-    /// authenticate. It reads from the standard input the redirect URI
-    /// in order to obtain the access token information. The resulting access
-    /// token will be saved internally once the operation is successful.
+    /// authenticate. The redirect URI is then captured by a local HTTP
+    /// listener or read from the standard input, as explained in
+    /// [`Self::get_code_from_user`], in order to obtain the access token
+    /// information. The resulting access token will be saved internally once
+    /// the operation is successful.
     ///
     /// If the [`Config::token_cached`] setting is enabled for this client,
     /// and a token exists in the cache, the token will be loaded and the client
@@ -164,7 +188,7 @@ pub trait OAuthClient: BaseClient {
                         // If not, prompt the user for it
                         None => {
                             log::info!("Unable to refresh expired token from token cache");
-                            let code = self.get_code_from_user(url)?;
+                            let code = self.get_code_from_user(url).await?;
                             self.request_token(&code).await?;
                         }
                     }
@@ -172,7 +196,7 @@ pub trait OAuthClient: BaseClient {
             }
             // Otherwise following the usual procedure to get the token.
             _ => {
-                let code = self.get_code_from_user(url)?;
+                let code = self.get_code_from_user(url).await?;
                 self.request_token(&code).await?;
             }
         }
@@ -180,6 +204,32 @@ pub trait OAuthClient: BaseClient {
         self.write_token_cache().await
     }
 
+    /// Checks that the token was granted at least one of the given scopes if
+    /// [`Config::check_scopes`] is enabled, so that requests that would be
+    /// rejected by Spotify aren't sent. Otherwise, it fails with
+    /// [`ClientError::MissingScope`] and the first of them.
+    ///
+    /// [`Config::check_scopes`]: crate::Config::check_scopes
+    #[doc(hidden)]
+    async fn require_scope(&self, any_of: &[Scope]) -> ClientResult<()> {
+        if !self.get_config().check_scopes {
+            return Ok(());
+        }
+
+        let token = self.get_token();
+        let token = token.lock().await.unwrap();
+        match token.as_ref() {
+            // Without a token the request will fail anyway
+            None => Ok(()),
+            Some(token) if any_of.iter().any(|&scope| token.has_scope(scope)) => Ok(()),
+            Some(_) => match any_of.first() {
+                Some(&scope) => Err(ClientError::MissingScope(scope)),
+                // Nothing is required
+                None => Ok(()),
+            },
+        }
+    }
+
     /// Get current user playlists without required getting his profile.
     ///
     /// Parameters:
@@ -191,10 +241,38 @@ pub trait OAuthClient: BaseClient {
     ///
     /// [Reference](https://developer.spotify.com/documentation/web-api/reference/#/operations/get-a-list-of-current-users-playlists)
     fn current_user_playlists(&self) -> Paginator<'_, ClientResult<SimplifiedPlaylist>> {
-        paginate(
+        self.current_user_playlists_pages().into_items()
+    }
+
+    /// The page-level version of [`Self::current_user_playlists`], which yields
+    /// the whole pages, including metadata like their `total`.
+    fn current_user_playlists_pages(
+        &self,
+    ) -> Paginator<'_, ClientResult<Page<SimplifiedPlaylist>>> {
+        paginate_pages(
             move |limit, offset| self.current_user_playlists_manual(Some(limit), Some(offset)),
             self.get_config().pagination_chunks,
         )
+        .max_page_size(50)
+    }
+
+    /// The resumable version of [`Self::current_user_playlists`], as
+    /// explained in [`BaseClient::playlist_items_resumable`]. A new pagination
+    /// starts with `PaginationState::new(())`.
+    fn current_user_playlists_resumable(
+        &self,
+        state: PaginationState<()>,
+        max_retries: u32,
+    ) -> ResumablePaginator<'_, Page<SimplifiedPlaylist>, ()> {
+        paginate_resumable(
+            state,
+            move |(), limit, position| {
+                self.current_user_playlists_manual(Some(limit), position.offset())
+            },
+            self.get_config().pagination_chunks,
+            max_retries,
+        )
+        .max_page_size(50)
     }
 
     /// The manually paginated version of [`Self::current_user_playlists`].
@@ -203,6 +281,8 @@ pub trait OAuthClient: BaseClient {
         limit: Option<u32>,
         offset: Option<u32>,
     ) -> ClientResult<Page<SimplifiedPlaylist>> {
+        self.require_scope(&[Scope::PlaylistReadPrivate]).await?;
+
         let limit = limit.map(|s| s.to_string());
         let offset = offset.map(|s| s.to_string());
         let params = build_map([("limit", limit.as_deref()), ("offset", offset.as_deref())]);
@@ -230,6 +310,9 @@ pub trait OAuthClient: BaseClient {
         collaborative: Option<bool>,
         description: Option<&str>,
     ) -> ClientResult<FullPlaylist> {
+        self.require_scope(&[Scope::PlaylistModifyPublic, Scope::PlaylistModifyPrivate])
+            .await?;
+
         debug_assert!(
             !(collaborative.unwrap_or(false) && public.unwrap_or(false)),
             "To create a collaborative playlist you must also set public to \
@@ -266,6 +349,9 @@ pub trait OAuthClient: BaseClient {
         description: Option<&str>,
         collaborative: Option<bool>,
     ) -> ClientResult<String> {
+        self.require_scope(&[Scope::PlaylistModifyPublic, Scope::PlaylistModifyPrivate])
+            .await?;
+
         let params = JsonBuilder::new()
             .optional("name", name)
             .optional("public", public)
@@ -284,6 +370,9 @@ pub trait OAuthClient: BaseClient {
     ///
     /// [Reference](https://developer.spotify.com/documentation/web-api/reference/#/operations/unfollow-playlist)
     async fn playlist_unfollow(&self, playlist_id: PlaylistId<'_>) -> ClientResult<()> {
+        self.require_scope(&[Scope::PlaylistModifyPublic, Scope::PlaylistModifyPrivate])
+            .await?;
+
         let url = format!("playlists/{}/followers", playlist_id.id());
         self.api_delete(&url, &json!({})).await?;
 
@@ -304,6 +393,9 @@ pub trait OAuthClient: BaseClient {
         items: impl IntoIterator<Item = PlayableId<'a>> + Send + 'a,
         position: Option<u32>,
     ) -> ClientResult<PlaylistResult> {
+        self.require_scope(&[Scope::PlaylistModifyPublic, Scope::PlaylistModifyPrivate])
+            .await?;
+
         let uris = items.into_iter().map(|id| id.uri()).collect::<Vec<_>>();
         let params = JsonBuilder::new()
             .required("uris", uris)
@@ -328,6 +420,9 @@ pub trait OAuthClient: BaseClient {
         playlist_id: PlaylistId<'_>,
         items: impl IntoIterator<Item = PlayableId<'a>> + Send + 'a,
     ) -> ClientResult<()> {
+        self.require_scope(&[Scope::PlaylistModifyPublic, Scope::PlaylistModifyPrivate])
+            .await?;
+
         let uris = items.into_iter().map(|id| id.uri()).collect::<Vec<_>>();
         let params = JsonBuilder::new().required("uris", uris).build();
 
@@ -357,6 +452,9 @@ pub trait OAuthClient: BaseClient {
         range_length: Option<u32>,
         snapshot_id: Option<&str>,
     ) -> ClientResult<PlaylistResult> {
+        self.require_scope(&[Scope::PlaylistModifyPublic, Scope::PlaylistModifyPrivate])
+            .await?;
+
         let params = JsonBuilder::new()
             .optional("range_start", range_start)
             .optional("insert_before", insert_before)
@@ -383,6 +481,9 @@ pub trait OAuthClient: BaseClient {
         track_ids: impl IntoIterator<Item = PlayableId<'a>> + Send + 'a,
         snapshot_id: Option<&str>,
     ) -> ClientResult<PlaylistResult> {
+        self.require_scope(&[Scope::PlaylistModifyPublic, Scope::PlaylistModifyPrivate])
+            .await?;
+
         let tracks = track_ids
             .into_iter()
             .map(|id| {
@@ -437,6 +538,9 @@ pub trait OAuthClient: BaseClient {
         items: impl IntoIterator<Item = ItemPositions<'a>> + Send + 'a,
         snapshot_id: Option<&str>,
     ) -> ClientResult<PlaylistResult> {
+        self.require_scope(&[Scope::PlaylistModifyPublic, Scope::PlaylistModifyPrivate])
+            .await?;
+
         let tracks = items
             .into_iter()
             .map(|track| {
@@ -468,6 +572,9 @@ pub trait OAuthClient: BaseClient {
         playlist_id: PlaylistId<'_>,
         public: Option<bool>,
     ) -> ClientResult<()> {
+        self.require_scope(&[Scope::PlaylistModifyPublic, Scope::PlaylistModifyPrivate])
+            .await?;
+
         let url = format!("playlists/{}/followers", playlist_id.id());
 
         let params = JsonBuilder::new().optional("public", public).build();
@@ -498,6 +605,9 @@ pub trait OAuthClient: BaseClient {
     ///
     /// [Reference](https://developer.spotify.com/documentation/web-api/reference/#/operations/get-the-users-currently-playing-track)
     async fn current_user_playing_item(&self) -> ClientResult<Option<CurrentlyPlayingContext>> {
+        self.require_scope(&[Scope::UserReadCurrentlyPlaying])
+            .await?;
+
         let result = self
             .api_get("me/player/currently-playing", &Query::new())
             .await?;
@@ -524,12 +634,22 @@ pub trait OAuthClient: BaseClient {
         &self,
         market: Option<Market>,
     ) -> Paginator<'_, ClientResult<SavedAlbum>> {
-        paginate(
+        self.current_user_saved_albums_pages(market).into_items()
+    }
+
+    /// The page-level version of [`Self::current_user_saved_albums`], which
+    /// yields the whole pages, including metadata like their `total`.
+    fn current_user_saved_albums_pages(
+        &self,
+        market: Option<Market>,
+    ) -> Paginator<'_, ClientResult<Page<SavedAlbum>>> {
+        paginate_pages(
             move |limit, offset| {
                 self.current_user_saved_albums_manual(market, Some(limit), Some(offset))
             },
             self.get_config().pagination_chunks,
         )
+        .max_page_size(50)
     }
 
     /// The manually paginated version of [`Self::current_user_saved_albums`].
@@ -539,6 +659,8 @@ pub trait OAuthClient: BaseClient {
         limit: Option<u32>,
         offset: Option<u32>,
     ) -> ClientResult<Page<SavedAlbum>> {
+        self.require_scope(&[Scope::UserLibraryRead]).await?;
+
         let limit = limit.map(|s| s.to_string());
         let offset = offset.map(|s| s.to_string());
         let params = build_map([
@@ -567,12 +689,65 @@ pub trait OAuthClient: BaseClient {
         &self,
         market: Option<Market>,
     ) -> Paginator<'_, ClientResult<SavedTrack>> {
-        paginate(
+        self.current_user_saved_tracks_pages(market).into_items()
+    }
+
+    /// The page-level version of [`Self::current_user_saved_tracks`], which
+    /// yields the whole pages, including metadata like their `total`.
+    fn current_user_saved_tracks_pages(
+        &self,
+        market: Option<Market>,
+    ) -> Paginator<'_, ClientResult<Page<SavedTrack>>> {
+        paginate_pages(
             move |limit, offset| {
                 self.current_user_saved_tracks_manual(market, Some(limit), Some(offset))
             },
             self.get_config().pagination_chunks,
         )
+        .max_page_size(50)
+    }
+
+    /// Same as [`Self::current_user_saved_tracks`], but the next page is
+    /// requested in the background while the current one is consumed, as
+    /// explained in [`BaseClient::playlist_items_prefetch`].
+    fn current_user_saved_tracks_prefetch<Spawn>(
+        &self,
+        market: Option<Market>,
+        spawn: Spawn,
+    ) -> Paginator<'static, ClientResult<SavedTrack>>
+    where
+        Self: 'static,
+        Spawn: 'static + FnOnce(PrefetchTask) + Send,
+    {
+        paginate_prefetch_with_ctx(
+            self.clone(),
+            move |slf, limit, offset| {
+                slf.current_user_saved_tracks_manual(market, Some(limit), Some(offset))
+            },
+            self.get_config().pagination_chunks,
+            spawn,
+        )
+        .max_page_size(50)
+    }
+
+    /// The resumable version of [`Self::current_user_saved_tracks`], as
+    /// explained in [`BaseClient::playlist_items_resumable`]. The market is
+    /// kept in the state, so a new pagination starts with
+    /// `PaginationState::new(market)`.
+    fn current_user_saved_tracks_resumable(
+        &self,
+        state: PaginationState<Option<Market>>,
+        max_retries: u32,
+    ) -> ResumablePaginator<'_, Page<SavedTrack>, Option<Market>> {
+        paginate_resumable(
+            state,
+            move |market, limit, position| {
+                self.current_user_saved_tracks_manual(market, Some(limit), position.offset())
+            },
+            self.get_config().pagination_chunks,
+            max_retries,
+        )
+        .max_page_size(50)
     }
 
     /// The manually paginated version of [`Self::current_user_saved_tracks`].
@@ -582,6 +757,8 @@ pub trait OAuthClient: BaseClient {
         limit: Option<u32>,
         offset: Option<u32>,
     ) -> ClientResult<Page<SavedTrack>> {
+        self.require_scope(&[Scope::UserLibraryRead]).await?;
+
         let limit = limit.map(|s| s.to_string());
         let offset = offset.map(|s| s.to_string());
         let params = build_map([
@@ -606,6 +783,8 @@ pub trait OAuthClient: BaseClient {
         after: Option<&str>,
         limit: Option<u32>,
     ) -> ClientResult<CursorBasedPage<FullArtist>> {
+        self.require_scope(&[Scope::UserFollowRead]).await?;
+
         let limit = limit.map(|s| s.to_string());
         let params = build_map([
             ("type", Some(Type::Artist.into())),
@@ -627,6 +806,8 @@ pub trait OAuthClient: BaseClient {
         &self,
         track_ids: impl IntoIterator<Item = TrackId<'a>> + Send + 'a,
     ) -> ClientResult<()> {
+        self.require_scope(&[Scope::UserLibraryModify]).await?;
+
         let url = format!("me/tracks/?ids={}", join_ids(track_ids));
         self.api_delete(&url, &json!({})).await?;
 
@@ -644,6 +825,8 @@ pub trait OAuthClient: BaseClient {
         &self,
         track_ids: impl IntoIterator<Item = TrackId<'a>> + Send + 'a,
     ) -> ClientResult<Vec<bool>> {
+        self.require_scope(&[Scope::UserLibraryRead]).await?;
+
         let url = format!("me/tracks/contains/?ids={}", join_ids(track_ids));
         let result = self.api_get(&url, &Query::new()).await?;
         convert_result(&result)
@@ -659,6 +842,8 @@ pub trait OAuthClient: BaseClient {
         &self,
         track_ids: impl IntoIterator<Item = TrackId<'a>> + Send + 'a,
     ) -> ClientResult<()> {
+        self.require_scope(&[Scope::UserLibraryModify]).await?;
+
         let url = format!("me/tracks/?ids={}", join_ids(track_ids));
         self.api_put(&url, &json!({})).await?;
 
@@ -680,12 +865,22 @@ pub trait OAuthClient: BaseClient {
         &self,
         time_range: Option<TimeRange>,
     ) -> Paginator<'_, ClientResult<FullArtist>> {
-        paginate(
+        self.current_user_top_artists_pages(time_range).into_items()
+    }
+
+    /// The page-level version of [`Self::current_user_top_artists`], which
+    /// yields the whole pages, including metadata like their `total`.
+    fn current_user_top_artists_pages(
+        &self,
+        time_range: Option<TimeRange>,
+    ) -> Paginator<'_, ClientResult<Page<FullArtist>>> {
+        paginate_pages(
             move |limit, offset| {
                 self.current_user_top_artists_manual(time_range, Some(limit), Some(offset))
             },
             self.get_config().pagination_chunks,
         )
+        .max_page_size(50)
     }
 
     /// The manually paginated version of [`Self::current_user_top_artists`].
@@ -695,6 +890,8 @@ pub trait OAuthClient: BaseClient {
         limit: Option<u32>,
         offset: Option<u32>,
     ) -> ClientResult<Page<FullArtist>> {
+        self.require_scope(&[Scope::UserTopRead]).await?;
+
         let limit = limit.map(|s| s.to_string());
         let offset = offset.map(|s| s.to_string());
         let params = build_map([
@@ -722,12 +919,22 @@ pub trait OAuthClient: BaseClient {
         &self,
         time_range: Option<TimeRange>,
     ) -> Paginator<'_, ClientResult<FullTrack>> {
-        paginate(
+        self.current_user_top_tracks_pages(time_range).into_items()
+    }
+
+    /// The page-level version of [`Self::current_user_top_tracks`], which
+    /// yields the whole pages, including metadata like their `total`.
+    fn current_user_top_tracks_pages(
+        &self,
+        time_range: Option<TimeRange>,
+    ) -> Paginator<'_, ClientResult<Page<FullTrack>>> {
+        paginate_pages(
             move |limit, offset| {
                 self.current_user_top_tracks_manual(time_range, Some(limit), Some(offset))
             },
             self.get_config().pagination_chunks,
         )
+        .max_page_size(50)
     }
 
     /// The manually paginated version of [`Self::current_user_top_tracks`].
@@ -737,6 +944,8 @@ pub trait OAuthClient: BaseClient {
         limit: Option<u32>,
         offset: Option<u32>,
     ) -> ClientResult<Page<FullTrack>> {
+        self.require_scope(&[Scope::UserTopRead]).await?;
+
         let limit = limit.map(|x| x.to_string());
         let offset = offset.map(|x| x.to_string());
         let params = build_map([
@@ -762,6 +971,8 @@ pub trait OAuthClient: BaseClient {
         limit: Option<u32>,
         time_limit: Option<TimeLimits>,
     ) -> ClientResult<CursorBasedPage<PlayHistory>> {
+        self.require_scope(&[Scope::UserReadRecentlyPlayed]).await?;
+
         let limit = limit.map(|x| x.to_string());
         let mut params = build_map([("limit", limit.as_deref())]);
 
@@ -788,6 +999,8 @@ pub trait OAuthClient: BaseClient {
         &self,
         album_ids: impl IntoIterator<Item = AlbumId<'a>> + Send + 'a,
     ) -> ClientResult<()> {
+        self.require_scope(&[Scope::UserLibraryModify]).await?;
+
         let url = format!("me/albums/?ids={}", join_ids(album_ids));
         self.api_put(&url, &json!({})).await?;
 
@@ -804,6 +1017,8 @@ pub trait OAuthClient: BaseClient {
         &self,
         album_ids: impl IntoIterator<Item = AlbumId<'a>> + Send + 'a,
     ) -> ClientResult<()> {
+        self.require_scope(&[Scope::UserLibraryModify]).await?;
+
         let url = format!("me/albums/?ids={}", join_ids(album_ids));
         self.api_delete(&url, &json!({})).await?;
 
@@ -821,6 +1036,8 @@ pub trait OAuthClient: BaseClient {
         &self,
         album_ids: impl IntoIterator<Item = AlbumId<'a>> + Send + 'a,
     ) -> ClientResult<Vec<bool>> {
+        self.require_scope(&[Scope::UserLibraryRead]).await?;
+
         let url = format!("me/albums/contains/?ids={}", join_ids(album_ids));
         let result = self.api_get(&url, &Query::new()).await?;
         convert_result(&result)
@@ -836,6 +1053,8 @@ pub trait OAuthClient: BaseClient {
         &self,
         artist_ids: impl IntoIterator<Item = ArtistId<'a>> + Send + 'a,
     ) -> ClientResult<()> {
+        self.require_scope(&[Scope::UserFollowModify]).await?;
+
         let url = format!("me/following?type=artist&ids={}", join_ids(artist_ids));
         self.api_put(&url, &json!({})).await?;
 
@@ -852,6 +1071,8 @@ pub trait OAuthClient: BaseClient {
         &self,
         artist_ids: impl IntoIterator<Item = ArtistId<'a>> + Send + 'a,
     ) -> ClientResult<()> {
+        self.require_scope(&[Scope::UserFollowModify]).await?;
+
         let url = format!("me/following?type=artist&ids={}", join_ids(artist_ids));
         self.api_delete(&url, &json!({})).await?;
 
@@ -869,6 +1090,8 @@ pub trait OAuthClient: BaseClient {
         &self,
         artist_ids: impl IntoIterator<Item = ArtistId<'a>> + Send + 'a,
     ) -> ClientResult<Vec<bool>> {
+        self.require_scope(&[Scope::UserFollowRead]).await?;
+
         let url = format!(
             "me/following/contains?type=artist&ids={}",
             join_ids(artist_ids)
@@ -887,6 +1110,8 @@ pub trait OAuthClient: BaseClient {
         &self,
         user_ids: impl IntoIterator<Item = UserId<'a>> + Send + 'a,
     ) -> ClientResult<()> {
+        self.require_scope(&[Scope::UserFollowModify]).await?;
+
         let url = format!("me/following?type=user&ids={}", join_ids(user_ids));
         self.api_put(&url, &json!({})).await?;
 
@@ -903,6 +1128,8 @@ pub trait OAuthClient: BaseClient {
         &self,
         user_ids: impl IntoIterator<Item = UserId<'a>> + Send + 'a,
     ) -> ClientResult<()> {
+        self.require_scope(&[Scope::UserFollowModify]).await?;
+
         let url = format!("me/following?type=user&ids={}", join_ids(user_ids));
         self.api_delete(&url, &json!({})).await?;
 
@@ -913,10 +1140,39 @@ pub trait OAuthClient: BaseClient {
     ///
     /// [Reference](https://developer.spotify.com/documentation/web-api/reference/#/operations/get-a-users-available-devices)
     async fn device(&self) -> ClientResult<Vec<Device>> {
+        self.require_scope(&[Scope::UserReadPlaybackState]).await?;
+
         let result = self.api_get("me/player/devices", &Query::new()).await?;
         convert_result::<DevicePayload>(&result).map(|x| x.devices)
     }
 
+    /// Follows the playback of the user, yielding a [`PlaybackEvent`] every
+    /// time something changes, e.g. when the track changes, the playback is
+    /// paused or the user seeks. Failed requests are yielded as well, but the
+    /// polling goes on, so it never ends.
+    ///
+    /// The playback state is requested with [`Self::current_playback`] at an
+    /// adaptive interval, with the default [`PlaybackEventsOptions`]. See
+    /// [`Self::playback_events_with`] to configure it.
+    ///
+    /// [`PlaybackEvent`]: crate::clients::playback::PlaybackEvent
+    fn playback_events(&self) -> PlaybackEvents<'_> {
+        self.playback_events_with(PlaybackEventsOptions::default())
+    }
+
+    /// Same as [`Self::playback_events`], but with the given options.
+    fn playback_events_with(&self, options: PlaybackEventsOptions) -> PlaybackEvents<'_> {
+        watch_playback(
+            move || {
+                self.current_playback(
+                    None,
+                    Some(&[AdditionalType::Track, AdditionalType::Episode]),
+                )
+            },
+            options,
+        )
+    }
+
     /// Get Information About The User’s Current Playback
     ///
     /// Parameters:
@@ -931,6 +1187,8 @@ pub trait OAuthClient: BaseClient {
         country: Option<Market>,
         additional_types: Option<impl IntoIterator<Item = &'a AdditionalType> + Send + 'a>,
     ) -> ClientResult<Option<CurrentPlaybackContext>> {
+        self.require_scope(&[Scope::UserReadPlaybackState]).await?;
+
         let additional_types = additional_types.map(|x| {
             x.into_iter()
                 .map(Into::into)
@@ -964,6 +1222,9 @@ pub trait OAuthClient: BaseClient {
         market: Option<Market>,
         additional_types: Option<impl IntoIterator<Item = &'a AdditionalType> + Send + 'a>,
     ) -> ClientResult<Option<CurrentlyPlayingContext>> {
+        self.require_scope(&[Scope::UserReadCurrentlyPlaying])
+            .await?;
+
         let additional_types = additional_types.map(|x| {
             x.into_iter()
                 .map(Into::into)
@@ -987,6 +1248,8 @@ pub trait OAuthClient: BaseClient {
     ///
     /// [Reference](https://developer.spotify.com/documentation/web-api/reference/#/operations/get-queue)
     async fn current_user_queue(&self) -> ClientResult<CurrentUserQueue> {
+        self.require_scope(&[Scope::UserReadPlaybackState]).await?;
+
         let params = build_map([]);
         let result = self.api_get("me/player/queue", &params).await?;
         convert_result(&result)
@@ -1002,9 +1265,16 @@ pub trait OAuthClient: BaseClient {
     /// - force_play - true: after transfer, play. false: keep current state.
     ///
     /// [Reference](https://developer.spotify.com/documentation/web-api/reference/#/operations/transfer-a-users-playback)
-    async fn transfer_playback(&self, device_id: &str, play: Option<bool>) -> ClientResult<()> {
+    async fn transfer_playback(
+        &self,
+        device_id: &DeviceId,
+        play: Option<bool>,
+    ) -> ClientResult<()> {
+        self.require_scope(&[Scope::UserModifyPlaybackState])
+            .await?;
+
         let params = JsonBuilder::new()
-            .required("device_ids", [device_id])
+            .required("device_ids", [device_id.id()])
             .optional("play", play)
             .build();
 
@@ -1030,10 +1300,13 @@ pub trait OAuthClient: BaseClient {
     async fn start_context_playback(
         &self,
         context_uri: PlayContextId<'_>,
-        device_id: Option<&str>,
+        device_id: Option<&DeviceId>,
         offset: Option<Offset>,
         position: Option<chrono::Duration>,
     ) -> ClientResult<()> {
+        self.require_scope(&[Scope::UserModifyPlaybackState])
+            .await?;
+
         let params = JsonBuilder::new()
             .required("context_uri", context_uri.uri())
             .optional(
@@ -1066,10 +1339,13 @@ pub trait OAuthClient: BaseClient {
     async fn start_uris_playback<'a>(
         &self,
         uris: impl IntoIterator<Item = PlayableId<'a>> + Send + 'a,
-        device_id: Option<&str>,
+        device_id: Option<&DeviceId>,
         offset: Option<crate::model::Offset>,
         position: Option<chrono::Duration>,
     ) -> ClientResult<()> {
+        self.require_scope(&[Scope::UserModifyPlaybackState])
+            .await?;
+
         let params = JsonBuilder::new()
             .required(
                 "uris",
@@ -1099,7 +1375,10 @@ pub trait OAuthClient: BaseClient {
     /// - device_id - device target for playback
     ///
     /// [Reference](https://developer.spotify.com/documentation/web-api/reference/#/operations/pause-a-users-playback)
-    async fn pause_playback(&self, device_id: Option<&str>) -> ClientResult<()> {
+    async fn pause_playback(&self, device_id: Option<&DeviceId>) -> ClientResult<()> {
+        self.require_scope(&[Scope::UserModifyPlaybackState])
+            .await?;
+
         let url = append_device_id("me/player/pause", device_id);
         self.api_put(&url, &json!({})).await?;
 
@@ -1115,9 +1394,12 @@ pub trait OAuthClient: BaseClient {
     /// [Reference](https://developer.spotify.com/documentation/web-api/reference/#/operations/start-a-users-playback)
     async fn resume_playback(
         &self,
-        device_id: Option<&str>,
+        device_id: Option<&DeviceId>,
         position: Option<chrono::Duration>,
     ) -> ClientResult<()> {
+        self.require_scope(&[Scope::UserModifyPlaybackState])
+            .await?;
+
         let params = JsonBuilder::new()
             .optional("position_ms", position.map(|p| p.num_milliseconds()))
             .build();
@@ -1134,7 +1416,10 @@ pub trait OAuthClient: BaseClient {
     /// - device_id - device target for playback
     ///
     /// [Reference](https://developer.spotify.com/documentation/web-api/reference/#/operations/skip-users-playback-to-next-track)
-    async fn next_track(&self, device_id: Option<&str>) -> ClientResult<()> {
+    async fn next_track(&self, device_id: Option<&DeviceId>) -> ClientResult<()> {
+        self.require_scope(&[Scope::UserModifyPlaybackState])
+            .await?;
+
         let url = append_device_id("me/player/next", device_id);
         self.api_post(&url, &json!({})).await?;
 
@@ -1147,7 +1432,10 @@ pub trait OAuthClient: BaseClient {
     /// - device_id - device target for playback
     ///
     /// [Reference](https://developer.spotify.com/documentation/web-api/reference/#/operations/skip-users-playback-to-previous-track)
-    async fn previous_track(&self, device_id: Option<&str>) -> ClientResult<()> {
+    async fn previous_track(&self, device_id: Option<&DeviceId>) -> ClientResult<()> {
+        self.require_scope(&[Scope::UserModifyPlaybackState])
+            .await?;
+
         let url = append_device_id("me/player/previous", device_id);
         self.api_post(&url, &json!({})).await?;
 
@@ -1164,8 +1452,11 @@ pub trait OAuthClient: BaseClient {
     async fn seek_track(
         &self,
         position: chrono::Duration,
-        device_id: Option<&str>,
+        device_id: Option<&DeviceId>,
     ) -> ClientResult<()> {
+        self.require_scope(&[Scope::UserModifyPlaybackState])
+            .await?;
+
         let url = append_device_id(
             &format!("me/player/seek?position_ms={}", position.num_milliseconds()),
             device_id,
@@ -1182,7 +1473,10 @@ pub trait OAuthClient: BaseClient {
     /// - device_id - device target for playback
     ///
     /// [Reference](https://developer.spotify.com/documentation/web-api/reference/#/operations/set-repeat-mode-on-users-playback)
-    async fn repeat(&self, state: RepeatState, device_id: Option<&str>) -> ClientResult<()> {
+    async fn repeat(&self, state: RepeatState, device_id: Option<&DeviceId>) -> ClientResult<()> {
+        self.require_scope(&[Scope::UserModifyPlaybackState])
+            .await?;
+
         let url = append_device_id(
             &format!("me/player/repeat?state={}", <&str>::from(state)),
             device_id,
@@ -1199,7 +1493,10 @@ pub trait OAuthClient: BaseClient {
     /// - device_id - device target for playback
     ///
     /// [Reference](https://developer.spotify.com/documentation/web-api/reference/#/operations/set-volume-for-users-playback)
-    async fn volume(&self, volume_percent: u8, device_id: Option<&str>) -> ClientResult<()> {
+    async fn volume(&self, volume_percent: u8, device_id: Option<&DeviceId>) -> ClientResult<()> {
+        self.require_scope(&[Scope::UserModifyPlaybackState])
+            .await?;
+
         debug_assert!(
             volume_percent <= 100u8,
             "volume must be between 0 and 100, inclusive"
@@ -1220,7 +1517,10 @@ pub trait OAuthClient: BaseClient {
     /// - device_id - device target for playback
     ///
     /// [Reference](https://developer.spotify.com/documentation/web-api/reference/#/operations/toggle-shuffle-for-users-playback)
-    async fn shuffle(&self, state: bool, device_id: Option<&str>) -> ClientResult<()> {
+    async fn shuffle(&self, state: bool, device_id: Option<&DeviceId>) -> ClientResult<()> {
+        self.require_scope(&[Scope::UserModifyPlaybackState])
+            .await?;
+
         let url = append_device_id(&format!("me/player/shuffle?state={state}"), device_id);
         self.api_put(&url, &json!({})).await?;
 
@@ -1239,8 +1539,11 @@ pub trait OAuthClient: BaseClient {
     async fn add_item_to_queue(
         &self,
         item: PlayableId<'_>,
-        device_id: Option<&str>,
+        device_id: Option<&DeviceId>,
     ) -> ClientResult<()> {
+        self.require_scope(&[Scope::UserModifyPlaybackState])
+            .await?;
+
         let url = append_device_id(&format!("me/player/queue?uri={}", item.uri()), device_id);
         self.api_post(&url, &json!({})).await?;
 
@@ -1258,6 +1561,8 @@ pub trait OAuthClient: BaseClient {
         &self,
         show_ids: impl IntoIterator<Item = ShowId<'a>> + Send + 'a,
     ) -> ClientResult<()> {
+        self.require_scope(&[Scope::UserLibraryModify]).await?;
+
         let url = format!("me/shows/?ids={}", join_ids(show_ids));
         self.api_put(&url, &json!({})).await?;
 
@@ -1278,10 +1583,17 @@ pub trait OAuthClient: BaseClient {
     ///
     /// [Reference](https://developer.spotify.com/documentation/web-api/reference/#/operations/get-users-saved-shows)
     fn get_saved_show(&self) -> Paginator<'_, ClientResult<Show>> {
-        paginate(
+        self.get_saved_show_pages().into_items()
+    }
+
+    /// The page-level version of [`Self::get_saved_show`], which yields the
+    /// whole pages, including metadata like their `total`.
+    fn get_saved_show_pages(&self) -> Paginator<'_, ClientResult<Page<Show>>> {
+        paginate_pages(
             move |limit, offset| self.get_saved_show_manual(Some(limit), Some(offset)),
             self.get_config().pagination_chunks,
         )
+        .max_page_size(50)
     }
 
     /// The manually paginated version of [`Self::get_saved_show`].
@@ -1290,6 +1602,8 @@ pub trait OAuthClient: BaseClient {
         limit: Option<u32>,
         offset: Option<u32>,
     ) -> ClientResult<Page<Show>> {
+        self.require_scope(&[Scope::UserLibraryRead]).await?;
+
         let limit = limit.map(|x| x.to_string());
         let offset = offset.map(|x| x.to_string());
         let params = build_map([("limit", limit.as_deref()), ("offset", offset.as_deref())]);
@@ -1308,6 +1622,8 @@ pub trait OAuthClient: BaseClient {
         &self,
         ids: impl IntoIterator<Item = ShowId<'a>> + Send + 'a,
     ) -> ClientResult<Vec<bool>> {
+        self.require_scope(&[Scope::UserLibraryRead]).await?;
+
         let ids = join_ids(ids);
         let params = build_map([("ids", Some(&ids))]);
         let result = self.api_get("me/shows/contains", &params).await?;
@@ -1327,6 +1643,8 @@ pub trait OAuthClient: BaseClient {
         show_ids: impl IntoIterator<Item = ShowId<'a>> + Send + 'a,
         country: Option<Market>,
     ) -> ClientResult<()> {
+        self.require_scope(&[Scope::UserLibraryModify]).await?;
+
         let url = format!("me/shows?ids={}", join_ids(show_ids));
         let params = JsonBuilder::new()
             .optional("country", country.map(<&str>::from))
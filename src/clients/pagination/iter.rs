@@ -1,46 +1,193 @@
 //! Synchronous implementation of automatic pagination requests.
 
+use super::{
+    retry_delay, PagePosition, PaginationOptions, PaginationState, Paginator, ResumablePage,
+};
 use crate::{model::Page, ClientError, ClientResult};
 
-/// Alias for `Iterator<Item = T>`, since sync mode is enabled.
-pub type Paginator<'a, T> = Box<dyn Iterator<Item = T> + 'a>;
+use std::{sync::mpsc, time::Duration};
 
-pub fn paginate_with_ctx<'a, Ctx: 'a, T: 'a, Request: 'a>(
+/// The underlying iterator of a [`Paginator`], since sync mode is enabled.
+pub type Inner<'a, T> = Box<dyn Iterator<Item = T> + 'a>;
+
+/// Builds the underlying iterator of a [`Paginator`] given its options.
+pub type Build<'a, T> = Box<dyn FnOnce(PaginationOptions) -> Inner<'a, T> + 'a>;
+
+impl<T> Iterator for Paginator<'_, T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.inner().next()
+    }
+}
+
+impl<'a, T: 'a> Paginator<'a, T> {
+    /// Applies `f` to the underlying iterator, whether it's been built or not.
+    fn map_inner<U, F>(self, f: F) -> Paginator<'a, U>
+    where
+        F: 'a + Fn(Inner<'a, T>) -> Inner<'a, U> + Copy,
+    {
+        Paginator {
+            options: self.options,
+            default_page_size: self.default_page_size,
+            max_page_size: self.max_page_size,
+            build: self
+                .build
+                .map(|build| -> Build<'a, U> { Box::new(move |options| f(build(options))) }),
+            inner: self.inner.map(f),
+        }
+    }
+}
+
+impl<'a, T: 'a> Paginator<'a, ClientResult<Page<T>>> {
+    /// Flattens the paginated pages into their items.
+    pub fn into_items(self) -> Paginator<'a, ClientResult<T>> {
+        self.map_inner(|pages| -> Inner<'a, ClientResult<T>> {
+            Box::new(
+                pages.flat_map(|result| ResultIter::new(result.map(|page| page.items.into_iter()))),
+            )
+        })
+    }
+}
+
+pub fn paginate_with_ctx<'a, Ctx, T, Request>(
     ctx: Ctx,
     req: Request,
     page_size: u32,
 ) -> Paginator<'a, ClientResult<T>>
 where
-    Request: Fn(&Ctx, u32, u32) -> ClientResult<Page<T>>,
+    Ctx: 'a,
+    T: 'a,
+    Request: 'a + Fn(&Ctx, u32, u32) -> ClientResult<Page<T>>,
 {
     paginate(move |limit, offset| req(&ctx, limit, offset), page_size)
 }
 
 /// This is used to handle paginated requests automatically.
-pub fn paginate<'a, T: 'a, Request: 'a>(
+pub fn paginate<'a, T, Request>(req: Request, page_size: u32) -> Paginator<'a, ClientResult<T>>
+where
+    T: 'a,
+    Request: 'a + Fn(u32, u32) -> ClientResult<Page<T>>,
+{
+    paginate_pages(req, page_size).into_items()
+}
+
+/// Same as [`paginate_with_ctx`], but the whole pages are yielded instead of
+/// their items.
+pub fn paginate_pages_with_ctx<'a, Ctx, T, Request>(
+    ctx: Ctx,
+    req: Request,
+    page_size: u32,
+) -> Paginator<'a, ClientResult<Page<T>>>
+where
+    Ctx: 'a,
+    T: 'a,
+    Request: 'a + Fn(&Ctx, u32, u32) -> ClientResult<Page<T>>,
+{
+    paginate_pages(move |limit, offset| req(&ctx, limit, offset), page_size)
+}
+
+/// Same as [`paginate`], but the whole pages are yielded instead of their
+/// items.
+///
+/// The pagination ends after a page without a `next` URL, an empty page or an
+/// error.
+pub fn paginate_pages<'a, T, Request>(
+    req: Request,
+    page_size: u32,
+) -> Paginator<'a, ClientResult<Page<T>>>
+where
+    T: 'a,
+    Request: 'a + Fn(u32, u32) -> ClientResult<Page<T>>,
+{
+    let build = move |options: PaginationOptions| -> Inner<'a, ClientResult<Page<T>>> {
+        Box::new(PageIterator::new(req, options))
+    };
+
+    Paginator::new(Box::new(build), page_size)
+}
+
+/// The work of [`paginate_prefetch`] that has to be spawned, which requests
+/// the pages in the background.
+pub type PrefetchTask = Box<dyn FnOnce() + Send>;
+
+/// Same as [`paginate`], but the next page is requested in the background
+/// while the current one is being consumed. Only a single page is requested
+/// ahead of the one being consumed.
+///
+/// The requests are performed by a task passed to `spawn`, which should run
+/// it in a different thread, e.g. `|task| { std::thread::spawn(task); }`.
+/// Since it's spawned, the request can't borrow anything, so the client has
+/// to be cloned into it. Dropping the paginator makes the task finish after
+/// its current request.
+pub fn paginate_prefetch<'a, T, Request, Spawn>(
     req: Request,
     page_size: u32,
+    spawn: Spawn,
 ) -> Paginator<'a, ClientResult<T>>
 where
-    Request: Fn(u32, u32) -> ClientResult<Page<T>>,
+    T: 'static + Send,
+    Request: 'static + Fn(u32, u32) -> ClientResult<Page<T>> + Send + Sync,
+    Spawn: 'a + FnOnce(PrefetchTask) + Send,
 {
-    let pages = PageIterator {
-        req,
-        offset: 0,
-        done: false,
-        page_size,
+    let build = move |options: PaginationOptions| -> Inner<'a, ClientResult<Page<T>>> {
+        // The channel doesn't hold any pages, so the task waits with the next
+        // one until the current one is received.
+        let (sender, receiver) = mpsc::sync_channel(0);
+        spawn(Box::new(move || {
+            for page in PageIterator::new(req, options) {
+                if sender.send(page).is_err() {
+                    break;
+                }
+            }
+        }));
+        Box::new(receiver.into_iter())
     };
 
-    Box::new(pages.flat_map(|result| ResultIter::new(result.map(|page| page.items.into_iter()))))
+    Paginator::new(Box::new(build), page_size).into_items()
 }
 
-/// Iterator that repeatedly calls a function that returns a page until an empty
-/// page is returned.
+/// Same as [`paginate_prefetch`], but the request is given a context owned by
+/// the spawned task, like with [`paginate_with_ctx`].
+pub fn paginate_prefetch_with_ctx<'a, Ctx, T, Request, Spawn>(
+    ctx: Ctx,
+    req: Request,
+    page_size: u32,
+    spawn: Spawn,
+) -> Paginator<'a, ClientResult<T>>
+where
+    Ctx: 'static + Send + Sync,
+    T: 'static + Send,
+    Request: 'static + Fn(&Ctx, u32, u32) -> ClientResult<Page<T>> + Send + Sync,
+    Spawn: 'a + FnOnce(PrefetchTask) + Send,
+{
+    paginate_prefetch(
+        move |limit, offset| req(&ctx, limit, offset),
+        page_size,
+        spawn,
+    )
+}
+
+/// Iterator that repeatedly calls a function that returns a page until the
+/// last one is returned.
 struct PageIterator<Request> {
     req: Request,
     offset: u32,
+    fetched: u32,
     done: bool,
-    page_size: u32,
+    options: PaginationOptions,
+}
+
+impl<Request> PageIterator<Request> {
+    fn new(req: Request, options: PaginationOptions) -> Self {
+        Self {
+            req,
+            offset: options.offset,
+            fetched: 0,
+            done: false,
+            options,
+        }
+    }
 }
 
 impl<T, Request> Iterator for PageIterator<Request>
@@ -54,16 +201,111 @@ where
             return None;
         }
 
-        match (self.req)(self.page_size, self.offset) {
-            Ok(page) if page.items.is_empty() => {
+        let limit = match self.options.next_limit(self.fetched) {
+            Some(limit) => limit,
+            None => {
                 self.done = true;
-                None
+                return None;
+            }
+        };
+
+        let result = (self.req)(limit, self.offset);
+        match &result {
+            Ok(page) => {
+                let len = page.items.len() as u32;
+                self.offset += len;
+                self.fetched += len;
+                self.done = page.next.is_none() || len == 0;
             }
+            Err(_) => self.done = true,
+        }
+
+        Some(result)
+    }
+}
+
+/// This is used to handle paginated requests that can be resumed later on.
+/// The pages are yielded along with the state right after them, which may be
+/// saved and passed to this function again to continue where it was left.
+///
+/// The request is given the parameters in `state.params`, the limit and the
+/// position to request. Failed requests are yielded as errors without
+/// advancing the state, so that the same page is requested again when the
+/// next element is polled. Before that, it waits for the time in the
+/// `Retry-After` header if it was rate limited, or for one second doubled
+/// with every consecutive failure otherwise. The pagination ends after
+/// `max_retries` consecutive failures, after a page without a `next` URL or
+/// after an empty page.
+pub fn paginate_resumable<'a, P, Pg, Request>(
+    state: PaginationState<P>,
+    req: Request,
+    page_size: u32,
+    max_retries: u32,
+) -> Paginator<'a, ClientResult<(Pg, PaginationState<P>)>>
+where
+    P: 'a + Clone,
+    Pg: 'a + ResumablePage,
+    Request: 'a + Fn(P, u32, PagePosition) -> ClientResult<Pg>,
+{
+    let build =
+        move |options: PaginationOptions| -> Inner<'a, ClientResult<(Pg, PaginationState<P>)>> {
+            let mut state = state;
+            state.start_at(options.offset);
+            Box::new(ResumableIterator {
+                req,
+                state,
+                failures: 0,
+                wait: None,
+                max_retries,
+                options,
+            })
+        };
+
+    Paginator::new(Box::new(build), page_size)
+}
+
+/// Iterator that repeatedly calls a function that returns a page, keeping
+/// track of its position in a [`PaginationState`].
+struct ResumableIterator<P, Request> {
+    req: Request,
+    state: PaginationState<P>,
+    failures: u32,
+    /// How long to wait before requesting the page again after a failure.
+    wait: Option<Duration>,
+    max_retries: u32,
+    options: PaginationOptions,
+}
+
+impl<P, Pg, Request> Iterator for ResumableIterator<P, Request>
+where
+    P: Clone,
+    Pg: ResumablePage,
+    Request: Fn(P, u32, PagePosition) -> ClientResult<Pg>,
+{
+    type Item = ClientResult<(Pg, PaginationState<P>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failures > self.max_retries {
+            return None;
+        }
+
+        let position = self.state.position.clone()?;
+        let limit = self.options.next_limit(self.state.fetched)?;
+        if let Some(wait) = self.wait.take() {
+            log::warn!("Requesting the page again in {:?}", wait);
+            crate::sync::sleep(wait);
+        }
+        match (self.req)(self.state.params.clone(), limit, position) {
             Ok(page) => {
-                self.offset += page.items.len() as u32;
-                Some(Ok(page))
+                self.failures = 0;
+                self.state.advance(&page);
+                Some(Ok((page, self.state.clone())))
+            }
+            Err(err) => {
+                self.wait = Some(retry_delay(&err, self.failures));
+                self.failures += 1;
+                Some(Err(err))
             }
-            Err(e) => Some(Err(e)),
         }
     }
 }
@@ -101,3 +343,66 @@ impl<T, I: Iterator<Item = T>> Iterator for ResultIter<T, I> {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::paginate_prefetch;
+    use crate::model::Page;
+    use std::{
+        sync::{
+            atomic::{AtomicU32, Ordering},
+            Arc,
+        },
+        thread,
+        time::Duration,
+    };
+
+    fn fake_page(total: u32, limit: u32, offset: u32) -> Page<u32> {
+        let end = total.min(offset + limit);
+        Page {
+            items: (offset..end).collect(),
+            limit,
+            offset,
+            total,
+            next: (end < total).then(|| String::from("next")),
+            ..Page::default()
+        }
+    }
+
+    #[test]
+    fn test_prefetch() {
+        let requests = Arc::new(AtomicU32::new(0));
+        let counter = Arc::clone(&requests);
+        let mut paginator = paginate_prefetch(
+            move |limit, offset| {
+                counter.fetch_add(1, Ordering::SeqCst);
+                Ok(fake_page(9, limit, offset))
+            },
+            3,
+            |task| {
+                thread::spawn(task);
+            },
+        );
+
+        // Nothing is requested until the paginator is first used
+        thread::sleep(Duration::from_millis(50));
+        assert_eq!(requests.load(Ordering::SeqCst), 0);
+        assert_eq!(paginator.next().unwrap().unwrap(), 0);
+
+        // The next page is requested while the first one is consumed, but not
+        // the one after it
+        for _ in 0..1000 {
+            if requests.load(Ordering::SeqCst) >= 2 {
+                break;
+            }
+            thread::sleep(Duration::from_millis(1));
+        }
+        thread::sleep(Duration::from_millis(50));
+        assert_eq!(requests.load(Ordering::SeqCst), 2);
+
+        let mut items = vec![0];
+        items.extend(paginator.map(Result::unwrap));
+        assert_eq!(items, (0..9).collect::<Vec<_>>());
+        assert_eq!(requests.load(Ordering::SeqCst), 3);
+    }
+}
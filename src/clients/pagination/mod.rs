@@ -4,18 +4,39 @@
 //!
 //! All implementations export:
 //!
-//! * A `Paginator` struct which wraps the iterable of items
+//! * A `Paginator` struct which wraps the iterable of items. Its builder
+//!   methods can be used to configure where the pagination starts, how many
+//!   items it yields and how many are requested at a time. See
+//!   [`PaginationOptions`] for more information.
 //! * A `paginate` function, which returns a `Paginator` based on a request that
 //!   may be repeated in order to return a continuous sequence of `Page`s
 //! * A `paginate_with_ctx` function that does the same as the `paginate`
 //!   function, but accepts a generic context that works around lifetime issues
 //!   in the async version due to restrictions in HRTBs
 //!   (<https://kevincox.ca/2022/04/16/rust-generic-closure-lifetimes/>)
+//! * The `paginate_pages` and `paginate_pages_with_ctx` functions, which yield
+//!   the whole `Page`s instead of their items, so that their metadata (like
+//!   `total`) is available. All of them stop after a page without a `next`
+//!   URL, an empty page or an error.
+//! * A `paginate_resumable` function, whose [`PaginationState`] is yielded
+//!   along with each page so that it can be saved and resumed later on. It
+//!   may also retry the failed pages instead of ending the pagination, after
+//!   waiting just like when the concurrent paginators are rate limited.
+//! * The `paginate_prefetch` and `paginate_prefetch_with_ctx` functions,
+//!   which request the next page in the background while the current one is
+//!   consumed. The work is given to a `spawn` function, which should run it
+//!   in a thread for synchronous clients, or as a future in the async runtime
+//!   for asynchronous ones.
+//!
+//! The asynchronous implementations also export `paginate_concurrent` and
+//! `paginate_concurrent_with_ctx`, which request the pages after the first one
+//! concurrently, based on the `total` field of the first page. The same can be
+//! done with any endpoint through the `Paginator::concurrency` builder method.
 //!
 //! Note that `Paginator` should actually be a trait so that a dynamic
 //! allocation can be avoided when returning it with `-> impl Iterator<T>`, as
-//! opposed to `-> Box<dyn Iterator<T>>`. But since the Spotify clients are
-//! trait-based, they can't return anonymous types, and the former option is
+//! opposed to wrapping a `Box<dyn Iterator<T>>`. But since the Spotify clients
+//! are trait-based, they can't return anonymous types, and the former option is
 //! impossible for now. This is the same small overhead introduced by the
 //! `async_trait` crate and that will hopefully be fixed in the future.
 //!
@@ -32,11 +53,327 @@ mod stream;
 #[cfg(all(feature = "__async", target_arch = "wasm32"))]
 mod wasm_stream;
 
+mod state;
+
 #[cfg(feature = "__sync")]
-pub use iter::{paginate, paginate_with_ctx, Paginator};
+use self::iter as imp;
+#[cfg(all(feature = "__async", not(target_arch = "wasm32")))]
+use self::stream as imp;
+#[cfg(all(feature = "__async", target_arch = "wasm32"))]
+use self::wasm_stream as imp;
+
+#[cfg(feature = "__sync")]
+pub use iter::{
+    paginate, paginate_pages, paginate_pages_with_ctx, paginate_prefetch,
+    paginate_prefetch_with_ctx, paginate_resumable, paginate_with_ctx, PrefetchTask,
+};
 
 #[cfg(all(feature = "__async", not(target_arch = "wasm32")))]
-pub use stream::{paginate, paginate_with_ctx, Paginator};
+pub use stream::{
+    paginate, paginate_concurrent, paginate_concurrent_with_ctx, paginate_pages,
+    paginate_pages_with_ctx, paginate_prefetch, paginate_prefetch_with_ctx, paginate_resumable,
+    paginate_with_ctx, PrefetchTask,
+};
 
 #[cfg(all(feature = "__async", target_arch = "wasm32"))]
-pub use wasm_stream::{paginate, paginate_with_ctx, Paginator};
+pub use wasm_stream::{
+    paginate, paginate_concurrent, paginate_concurrent_with_ctx, paginate_pages,
+    paginate_pages_with_ctx, paginate_prefetch, paginate_prefetch_with_ctx, paginate_resumable,
+    paginate_with_ctx, PrefetchTask,
+};
+
+pub use state::{PagePosition, PaginationState, ResumablePage};
+
+#[cfg(feature = "__async")]
+use crate::model::Page;
+use crate::{ClientError, ClientResult, DEFAULT_PAGINATION_CHUNKS};
+
+#[cfg(feature = "__async")]
+use std::future::Future;
+use std::time::Duration;
+
+#[cfg(feature = "__async")]
+use futures::stream::{iter, Stream, StreamExt};
+
+/// How many times a page is requested again by the concurrent paginators
+/// after being rate limited.
+#[cfg(feature = "__async")]
+const MAX_RATE_LIMIT_RETRIES: u32 = 5;
+
+/// The configuration of an automatically paginated request, which can be set
+/// with the builder methods in [`Paginator`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PaginationOptions {
+    /// The index of the first item to request. By default it's `0`.
+    pub offset: u32,
+    /// The maximum number of items to yield. By default there's no limit.
+    pub max_items: Option<u32>,
+    /// The number of items requested per call. By default it's
+    /// [`Config::pagination_chunks`](crate::Config::pagination_chunks).
+    pub page_size: Option<u32>,
+    /// The maximum number of pages requested at the same time after the
+    /// first one. By default they're requested one after another. Only
+    /// asynchronous clients support this.
+    pub concurrency: Option<usize>,
+}
+
+impl PaginationOptions {
+    /// Returns the limit for the next request after `fetched` items have been
+    /// obtained, or `None` if the maximum number of items has been reached.
+    pub(crate) fn next_limit(&self, fetched: u32) -> Option<u32> {
+        let page_size = self.page_size.unwrap_or(DEFAULT_PAGINATION_CHUNKS).max(1);
+        match self.max_items {
+            Some(max_items) if fetched >= max_items => None,
+            Some(max_items) => Some(page_size.min(max_items - fetched)),
+            None => Some(page_size),
+        }
+    }
+}
+
+/// The paginator returned by resumable paginated requests, which yields every
+/// page of type `Pg` along with the [`PaginationState`] right after it.
+pub type ResumablePaginator<'a, Pg, P> = Paginator<'a, ClientResult<(Pg, PaginationState<P>)>>;
+
+/// Wraps the iterable of items obtained from an automatically paginated
+/// request. It implements `Iterator` if the client is synchronous, and
+/// `Stream` if it's asynchronous.
+///
+/// No requests are made until the first item is requested, so the pagination
+/// can be configured beforehand with the builder methods:
+///
+/// ```ignore
+/// let tracks = spotify
+///     .current_user_saved_tracks(None)
+///     .offset(100)
+///     .max_items(500)
+///     .page_size(20);
+/// ```
+pub struct Paginator<'a, T> {
+    options: PaginationOptions,
+    default_page_size: u32,
+    max_page_size: u32,
+    build: Option<imp::Build<'a, T>>,
+    inner: Option<imp::Inner<'a, T>>,
+}
+
+impl<'a, T> Paginator<'a, T> {
+    /// Creates a paginator that will be lazily built with the configured
+    /// options once it's first used.
+    pub(crate) fn new(build: imp::Build<'a, T>, default_page_size: u32) -> Self {
+        Self {
+            options: PaginationOptions::default(),
+            default_page_size,
+            max_page_size: u32::MAX,
+            build: Some(build),
+            inner: None,
+        }
+    }
+
+    /// Sets the index of the first item to request.
+    #[must_use]
+    pub fn offset(mut self, offset: u32) -> Self {
+        self.options.offset = offset;
+        self
+    }
+
+    /// Sets the maximum number of items to yield. Fewer items may be requested
+    /// in the last call so that no more than these are fetched.
+    #[must_use]
+    pub fn max_items(mut self, max_items: u32) -> Self {
+        self.options.max_items = Some(max_items);
+        self
+    }
+
+    /// Sets the number of items requested per call, overriding
+    /// [`Config::pagination_chunks`](crate::Config::pagination_chunks). It will
+    /// be clamped to the maximum allowed by the endpoint.
+    #[must_use]
+    pub fn page_size(mut self, page_size: u32) -> Self {
+        self.options.page_size = Some(page_size);
+        self
+    }
+
+    /// Requests up to `concurrency` pages at the same time, based on the
+    /// `total` field of the first page. The items are still yielded in order.
+    ///
+    /// The rate limit imposed by Spotify is shared by all the requests, so it
+    /// should be kept low. Requests rejected with `429 Too Many Requests` are
+    /// tried again after the time in their `Retry-After` header, up to 5
+    /// times per page.
+    ///
+    /// ```ignore
+    /// let tracks = spotify.current_user_saved_tracks(None).concurrency(4);
+    /// ```
+    #[cfg(feature = "__async")]
+    #[must_use]
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.options.concurrency = Some(concurrency);
+        self
+    }
+
+    /// Sets the maximum page size allowed by the endpoint, as documented in
+    /// Spotify's reference.
+    #[must_use]
+    pub(crate) fn max_page_size(mut self, max_page_size: u32) -> Self {
+        self.max_page_size = max_page_size;
+        self
+    }
+
+    /// Returns the configured options, with the page size already resolved
+    /// and clamped.
+    #[must_use]
+    pub fn options(&self) -> PaginationOptions {
+        let page_size = self
+            .options
+            .page_size
+            .unwrap_or(self.default_page_size)
+            .clamp(1, self.max_page_size.max(1));
+
+        PaginationOptions {
+            page_size: Some(page_size),
+            ..self.options
+        }
+    }
+
+    /// Returns the underlying iterable, building it the first time.
+    fn inner(&mut self) -> &mut imp::Inner<'a, T> {
+        if let Some(build) = self.build.take() {
+            self.inner = Some(build(self.options()));
+        }
+
+        self.inner
+            .as_mut()
+            .expect("the paginator is always built before its first use")
+    }
+}
+
+/// Returns the `(limit, offset)` pairs left to request after the first page,
+/// based on its `total`.
+///
+/// Spotify may apply a lower limit than the requested one, so the one in the
+/// first page is used when available.
+#[cfg(feature = "__async")]
+fn remaining_offsets<T>(first: &Page<T>, options: &PaginationOptions) -> Vec<(u32, u32)> {
+    let page_size = options
+        .page_size
+        .unwrap_or(DEFAULT_PAGINATION_CHUNKS)
+        .max(1);
+    let limit = match first.limit {
+        0 => page_size,
+        limit => limit.min(page_size),
+    };
+    let fetched = first.items.len() as u32;
+    let end = match options.max_items {
+        Some(max_items) => first.total.min(options.offset.saturating_add(max_items)),
+        None => first.total,
+    };
+
+    if first.next.is_none() || fetched == 0 {
+        Vec::new()
+    } else {
+        (options.offset + fetched..end)
+            .step_by(limit as usize)
+            .map(|offset| (limit.min(end - offset), offset))
+            .collect()
+    }
+}
+
+/// Returns how long to wait before requesting a page again after `retries`
+/// previous attempts. Rate limited requests wait for the time in their
+/// `Retry-After` header, and it's doubled with every attempt if it's missing.
+fn retry_delay(err: &ClientError, retries: u32) -> Duration {
+    let retry_after = match err {
+        ClientError::Http(err) => err.retry_after(),
+        _ => None,
+    };
+    retry_after.unwrap_or_else(|| Duration::from_secs(1 << retries.min(6)))
+}
+
+/// Performs a request of the concurrent paginators, trying again after
+/// waiting if Spotify responds with `429 Too Many Requests`. The wait is
+/// taken from the `Retry-After` header, or doubled with every attempt if
+/// it's missing.
+#[cfg(feature = "__async")]
+async fn request_page<T, Fut, Request>(
+    req: &Request,
+    limit: u32,
+    offset: u32,
+) -> ClientResult<Page<T>>
+where
+    Fut: Future<Output = ClientResult<Page<T>>>,
+    Request: Fn(u32, u32) -> Fut,
+{
+    let mut retries = 0;
+    loop {
+        match req(limit, offset).await {
+            Err(ClientError::Http(err))
+                if err.status_code() == Some(429) && retries < MAX_RATE_LIMIT_RETRIES =>
+            {
+                let wait = retry_delay(&ClientError::Http(err), retries);
+                log::warn!("Rate limited, requesting the page again in {:?}", wait);
+                crate::sync::sleep(wait).await;
+                retries += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// The stream of pages shared by the concurrent paginators, which yields the
+/// first page and then the rest as they're received.
+#[cfg(feature = "__async")]
+fn concurrent_pages<'a, T, Fut, Request>(
+    req: Request,
+    options: PaginationOptions,
+    concurrency: usize,
+    ordered: bool,
+) -> impl Stream<Item = ClientResult<Page<T>>> + 'a
+where
+    T: 'a,
+    Fut: 'a + Future<Output = ClientResult<Page<T>>>,
+    Request: 'a + Fn(u32, u32) -> Fut,
+{
+    use async_stream::stream;
+    stream! {
+        if let Some(limit) = options.next_limit(0) {
+            let first = request_page(&req, limit, options.offset).await?;
+            let offsets = remaining_offsets(&first, &options);
+            yield Ok(first);
+
+            let requests = iter(offsets)
+                .map(|(limit, offset)| request_page(&req, limit, offset));
+            let mut pages = if ordered {
+                requests.buffered(concurrency.max(1)).left_stream()
+            } else {
+                requests.buffer_unordered(concurrency.max(1)).right_stream()
+            };
+            while let Some(page) = pages.next().await {
+                yield Ok(page?);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::PaginationOptions;
+
+    #[test]
+    fn test_next_limit() {
+        let options = PaginationOptions {
+            page_size: Some(20),
+            ..Default::default()
+        };
+        assert_eq!(options.next_limit(0), Some(20));
+        assert_eq!(options.next_limit(1000), Some(20));
+
+        let options = PaginationOptions {
+            page_size: Some(20),
+            max_items: Some(50),
+            ..Default::default()
+        };
+        assert_eq!(options.next_limit(0), Some(20));
+        assert_eq!(options.next_limit(40), Some(10));
+        assert_eq!(options.next_limit(50), None);
+    }
+}
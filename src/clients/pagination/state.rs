@@ -0,0 +1,219 @@
+//! Serializable state of resumable pagination requests.
+
+use crate::model::{CursorBasedPage, Page};
+
+use serde::{Deserialize, Serialize};
+
+/// The position of the next page to request in a resumable pagination.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PagePosition {
+    /// The index of the first item in offset-based endpoints.
+    Offset(u32),
+    /// The `after` cursor in cursor-based endpoints, which is `None` for the
+    /// first page.
+    Cursor(Option<String>),
+}
+
+impl PagePosition {
+    /// Returns the offset, if it's an offset-based position.
+    #[must_use]
+    pub fn offset(&self) -> Option<u32> {
+        match self {
+            Self::Offset(offset) => Some(*offset),
+            Self::Cursor(_) => None,
+        }
+    }
+
+    /// Returns the `after` cursor, if it's a cursor-based position that isn't
+    /// at the first page.
+    #[must_use]
+    pub fn cursor(&self) -> Option<&str> {
+        match self {
+            Self::Offset(_) => None,
+            Self::Cursor(cursor) => cursor.as_deref(),
+        }
+    }
+}
+
+/// The state of a resumable pagination, which can be serialized in order to
+/// continue it later on, even from a different process.
+///
+/// `params` holds whatever is needed to perform the request besides its
+/// position, like the playlist ID or the market, so that the whole request
+/// can be restored from the state.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PaginationState<P> {
+    /// The parameters of the endpoint.
+    pub params: P,
+    /// The position of the next page to request, or `None` once the last one
+    /// has been received.
+    pub position: Option<PagePosition>,
+    /// The number of items received so far, which counts towards
+    /// [`Paginator::max_items`](super::Paginator::max_items).
+    pub fetched: u32,
+}
+
+impl<P> PaginationState<P> {
+    /// Creates the state of a new offset-based pagination. The initial offset
+    /// can be set with [`Paginator::offset`](super::Paginator::offset).
+    #[must_use]
+    pub fn new(params: P) -> Self {
+        Self {
+            params,
+            position: Some(PagePosition::Offset(0)),
+            fetched: 0,
+        }
+    }
+
+    /// Creates the state of a new cursor-based pagination.
+    #[must_use]
+    pub fn with_cursor(params: P) -> Self {
+        Self {
+            params,
+            position: Some(PagePosition::Cursor(None)),
+            fetched: 0,
+        }
+    }
+
+    /// Returns whether the last page has already been received.
+    #[must_use]
+    pub fn is_finished(&self) -> bool {
+        self.position.is_none()
+    }
+
+    /// Applies the initial offset of the paginator if nothing has been
+    /// requested yet.
+    pub(crate) fn start_at(&mut self, offset: u32) {
+        if self.fetched == 0 && self.position == Some(PagePosition::Offset(0)) {
+            self.position = Some(PagePosition::Offset(offset));
+        }
+    }
+
+    /// Moves the state past the given page.
+    pub(crate) fn advance<Pg: ResumablePage>(&mut self, page: &Pg) {
+        self.fetched += page.item_count() as u32;
+        self.position = self
+            .position
+            .as_ref()
+            .and_then(|position| page.next_position(position));
+    }
+}
+
+/// A page that can be requested by a resumable pagination.
+pub trait ResumablePage {
+    /// The number of items in the page.
+    fn item_count(&self) -> usize;
+
+    /// Returns the position of the page after this one, which was requested
+    /// at `position`, or `None` if this is the last one.
+    fn next_position(&self, position: &PagePosition) -> Option<PagePosition>;
+}
+
+impl<T> ResumablePage for Page<T> {
+    fn item_count(&self) -> usize {
+        self.items.len()
+    }
+
+    fn next_position(&self, position: &PagePosition) -> Option<PagePosition> {
+        if self.next.is_none() || self.items.is_empty() {
+            return None;
+        }
+
+        position
+            .offset()
+            .map(|offset| PagePosition::Offset(offset + self.items.len() as u32))
+    }
+}
+
+impl<T> ResumablePage for CursorBasedPage<T> {
+    fn item_count(&self) -> usize {
+        self.items.len()
+    }
+
+    fn next_position(&self, _position: &PagePosition) -> Option<PagePosition> {
+        if self.next.is_none() || self.items.is_empty() {
+            return None;
+        }
+
+        self.cursors
+            .as_ref()
+            .and_then(|cursors| cursors.after.clone())
+            .map(|after| PagePosition::Cursor(Some(after)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{PagePosition, PaginationState};
+    use crate::model::{Cursor, CursorBasedPage, Page};
+
+    #[test]
+    fn test_advance_offset() {
+        let mut state = PaginationState::new("params");
+        state.start_at(10);
+        assert_eq!(state.position, Some(PagePosition::Offset(10)));
+
+        let page = Page {
+            items: vec![1, 2, 3],
+            next: Some("next".to_owned()),
+            ..Page::default()
+        };
+        state.advance(&page);
+        assert_eq!(state.position, Some(PagePosition::Offset(13)));
+        assert_eq!(state.fetched, 3);
+
+        // The initial offset only applies to new paginations
+        state.start_at(0);
+        assert_eq!(state.position, Some(PagePosition::Offset(13)));
+
+        let page = Page {
+            items: vec![4],
+            ..Page::default()
+        };
+        state.advance(&page);
+        assert!(state.is_finished());
+        assert_eq!(state.fetched, 4);
+    }
+
+    #[test]
+    fn test_advance_cursor() {
+        let mut state = PaginationState::with_cursor(());
+        let page = CursorBasedPage {
+            href: String::new(),
+            items: vec![1, 2],
+            limit: 2,
+            next: Some("next".to_owned()),
+            cursors: Some(Cursor {
+                after: Some("abc".to_owned()),
+            }),
+            total: None,
+        };
+        state.advance(&page);
+        assert_eq!(
+            state.position,
+            Some(PagePosition::Cursor(Some("abc".to_owned())))
+        );
+
+        state.advance(&CursorBasedPage { next: None, ..page });
+        assert!(state.is_finished());
+    }
+
+    #[test]
+    fn test_serialize() {
+        let state = PaginationState {
+            params: "37i9dQZF1DXcBWIGoYBM5M".to_owned(),
+            position: Some(PagePosition::Offset(100)),
+            fetched: 100,
+        };
+        let json = serde_json::to_string(&state).unwrap();
+        assert_eq!(
+            json,
+            r#"{"params":"37i9dQZF1DXcBWIGoYBM5M","position":{"offset":100},"fetched":100}"#
+        );
+        assert_eq!(
+            serde_json::from_str::<PaginationState<String>>(&json).unwrap(),
+            state
+        );
+    }
+}
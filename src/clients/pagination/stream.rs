@@ -1,70 +1,412 @@
 //! Asynchronous implementation of automatic pagination requests.
 
+use super::{
+    concurrent_pages, retry_delay, PagePosition, PaginationOptions, PaginationState, Paginator,
+    ResumablePage,
+};
 use crate::{model::Page, ClientResult};
 
-use std::pin::Pin;
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+};
 
-use futures::{future::Future, stream::Stream};
+use futures::{
+    channel::{mpsc, oneshot},
+    future::{BoxFuture, Future},
+    stream::{Stream, StreamExt},
+};
 
-/// Alias for `futures::stream::Stream<Item = T>`, since async mode is enabled.
-pub type Paginator<'a, T> = Pin<Box<dyn Stream<Item = T> + 'a + Send>>;
+/// The underlying stream of a [`Paginator`], since async mode is enabled.
+pub type Inner<'a, T> = Pin<Box<dyn Stream<Item = T> + 'a + Send>>;
+
+/// Builds the underlying stream of a [`Paginator`] given its options.
+pub type Build<'a, T> = Box<dyn FnOnce(PaginationOptions) -> Inner<'a, T> + 'a + Send>;
 
 pub type RequestFuture<'a, T> = Pin<Box<dyn 'a + Future<Output = ClientResult<Page<T>>> + Send>>;
 
+impl<T> Stream for Paginator<'_, T> {
+    type Item = T;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        self.inner().as_mut().poll_next(cx)
+    }
+}
+
+impl<'a, T: 'a> Paginator<'a, T> {
+    /// Applies `f` to the underlying stream, whether it's been built or not.
+    fn map_inner<U, F>(self, f: F) -> Paginator<'a, U>
+    where
+        F: 'a + Fn(Inner<'a, T>) -> Inner<'a, U> + Copy + Send,
+    {
+        Paginator {
+            options: self.options,
+            default_page_size: self.default_page_size,
+            max_page_size: self.max_page_size,
+            build: self
+                .build
+                .map(|build| -> Build<'a, U> { Box::new(move |options| f(build(options))) }),
+            inner: self.inner.map(f),
+        }
+    }
+}
+
+impl<'a, T: 'a + Send> Paginator<'a, ClientResult<Page<T>>> {
+    /// Flattens the paginated pages into their items.
+    pub fn into_items(self) -> Paginator<'a, ClientResult<T>> {
+        use async_stream::stream;
+        self.map_inner(|mut pages| -> Inner<'a, ClientResult<T>> {
+            Box::pin(stream! {
+                while let Some(page) = pages.next().await {
+                    for item in page?.items {
+                        yield Ok(item);
+                    }
+                }
+            })
+        })
+    }
+}
+
 /// This is used to handle paginated requests automatically.
-pub fn paginate_with_ctx<'a, Ctx: 'a + Send, T, Request>(
+pub fn paginate_with_ctx<'a, Ctx: 'a + Send + Sync, T, Request>(
     ctx: Ctx,
     req: Request,
     page_size: u32,
 ) -> Paginator<'a, ClientResult<T>>
 where
     T: 'a + Unpin + Send,
-    Request: 'a + for<'ctx> Fn(&'ctx Ctx, u32, u32) -> RequestFuture<'ctx, T> + Send,
+    Request: 'a + for<'ctx> Fn(&'ctx Ctx, u32, u32) -> RequestFuture<'ctx, T> + Send + Sync,
+{
+    paginate_pages_with_ctx(ctx, req, page_size).into_items()
+}
+
+pub fn paginate<'a, T, Fut, Request>(req: Request, page_size: u32) -> Paginator<'a, ClientResult<T>>
+where
+    T: 'a + Unpin + Send,
+    Fut: Future<Output = ClientResult<Page<T>>> + Send,
+    Request: 'a + Fn(u32, u32) -> Fut + Send + Sync,
+{
+    paginate_pages(req, page_size).into_items()
+}
+
+/// Same as [`paginate_with_ctx`], but the whole pages are yielded instead of
+/// their items.
+pub fn paginate_pages_with_ctx<'a, Ctx: 'a + Send + Sync, T, Request>(
+    ctx: Ctx,
+    req: Request,
+    page_size: u32,
+) -> Paginator<'a, ClientResult<Page<T>>>
+where
+    T: 'a + Unpin + Send,
+    Request: 'a + for<'ctx> Fn(&'ctx Ctx, u32, u32) -> RequestFuture<'ctx, T> + Send + Sync,
 {
     use async_stream::stream;
-    let mut offset = 0;
-    Box::pin(stream! {
-        loop {
-            let request = req(&ctx, page_size, offset);
-            let page = request.await?;
-            offset += page.items.len() as u32;
-            for item in page.items {
-                yield Ok(item);
-            }
-            if page.next.is_none() {
-                break;
+    let build = move |options: PaginationOptions| -> Inner<'a, ClientResult<Page<T>>> {
+        // The requests borrow the context, so it has to be owned by the
+        // stream itself.
+        Box::pin(stream! {
+            let ctx = &ctx;
+            let mut pages = page_stream(move |limit, offset| req(ctx, limit, offset), options);
+            while let Some(page) = pages.next().await {
+                yield page;
             }
-        }
-    })
+        })
+    };
+
+    Paginator::new(Box::new(build), page_size)
 }
 
-pub fn paginate<'a, T, Fut, Request>(req: Request, page_size: u32) -> Paginator<'a, ClientResult<T>>
+/// Same as [`paginate`], but the whole pages are yielded instead of their
+/// items.
+///
+/// The pagination ends after a page without a `next` URL, an empty page or an
+/// error.
+pub fn paginate_pages<'a, T, Fut, Request>(
+    req: Request,
+    page_size: u32,
+) -> Paginator<'a, ClientResult<Page<T>>>
 where
     T: 'a + Unpin + Send,
     Fut: Future<Output = ClientResult<Page<T>>> + Send,
-    Request: 'a + Fn(u32, u32) -> Fut + Send,
+    Request: 'a + Fn(u32, u32) -> Fut + Send + Sync,
+{
+    let build = move |options: PaginationOptions| page_stream(req, options);
+    Paginator::new(Box::new(build), page_size)
+}
+
+/// Builds the stream of pages used by [`paginate_pages`] given its options.
+/// They're requested concurrently if [`PaginationOptions::concurrency`] is
+/// set.
+fn page_stream<'a, T, Fut, Request>(
+    req: Request,
+    options: PaginationOptions,
+) -> Inner<'a, ClientResult<Page<T>>>
+where
+    T: 'a + Unpin + Send,
+    Fut: Future<Output = ClientResult<Page<T>>> + Send,
+    Request: 'a + Fn(u32, u32) -> Fut + Send + Sync,
 {
     use async_stream::stream;
-    let mut offset = 0;
+    if let Some(concurrency) = options.concurrency {
+        return Box::pin(stream! {
+            let pages = concurrent_pages(&req, options, concurrency, true);
+            futures::pin_mut!(pages);
+            while let Some(page) = pages.next().await {
+                yield page;
+            }
+        });
+    }
+
+    let mut offset = options.offset;
+    let mut fetched = 0;
     Box::pin(stream! {
-        loop {
-            let request = req(page_size, offset);
+        while let Some(limit) = options.next_limit(fetched) {
+            let request = req(limit, offset);
             let page = request.await?;
-            offset += page.items.len() as u32;
-            for item in page.items {
-                yield Ok(item);
-            }
-            if page.next.is_none() {
+            let len = page.items.len() as u32;
+            offset += len;
+            fetched += len;
+            let last = page.next.is_none() || len == 0;
+            yield Ok(page);
+            if last {
                 break;
             }
         }
     })
 }
 
+/// The work of [`paginate_prefetch`] that has to be spawned, which requests
+/// the pages in the background.
+pub type PrefetchTask = BoxFuture<'static, ()>;
+
+/// Where the pages requested by [`serve_pages`] are sent.
+type PageReply<T> = oneshot::Sender<Option<ClientResult<Page<T>>>>;
+
+/// Same as [`paginate`], but the next page is requested in the background
+/// while the current one is being consumed. Only a single page is requested
+/// ahead of the one being consumed.
+///
+/// The requests are performed by a task passed to `spawn`, which should run
+/// it on the async runtime in use, e.g. `|task| { tokio::spawn(task); }`. Since it's
+/// spawned, the request can't borrow anything, so the client has to be cloned
+/// into it. Dropping the paginator makes the task finish after its current
+/// request.
+pub fn paginate_prefetch<'a, T, Fut, Request, Spawn>(
+    req: Request,
+    page_size: u32,
+    spawn: Spawn,
+) -> Paginator<'a, ClientResult<T>>
+where
+    T: 'static + Unpin + Send,
+    Fut: 'static + Future<Output = ClientResult<Page<T>>> + Send,
+    Request: 'static + Fn(u32, u32) -> Fut + Send + Sync,
+    Spawn: 'a + FnOnce(PrefetchTask) + Send,
+{
+    let build = move |options: PaginationOptions| -> Inner<'a, ClientResult<Page<T>>> {
+        let (requests, received) = mpsc::unbounded();
+        spawn(Box::pin(serve_pages(page_stream(req, options), received)));
+        prefetched_pages(requests)
+    };
+
+    Paginator::new(Box::new(build), page_size).into_items()
+}
+
+/// Same as [`paginate_prefetch`], but the request is given a context owned by
+/// the spawned task, like with [`paginate_with_ctx`].
+pub fn paginate_prefetch_with_ctx<'a, Ctx, T, Request, Spawn>(
+    ctx: Ctx,
+    req: Request,
+    page_size: u32,
+    spawn: Spawn,
+) -> Paginator<'a, ClientResult<T>>
+where
+    Ctx: 'static + Send + Sync,
+    T: 'static + Unpin + Send,
+    Request: 'static + for<'ctx> Fn(&'ctx Ctx, u32, u32) -> RequestFuture<'ctx, T> + Send + Sync,
+    Spawn: 'a + FnOnce(PrefetchTask) + Send,
+{
+    let build = move |options: PaginationOptions| -> Inner<'a, ClientResult<Page<T>>> {
+        let (requests, received) = mpsc::unbounded();
+        spawn(Box::pin(async move {
+            let pages = page_stream(|limit, offset| req(&ctx, limit, offset), options);
+            serve_pages(pages, received).await;
+        }));
+        prefetched_pages(requests)
+    };
+
+    Paginator::new(Box::new(build), page_size).into_items()
+}
+
+/// Requests a page every time [`prefetched_pages`] asks for one, and sends it
+/// back. `None` is sent after the last page.
+async fn serve_pages<T>(
+    mut pages: Inner<'_, ClientResult<Page<T>>>,
+    mut requests: mpsc::UnboundedReceiver<PageReply<T>>,
+) {
+    while let Some(reply) = requests.next().await {
+        let page = pages.next().await;
+        let last = page.is_none();
+        if reply.send(page).is_err() || last {
+            break;
+        }
+    }
+}
+
+/// Receives the pages from [`serve_pages`]. The next one is asked for right
+/// before yielding the current one, so that it's requested while the current
+/// one is being consumed, but not the one after it.
+fn prefetched_pages<'a, T: 'a + Send>(
+    requests: mpsc::UnboundedSender<PageReply<T>>,
+) -> Inner<'a, ClientResult<Page<T>>> {
+    use async_stream::stream;
+    Box::pin(stream! {
+        let (reply, mut next) = oneshot::channel();
+        let _ = requests.unbounded_send(reply);
+        while let Ok(Some(page)) = next.await {
+            let (reply, following) = oneshot::channel();
+            let _ = requests.unbounded_send(reply);
+            next = following;
+            yield page;
+        }
+    })
+}
+
+/// This is used to handle paginated requests that can be resumed later on.
+/// The pages are yielded along with the state right after them, which may be
+/// saved and passed to this function again to continue where it was left.
+///
+/// The request is given the parameters in `state.params`, the limit and the
+/// position to request. Failed requests are yielded as errors without
+/// advancing the state, so that the same page is requested again when the
+/// next element is polled. Before that, it waits for the time in the
+/// `Retry-After` header if it was rate limited, or for one second doubled
+/// with every consecutive failure otherwise. The pagination ends after
+/// `max_retries` consecutive failures, after a page without a `next` URL or
+/// after an empty page.
+pub fn paginate_resumable<'a, P, Pg, Fut, Request>(
+    state: PaginationState<P>,
+    req: Request,
+    page_size: u32,
+    max_retries: u32,
+) -> Paginator<'a, ClientResult<(Pg, PaginationState<P>)>>
+where
+    P: 'a + Clone + Send,
+    Pg: 'a + ResumablePage + Send,
+    Fut: Future<Output = ClientResult<Pg>> + Send,
+    Request: 'a + Fn(P, u32, PagePosition) -> Fut + Send,
+{
+    use async_stream::stream;
+    let build =
+        move |options: PaginationOptions| -> Inner<'a, ClientResult<(Pg, PaginationState<P>)>> {
+            let mut state = state;
+            state.start_at(options.offset);
+            let mut failures = 0;
+            Box::pin(stream! {
+                while let Some(position) = state.position.clone() {
+                    let limit = match options.next_limit(state.fetched) {
+                        Some(limit) => limit,
+                        None => break,
+                    };
+
+                    match req(state.params.clone(), limit, position).await {
+                        Ok(page) => {
+                            failures = 0;
+                            state.advance(&page);
+                            yield Ok((page, state.clone()));
+                        }
+                        Err(err) => {
+                            let wait = retry_delay(&err, failures);
+                            failures += 1;
+                            yield Err(err);
+                            if failures > max_retries {
+                                break;
+                            }
+
+                            log::warn!("Requesting the page again in {:?}", wait);
+                            crate::sync::sleep(wait).await;
+                        }
+                    }
+                }
+            })
+        };
+
+    Paginator::new(Box::new(build), page_size)
+}
+
+/// Same as [`paginate_with_ctx`], but the pages after the first one are
+/// requested concurrently. See [`paginate_concurrent`] for more information.
+pub fn paginate_concurrent_with_ctx<'a, Ctx: 'a + Send + Sync, T, Request>(
+    ctx: Ctx,
+    req: Request,
+    page_size: u32,
+    concurrency: usize,
+    ordered: bool,
+) -> Paginator<'a, ClientResult<T>>
+where
+    T: 'a + Unpin + Send,
+    Request: 'a + for<'ctx> Fn(&'ctx Ctx, u32, u32) -> RequestFuture<'ctx, T> + Send + Sync,
+{
+    use async_stream::stream;
+    let build = move |options: PaginationOptions| -> Inner<'a, ClientResult<Page<T>>> {
+        Box::pin(stream! {
+            let request = |limit, offset| req(&ctx, limit, offset);
+            let pages = concurrent_pages(request, options, concurrency, ordered);
+            futures::pin_mut!(pages);
+            while let Some(page) = pages.next().await {
+                yield page;
+            }
+        })
+    };
+
+    Paginator::new(Box::new(build), page_size).into_items()
+}
+
+/// This is used to handle paginated requests concurrently. The first page is
+/// requested to find out the total number of items, and the rest of offsets
+/// are then requested with up to `concurrency` requests in flight at the same
+/// time.
+///
+/// If `ordered` is true, the items are yielded in the same order as the
+/// sequential paginator. Otherwise, they're yielded as soon as their page is
+/// received.
+///
+/// Since the requests are still performed by the client's endpoints, they go
+/// through the same authentication and token refreshing logic. The rate limit
+/// imposed by Spotify is shared by all of them, so `concurrency` should be
+/// kept low. Requests rejected with `429 Too Many Requests` are tried again
+/// after the time in their `Retry-After` header, up to 5 times per page.
+pub fn paginate_concurrent<'a, T, Fut, Request>(
+    req: Request,
+    page_size: u32,
+    concurrency: usize,
+    ordered: bool,
+) -> Paginator<'a, ClientResult<T>>
+where
+    T: 'a + Unpin + Send,
+    Fut: Future<Output = ClientResult<Page<T>>> + Send,
+    Request: 'a + Fn(u32, u32) -> Fut + Send + Sync,
+{
+    use async_stream::stream;
+    let build = move |options: PaginationOptions| -> Inner<'a, ClientResult<Page<T>>> {
+        Box::pin(stream! {
+            let pages = concurrent_pages(&req, options, concurrency, ordered);
+            futures::pin_mut!(pages);
+            while let Some(page) = pages.next().await {
+                yield page;
+            }
+        })
+    };
+
+    Paginator::new(Box::new(build), page_size).into_items()
+}
+
 #[cfg(test)]
 mod test {
-    use super::paginate;
-    use crate::model::Page;
+    use super::{
+        paginate, paginate_concurrent, paginate_pages, paginate_prefetch, paginate_resumable,
+    };
+    use crate::clients::pagination::PaginationState;
+    use crate::{model::Page, ClientError};
     use futures::{future, StreamExt};
     use std::future::Future;
 
@@ -96,4 +438,290 @@ mod test {
         }
         schedule_future(test());
     }
+
+    fn fake_page(total: u32, limit: u32, offset: u32) -> Page<u32> {
+        let end = total.min(offset + limit);
+        Page {
+            items: (offset..end).collect(),
+            limit,
+            offset,
+            total,
+            next: (end < total).then(|| String::from("next")),
+            ..Page::default()
+        }
+    }
+
+    #[test]
+    fn test_concurrent_ordered() {
+        let paginator = paginate_concurrent(
+            |limit, offset| future::ok(fake_page(10, limit, offset)),
+            3,
+            4,
+            true,
+        );
+
+        let items = futures::executor::block_on(paginator.map(Result::unwrap).collect::<Vec<_>>());
+        assert_eq!(items, (0..10).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_concurrent_unordered() {
+        let paginator = paginate_concurrent(
+            |limit, offset| future::ok(fake_page(10, limit, offset)),
+            3,
+            0,
+            false,
+        );
+
+        let mut items =
+            futures::executor::block_on(paginator.map(Result::unwrap).collect::<Vec<_>>());
+        items.sort_unstable();
+        assert_eq!(items, (0..10).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_concurrent_lower_limit() {
+        // The endpoint only returns up to 2 items per page even though 5 were
+        // requested, so the offsets must follow the actual limit.
+        let paginator = paginate_concurrent(
+            |limit, offset| future::ok(fake_page(7, limit.min(2), offset)),
+            5,
+            2,
+            true,
+        );
+
+        let items = futures::executor::block_on(paginator.map(Result::unwrap).collect::<Vec<_>>());
+        assert_eq!(items, (0..7).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_options() {
+        let paginator = paginate(
+            |limit, offset| future::ok(fake_page(100, limit, offset)),
+            10,
+        )
+        .offset(5)
+        .max_items(23);
+        assert_eq!(paginator.options().page_size, Some(10));
+
+        let items = futures::executor::block_on(paginator.map(Result::unwrap).collect::<Vec<_>>());
+        assert_eq!(items, (5..28).collect::<Vec<_>>());
+
+        let paginator = paginate_concurrent(
+            |limit, offset| future::ok(fake_page(100, limit, offset)),
+            10,
+            3,
+            true,
+        )
+        .offset(5)
+        .max_items(23);
+
+        let items = futures::executor::block_on(paginator.map(Result::unwrap).collect::<Vec<_>>());
+        assert_eq!(items, (5..28).collect::<Vec<_>>());
+    }
+
+    #[tokio::test]
+    async fn test_concurrency() {
+        use std::sync::{
+            atomic::{AtomicU32, Ordering},
+            Arc,
+        };
+
+        let in_flight = Arc::new(AtomicU32::new(0));
+        let max_in_flight = Arc::new(AtomicU32::new(0));
+        let paginator = paginate(
+            |limit, offset| {
+                let in_flight = Arc::clone(&in_flight);
+                let max_in_flight = Arc::clone(&max_in_flight);
+                async move {
+                    let current = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                    max_in_flight.fetch_max(current, Ordering::SeqCst);
+                    tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+                    in_flight.fetch_sub(1, Ordering::SeqCst);
+                    Ok(fake_page(10, limit, offset))
+                }
+            },
+            2,
+        )
+        .concurrency(3);
+        assert_eq!(paginator.options().concurrency, Some(3));
+
+        // The items are still yielded in order
+        let items = paginator.map(Result::unwrap).collect::<Vec<_>>().await;
+        assert_eq!(items, (0..10).collect::<Vec<_>>());
+        assert_eq!(max_in_flight.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn test_page_size_clamped() {
+        let paginator = paginate(|_, _| future::ok(Page::<u32>::default()), 10)
+            .max_page_size(50)
+            .page_size(100);
+        assert_eq!(paginator.options().page_size, Some(50));
+
+        let paginator = paginate(|_, _| future::ok(Page::<u32>::default()), 10).page_size(0);
+        assert_eq!(paginator.options().page_size, Some(1));
+    }
+
+    #[test]
+    fn test_pages() {
+        let paginator = paginate_pages(|limit, offset| future::ok(fake_page(10, limit, offset)), 4);
+
+        let pages = futures::executor::block_on(paginator.map(Result::unwrap).collect::<Vec<_>>());
+        let offsets = pages.iter().map(|page| page.offset).collect::<Vec<_>>();
+        assert_eq!(offsets, [0, 4, 8]);
+        assert!(pages.iter().all(|page| page.total == 10));
+    }
+
+    #[test]
+    fn test_pages_end() {
+        // An empty page ends the pagination even if it has a `next` URL
+        let paginator = paginate_pages(
+            |limit, offset| {
+                let mut page = fake_page(10, limit, offset);
+                if offset >= 4 {
+                    page.items.clear();
+                }
+                future::ok(page)
+            },
+            4,
+        );
+        let pages = futures::executor::block_on(paginator.collect::<Vec<_>>());
+        assert_eq!(pages.len(), 2);
+
+        // And so does an error, which is yielded last
+        let paginator = paginate_pages(
+            |limit, offset| {
+                if offset >= 4 {
+                    future::err(ClientError::InvalidToken)
+                } else {
+                    future::ok(fake_page(10, limit, offset))
+                }
+            },
+            4,
+        );
+        let pages = futures::executor::block_on(paginator.collect::<Vec<_>>());
+        assert_eq!(pages.len(), 2);
+        assert!(matches!(pages[1], Err(ClientError::InvalidToken)));
+    }
+
+    #[test]
+    fn test_resumable() {
+        // The state is yielded with every page, and can be used to continue
+        // the pagination with a new paginator
+        let paginator = paginate_resumable(
+            PaginationState::new(10),
+            |total, limit, position| {
+                future::ok(fake_page(total, limit, position.offset().unwrap()))
+            },
+            4,
+            0,
+        );
+        let mut steps = futures::executor::block_on(paginator.take(1).collect::<Vec<_>>());
+        let (page, state) = steps.remove(0).unwrap();
+        assert_eq!(page.items, [0, 1, 2, 3]);
+
+        let state = serde_json::from_str(&serde_json::to_string(&state).unwrap()).unwrap();
+        let paginator = paginate_resumable(
+            state,
+            |total: u32, limit, position| {
+                future::ok(fake_page(total, limit, position.offset().unwrap()))
+            },
+            4,
+            0,
+        );
+        let steps = futures::executor::block_on(paginator.map(Result::unwrap).collect::<Vec<_>>());
+        let items = steps
+            .into_iter()
+            .flat_map(|(page, _)| page.items)
+            .collect::<Vec<_>>();
+        assert_eq!(items, (4..10).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_resumable_retry() {
+        use std::{
+            sync::atomic::{AtomicU32, Ordering},
+            time::{Duration, Instant},
+        };
+
+        // Every page fails on its first attempt
+        let start = Instant::now();
+        let attempts = AtomicU32::new(0);
+        let paginator = paginate_resumable(
+            PaginationState::new(()),
+            |_, limit, position| {
+                if attempts.fetch_add(1, Ordering::SeqCst) % 2 == 0 {
+                    future::err(ClientError::InvalidToken)
+                } else {
+                    future::ok(fake_page(6, limit, position.offset().unwrap()))
+                }
+            },
+            2,
+            1,
+        );
+        let steps = futures::executor::block_on(paginator.collect::<Vec<_>>());
+        assert_eq!(steps.len(), 6);
+        let items = steps
+            .into_iter()
+            .filter_map(Result::ok)
+            .flat_map(|(page, _)| page.items)
+            .collect::<Vec<_>>();
+        assert_eq!(items, (0..6).collect::<Vec<_>>());
+        // Waiting a second before each retry
+        assert!(start.elapsed() >= Duration::from_secs(3));
+
+        // Without retries, the pagination ends after the first error
+        let paginator = paginate_resumable(
+            PaginationState::new(()),
+            |_, _, _| future::err::<Page<u32>, _>(ClientError::InvalidToken),
+            2,
+            0,
+        );
+        let steps = futures::executor::block_on(paginator.collect::<Vec<_>>());
+        assert_eq!(steps.len(), 1);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_prefetch() {
+        use std::sync::{
+            atomic::{AtomicU32, Ordering},
+            Arc,
+        };
+
+        let requests = Arc::new(AtomicU32::new(0));
+        let counter = Arc::clone(&requests);
+        let mut paginator = paginate_prefetch(
+            move |limit, offset| {
+                counter.fetch_add(1, Ordering::SeqCst);
+                future::ok(fake_page(9, limit, offset))
+            },
+            3,
+            |task| {
+                tokio::spawn(task);
+            },
+        );
+
+        // Nothing is requested until the paginator is first used
+        assert_eq!(requests.load(Ordering::SeqCst), 0);
+        assert_eq!(paginator.next().await.unwrap().unwrap(), 0);
+
+        // The next page is requested while the first one is consumed, without
+        // polling the paginator, but not the one after it
+        for _ in 0..1000 {
+            if requests.load(Ordering::SeqCst) >= 2 {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        assert_eq!(requests.load(Ordering::SeqCst), 2);
+
+        let mut items = vec![0];
+        while let Some(item) = paginator.next().await {
+            items.push(item.unwrap());
+        }
+        assert_eq!(items, (0..9).collect::<Vec<_>>());
+        assert_eq!(requests.load(Ordering::SeqCst), 3);
+    }
 }
@@ -1,62 +1,398 @@
 //! Asynchronous implementation of automatic pagination requests.
 
+use super::{
+    concurrent_pages, retry_delay, PagePosition, PaginationOptions, PaginationState, Paginator,
+    ResumablePage,
+};
 use crate::{model::Page, ClientResult};
 
-use std::pin::Pin;
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+};
 
-use futures::{future::Future, stream::Stream};
+use futures::{
+    channel::{mpsc, oneshot},
+    future::{Future, LocalBoxFuture},
+    stream::{Stream, StreamExt},
+};
 
-/// Alias for `futures::stream::Stream<Item = T>`, since async mode is enabled.
-pub type Paginator<'a, T> = Pin<Box<dyn Stream<Item = T> + 'a>>;
+/// The underlying stream of a [`Paginator`], since async mode is enabled.
+pub type Inner<'a, T> = Pin<Box<dyn Stream<Item = T> + 'a>>;
+
+/// Builds the underlying stream of a [`Paginator`] given its options.
+pub type Build<'a, T> = Box<dyn FnOnce(PaginationOptions) -> Inner<'a, T> + 'a>;
 
 pub type RequestFuture<'a, T> = Pin<Box<dyn 'a + Future<Output = ClientResult<Page<T>>>>>;
 
+impl<T> Stream for Paginator<'_, T> {
+    type Item = T;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        self.inner().as_mut().poll_next(cx)
+    }
+}
+
+impl<'a, T: 'a> Paginator<'a, T> {
+    /// Applies `f` to the underlying stream, whether it's been built or not.
+    fn map_inner<U, F>(self, f: F) -> Paginator<'a, U>
+    where
+        F: 'a + Fn(Inner<'a, T>) -> Inner<'a, U> + Copy,
+    {
+        Paginator {
+            options: self.options,
+            default_page_size: self.default_page_size,
+            max_page_size: self.max_page_size,
+            build: self
+                .build
+                .map(|build| -> Build<'a, U> { Box::new(move |options| f(build(options))) }),
+            inner: self.inner.map(f),
+        }
+    }
+}
+
+impl<'a, T: 'a> Paginator<'a, ClientResult<Page<T>>> {
+    /// Flattens the paginated pages into their items.
+    pub fn into_items(self) -> Paginator<'a, ClientResult<T>> {
+        use async_stream::stream;
+        self.map_inner(|mut pages| -> Inner<'a, ClientResult<T>> {
+            Box::pin(stream! {
+                while let Some(page) = pages.next().await {
+                    for item in page?.items {
+                        yield Ok(item);
+                    }
+                }
+            })
+        })
+    }
+}
+
 /// This is used to handle paginated requests automatically.
 pub fn paginate_with_ctx<'a, Ctx: 'a, T, Request>(
     ctx: Ctx,
     req: Request,
     page_size: u32,
 ) -> Paginator<'a, ClientResult<T>>
+where
+    T: 'a + Unpin,
+    Request: 'a + for<'ctx> Fn(&'ctx Ctx, u32, u32) -> RequestFuture<'ctx, T>,
+{
+    paginate_pages_with_ctx(ctx, req, page_size).into_items()
+}
+
+pub fn paginate<'a, T, Fut, Request>(req: Request, page_size: u32) -> Paginator<'a, ClientResult<T>>
+where
+    T: 'a + Unpin,
+    Fut: Future<Output = ClientResult<Page<T>>>,
+    Request: 'a + Fn(u32, u32) -> Fut,
+{
+    paginate_pages(req, page_size).into_items()
+}
+
+/// Same as [`paginate_with_ctx`], but the whole pages are yielded instead of
+/// their items.
+pub fn paginate_pages_with_ctx<'a, Ctx: 'a, T, Request>(
+    ctx: Ctx,
+    req: Request,
+    page_size: u32,
+) -> Paginator<'a, ClientResult<Page<T>>>
 where
     T: 'a + Unpin,
     Request: 'a + for<'ctx> Fn(&'ctx Ctx, u32, u32) -> RequestFuture<'ctx, T>,
 {
     use async_stream::stream;
-    let mut offset = 0;
-    Box::pin(stream! {
-        loop {
-            let request = req(&ctx, page_size, offset);
-            let page = request.await?;
-            offset += page.items.len() as u32;
-            for item in page.items {
-                yield Ok(item);
+    let build = move |options: PaginationOptions| -> Inner<'a, ClientResult<Page<T>>> {
+        // The requests borrow the context, so it has to be owned by the
+        // stream itself.
+        Box::pin(stream! {
+            let ctx = &ctx;
+            let mut pages = page_stream(move |limit, offset| req(ctx, limit, offset), options);
+            while let Some(page) = pages.next().await {
+                yield page;
             }
-            if page.next.is_none() {
-                break;
-            }
-        }
-    })
+        })
+    };
+
+    Paginator::new(Box::new(build), page_size)
 }
 
-pub fn paginate<'a, T, Fut, Request>(req: Request, page_size: u32) -> Paginator<'a, ClientResult<T>>
+/// Same as [`paginate`], but the whole pages are yielded instead of their
+/// items.
+///
+/// The pagination ends after a page without a `next` URL, an empty page or an
+/// error.
+pub fn paginate_pages<'a, T, Fut, Request>(
+    req: Request,
+    page_size: u32,
+) -> Paginator<'a, ClientResult<Page<T>>>
+where
+    T: 'a + Unpin,
+    Fut: Future<Output = ClientResult<Page<T>>>,
+    Request: 'a + Fn(u32, u32) -> Fut,
+{
+    let build = move |options: PaginationOptions| page_stream(req, options);
+    Paginator::new(Box::new(build), page_size)
+}
+
+/// Builds the stream of pages used by [`paginate_pages`] given its options.
+/// They're requested concurrently if [`PaginationOptions::concurrency`] is
+/// set.
+fn page_stream<'a, T, Fut, Request>(
+    req: Request,
+    options: PaginationOptions,
+) -> Inner<'a, ClientResult<Page<T>>>
 where
     T: 'a + Unpin,
     Fut: Future<Output = ClientResult<Page<T>>>,
     Request: 'a + Fn(u32, u32) -> Fut,
 {
     use async_stream::stream;
-    let mut offset = 0;
+    if let Some(concurrency) = options.concurrency {
+        return Box::pin(stream! {
+            let pages = concurrent_pages(&req, options, concurrency, true);
+            futures::pin_mut!(pages);
+            while let Some(page) = pages.next().await {
+                yield page;
+            }
+        });
+    }
+
+    let mut offset = options.offset;
+    let mut fetched = 0;
     Box::pin(stream! {
-        loop {
-            let request = req(page_size, offset);
+        while let Some(limit) = options.next_limit(fetched) {
+            let request = req(limit, offset);
             let page = request.await?;
-            offset += page.items.len() as u32;
-            for item in page.items {
-                yield Ok(item);
-            }
-            if page.next.is_none() {
+            let len = page.items.len() as u32;
+            offset += len;
+            fetched += len;
+            let last = page.next.is_none() || len == 0;
+            yield Ok(page);
+            if last {
                 break;
             }
         }
     })
 }
+
+/// The work of [`paginate_prefetch`] that has to be spawned, which requests
+/// the pages in the background.
+pub type PrefetchTask = LocalBoxFuture<'static, ()>;
+
+/// Where the pages requested by [`serve_pages`] are sent.
+type PageReply<T> = oneshot::Sender<Option<ClientResult<Page<T>>>>;
+
+/// Same as [`paginate`], but the next page is requested in the background
+/// while the current one is being consumed. Only a single page is requested
+/// ahead of the one being consumed.
+///
+/// The requests are performed by a task passed to `spawn`, which should run
+/// it on the async runtime in use, e.g. `wasm_bindgen_futures::spawn_local`. Since it's
+/// spawned, the request can't borrow anything, so the client has to be cloned
+/// into it. Dropping the paginator makes the task finish after its current
+/// request.
+pub fn paginate_prefetch<'a, T, Fut, Request, Spawn>(
+    req: Request,
+    page_size: u32,
+    spawn: Spawn,
+) -> Paginator<'a, ClientResult<T>>
+where
+    T: 'static + Unpin,
+    Fut: 'static + Future<Output = ClientResult<Page<T>>>,
+    Request: 'static + Fn(u32, u32) -> Fut,
+    Spawn: 'a + FnOnce(PrefetchTask),
+{
+    let build = move |options: PaginationOptions| -> Inner<'a, ClientResult<Page<T>>> {
+        let (requests, received) = mpsc::unbounded();
+        spawn(Box::pin(serve_pages(page_stream(req, options), received)));
+        prefetched_pages(requests)
+    };
+
+    Paginator::new(Box::new(build), page_size).into_items()
+}
+
+/// Same as [`paginate_prefetch`], but the request is given a context owned by
+/// the spawned task, like with [`paginate_with_ctx`].
+pub fn paginate_prefetch_with_ctx<'a, Ctx, T, Request, Spawn>(
+    ctx: Ctx,
+    req: Request,
+    page_size: u32,
+    spawn: Spawn,
+) -> Paginator<'a, ClientResult<T>>
+where
+    Ctx: 'static,
+    T: 'static + Unpin,
+    Request: 'static + for<'ctx> Fn(&'ctx Ctx, u32, u32) -> RequestFuture<'ctx, T>,
+    Spawn: 'a + FnOnce(PrefetchTask),
+{
+    let build = move |options: PaginationOptions| -> Inner<'a, ClientResult<Page<T>>> {
+        let (requests, received) = mpsc::unbounded();
+        spawn(Box::pin(async move {
+            let pages = page_stream(|limit, offset| req(&ctx, limit, offset), options);
+            serve_pages(pages, received).await;
+        }));
+        prefetched_pages(requests)
+    };
+
+    Paginator::new(Box::new(build), page_size).into_items()
+}
+
+/// Requests a page every time [`prefetched_pages`] asks for one, and sends it
+/// back. `None` is sent after the last page.
+async fn serve_pages<T>(
+    mut pages: Inner<'_, ClientResult<Page<T>>>,
+    mut requests: mpsc::UnboundedReceiver<PageReply<T>>,
+) {
+    while let Some(reply) = requests.next().await {
+        let page = pages.next().await;
+        let last = page.is_none();
+        if reply.send(page).is_err() || last {
+            break;
+        }
+    }
+}
+
+/// Receives the pages from [`serve_pages`]. The next one is asked for right
+/// before yielding the current one, so that it's requested while the current
+/// one is being consumed, but not the one after it.
+fn prefetched_pages<'a, T: 'a>(
+    requests: mpsc::UnboundedSender<PageReply<T>>,
+) -> Inner<'a, ClientResult<Page<T>>> {
+    use async_stream::stream;
+    Box::pin(stream! {
+        let (reply, mut next) = oneshot::channel();
+        let _ = requests.unbounded_send(reply);
+        while let Ok(Some(page)) = next.await {
+            let (reply, following) = oneshot::channel();
+            let _ = requests.unbounded_send(reply);
+            next = following;
+            yield page;
+        }
+    })
+}
+
+/// This is used to handle paginated requests that can be resumed later on.
+/// The pages are yielded along with the state right after them, which may be
+/// saved and passed to this function again to continue where it was left.
+///
+/// The request is given the parameters in `state.params`, the limit and the
+/// position to request. Failed requests are yielded as errors without
+/// advancing the state, so that the same page is requested again when the
+/// next element is polled. Before that, it waits for the time in the
+/// `Retry-After` header if it was rate limited, or for one second doubled
+/// with every consecutive failure otherwise. The pagination ends after
+/// `max_retries` consecutive failures, after a page without a `next` URL or
+/// after an empty page.
+pub fn paginate_resumable<'a, P, Pg, Fut, Request>(
+    state: PaginationState<P>,
+    req: Request,
+    page_size: u32,
+    max_retries: u32,
+) -> Paginator<'a, ClientResult<(Pg, PaginationState<P>)>>
+where
+    P: 'a + Clone,
+    Pg: 'a + ResumablePage,
+    Fut: Future<Output = ClientResult<Pg>>,
+    Request: 'a + Fn(P, u32, PagePosition) -> Fut,
+{
+    use async_stream::stream;
+    let build =
+        move |options: PaginationOptions| -> Inner<'a, ClientResult<(Pg, PaginationState<P>)>> {
+            let mut state = state;
+            state.start_at(options.offset);
+            let mut failures = 0;
+            Box::pin(stream! {
+                while let Some(position) = state.position.clone() {
+                    let limit = match options.next_limit(state.fetched) {
+                        Some(limit) => limit,
+                        None => break,
+                    };
+
+                    match req(state.params.clone(), limit, position).await {
+                        Ok(page) => {
+                            failures = 0;
+                            state.advance(&page);
+                            yield Ok((page, state.clone()));
+                        }
+                        Err(err) => {
+                            let wait = retry_delay(&err, failures);
+                            failures += 1;
+                            yield Err(err);
+                            if failures > max_retries {
+                                break;
+                            }
+
+                            log::warn!("Requesting the page again in {:?}", wait);
+                            crate::sync::sleep(wait).await;
+                        }
+                    }
+                }
+            })
+        };
+
+    Paginator::new(Box::new(build), page_size)
+}
+
+/// Same as [`paginate_with_ctx`], but the pages after the first one are
+/// requested concurrently. See [`paginate_concurrent`] for more information.
+pub fn paginate_concurrent_with_ctx<'a, Ctx: 'a, T, Request>(
+    ctx: Ctx,
+    req: Request,
+    page_size: u32,
+    concurrency: usize,
+    ordered: bool,
+) -> Paginator<'a, ClientResult<T>>
+where
+    T: 'a + Unpin,
+    Request: 'a + for<'ctx> Fn(&'ctx Ctx, u32, u32) -> RequestFuture<'ctx, T>,
+{
+    use async_stream::stream;
+    let build = move |options: PaginationOptions| -> Inner<'a, ClientResult<Page<T>>> {
+        Box::pin(stream! {
+            let request = |limit, offset| req(&ctx, limit, offset);
+            let pages = concurrent_pages(request, options, concurrency, ordered);
+            futures::pin_mut!(pages);
+            while let Some(page) = pages.next().await {
+                yield page;
+            }
+        })
+    };
+
+    Paginator::new(Box::new(build), page_size).into_items()
+}
+
+/// This is used to handle paginated requests concurrently. The first page is
+/// requested to find out the total number of items, and the rest of offsets
+/// are then requested with up to `concurrency` requests in flight at the same
+/// time.
+///
+/// If `ordered` is true, the items are yielded in the same order as the
+/// sequential paginator. Otherwise, they're yielded as soon as their page is
+/// received.
+///
+/// Requests rejected with `429 Too Many Requests` are tried again after the
+/// time in their `Retry-After` header, up to 5 times per page.
+pub fn paginate_concurrent<'a, T, Fut, Request>(
+    req: Request,
+    page_size: u32,
+    concurrency: usize,
+    ordered: bool,
+) -> Paginator<'a, ClientResult<T>>
+where
+    T: 'a + Unpin,
+    Fut: Future<Output = ClientResult<Page<T>>>,
+    Request: 'a + Fn(u32, u32) -> Fut,
+{
+    use async_stream::stream;
+    let build = move |options: PaginationOptions| -> Inner<'a, ClientResult<Page<T>>> {
+        Box::pin(stream! {
+            let pages = concurrent_pages(&req, options, concurrency, ordered);
+            futures::pin_mut!(pages);
+            while let Some(page) = pages.next().await {
+                yield page;
+            }
+        })
+    };
+
+    Paginator::new(Box::new(build), page_size).into_items()
+}
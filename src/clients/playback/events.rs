@@ -0,0 +1,529 @@
+//! Detection of changes between snapshots of the playback state.
+
+use super::PlaybackPosition;
+use crate::model::{Context, CurrentPlaybackContext, Device, Id, PlayableItem, RepeatState};
+
+use std::time::Duration;
+
+use chrono::{DateTime, TimeDelta, Utc};
+
+/// A change in the playback state of the user.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PlaybackEvent {
+    /// A different item is being played, or nothing is being played at all
+    /// anymore if it's `None`.
+    ///
+    /// The item is boxed because it's much larger than the rest of variants,
+    /// see <https://rust-lang.github.io/rust-clippy/master/index.html#large_enum_variant>.
+    TrackChanged(Option<Box<PlayableItem>>),
+    /// The playback was paused.
+    Paused,
+    /// The playback was resumed.
+    Resumed,
+    /// The user jumped to a different position of the same item.
+    Seeked(TimeDelta),
+    /// The playback was transferred to a different device, or there isn't an
+    /// active device anymore if it's `None`.
+    DeviceChanged(Option<Device>),
+    /// The shuffle mode was toggled.
+    ShuffleChanged(bool),
+    /// The repeat mode changed.
+    RepeatChanged(RepeatState),
+    /// The volume of the current device changed.
+    VolumeChanged(u32),
+    /// The playback moved to a different album, playlist, artist or show, or
+    /// it's not playing from any of them if it's `None`.
+    ContextChanged(Option<Context>),
+}
+
+/// The configuration of [`playback_events`], which polls the playback state
+/// at an adaptive interval.
+///
+/// The interval starts at `min_interval` and it's doubled every time nothing
+/// changes, up to `max_interval`. It's reset after any change, and it's also
+/// shortened so that the playback state is requested right when the current
+/// item is expected to end.
+///
+/// [`playback_events`]: crate::clients::OAuthClient::playback_events
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PlaybackEventsOptions {
+    /// The shortest time between requests. By default it's one second.
+    pub min_interval: Duration,
+    /// The longest time between requests. By default it's 15 seconds.
+    pub max_interval: Duration,
+    /// How far the progress may drift from its expected value before it's
+    /// considered a seek. By default it's two seconds.
+    pub seek_tolerance: Duration,
+}
+
+impl Default for PlaybackEventsOptions {
+    fn default() -> Self {
+        Self {
+            min_interval: Duration::from_secs(1),
+            max_interval: Duration::from_secs(15),
+            seek_tolerance: Duration::from_secs(2),
+        }
+    }
+}
+
+/// Keeps the last known playback state, and turns the differences with a new
+/// one into [`PlaybackEvent`]s.
+///
+/// This is what [`playback_events`] uses internally, so it's only needed to
+/// diff the playback states obtained by other means.
+///
+/// [`playback_events`]: crate::clients::OAuthClient::playback_events
+#[derive(Debug, Clone, Default)]
+pub struct PlaybackTracker {
+    options: PlaybackEventsOptions,
+    last: Option<CurrentPlaybackContext>,
+    updated_at: Option<DateTime<Utc>>,
+    unchanged: u32,
+}
+
+impl PlaybackTracker {
+    #[must_use]
+    pub fn new(options: PlaybackEventsOptions) -> Self {
+        Self {
+            options,
+            ..Default::default()
+        }
+    }
+
+    /// The last known playback state, if anything is being played.
+    #[must_use]
+    pub fn last(&self) -> Option<&CurrentPlaybackContext> {
+        self.last.as_ref()
+    }
+
+    /// The progress of the last known playback state, which can be used to
+    /// estimate the current position until the next update.
+    #[must_use]
+    pub fn position(&self) -> Option<PlaybackPosition> {
+        let playback = self.last.as_ref()?;
+        Some(PlaybackPosition::new(playback, self.updated_at?))
+    }
+
+    /// Replaces the last known playback state with the one obtained at `now`,
+    /// returning what changed in between. Initially, nothing is being played,
+    /// so the first update describes the whole playback state.
+    pub fn update(
+        &mut self,
+        playback: Option<CurrentPlaybackContext>,
+        now: DateTime<Utc>,
+    ) -> Vec<PlaybackEvent> {
+        let events = match (&self.last, &playback) {
+            (None, None) => Vec::new(),
+            (Some(_), None) => {
+                vec![
+                    PlaybackEvent::DeviceChanged(None),
+                    PlaybackEvent::ContextChanged(None),
+                    PlaybackEvent::TrackChanged(None),
+                ]
+            }
+            (None, Some(current)) => {
+                let mut events = vec![
+                    PlaybackEvent::DeviceChanged(Some(current.device.clone())),
+                    PlaybackEvent::ContextChanged(current.context.clone()),
+                    PlaybackEvent::TrackChanged(current.item.clone().map(Box::new)),
+                ];
+                if current.is_playing {
+                    events.push(PlaybackEvent::Resumed);
+                }
+                events
+            }
+            (Some(previous), Some(current)) => self.diff(previous, current, now),
+        };
+
+        if events.is_empty() {
+            self.unchanged = self.unchanged.saturating_add(1);
+        } else {
+            self.unchanged = 0;
+        }
+        self.last = playback;
+        self.updated_at = Some(now);
+        events
+    }
+
+    /// Takes into account a failed request for the playback state, so that
+    /// the interval is increased just like when nothing changes.
+    pub fn record_failure(&mut self) {
+        self.unchanged = self.unchanged.saturating_add(1);
+    }
+
+    /// Returns how long to wait until the next time the playback state is
+    /// requested, as explained in [`PlaybackEventsOptions`].
+    #[must_use]
+    pub fn next_interval(&self) -> Duration {
+        let PlaybackEventsOptions {
+            min_interval,
+            max_interval,
+            ..
+        } = self.options;
+
+        let backoff = 2u32.saturating_pow(self.unchanged.min(16));
+        let mut interval = min_interval.saturating_mul(backoff).min(max_interval);
+
+        let remaining = self
+            .position()
+            .filter(|position| position.is_playing)
+            .and_then(|position| position.remaining_at(position.fetched_at))
+            .and_then(|remaining| remaining.to_std().ok());
+        if let Some(remaining) = remaining {
+            interval = interval.min(remaining);
+        }
+
+        interval.max(min_interval)
+    }
+
+    fn diff(
+        &self,
+        previous: &CurrentPlaybackContext,
+        current: &CurrentPlaybackContext,
+        now: DateTime<Utc>,
+    ) -> Vec<PlaybackEvent> {
+        let mut events = Vec::new();
+
+        let same_device =
+            previous.device.id == current.device.id && previous.device.name == current.device.name;
+        if !same_device {
+            events.push(PlaybackEvent::DeviceChanged(Some(current.device.clone())));
+        }
+
+        let same_context = previous.context.as_ref().map(|context| &context.uri)
+            == current.context.as_ref().map(|context| &context.uri);
+        if !same_context {
+            events.push(PlaybackEvent::ContextChanged(current.context.clone()));
+        }
+
+        let same_item = same_item(previous.item.as_ref(), current.item.as_ref());
+        if !same_item {
+            events.push(PlaybackEvent::TrackChanged(
+                current.item.clone().map(Box::new),
+            ));
+        }
+
+        match (previous.is_playing, current.is_playing) {
+            (true, false) => events.push(PlaybackEvent::Paused),
+            (false, true) => events.push(PlaybackEvent::Resumed),
+            _ => (),
+        }
+
+        if same_item {
+            if let Some(position) = self.seek(previous, current, now) {
+                events.push(PlaybackEvent::Seeked(position));
+            }
+        }
+
+        if previous.shuffle_state != current.shuffle_state {
+            events.push(PlaybackEvent::ShuffleChanged(current.shuffle_state));
+        }
+        if previous.repeat_state != current.repeat_state {
+            events.push(PlaybackEvent::RepeatChanged(current.repeat_state));
+        }
+
+        if same_device && previous.device.volume_percent != current.device.volume_percent {
+            if let Some(volume) = current.device.volume_percent {
+                events.push(PlaybackEvent::VolumeChanged(volume));
+            }
+        }
+
+        events
+    }
+
+    /// Returns the new position if the progress of the same item isn't within
+    /// what could have been played since the previous state, which means that
+    /// the user seeked.
+    fn seek(
+        &self,
+        previous: &CurrentPlaybackContext,
+        current: &CurrentPlaybackContext,
+        now: DateTime<Utc>,
+    ) -> Option<TimeDelta> {
+        let (from, to) = (previous.progress?, current.progress?);
+
+        // Spotify's timestamp is when the state last changed rather than when
+        // it was obtained, so the local clock is used to know how much time
+        // passed. The timestamp is only used to know when the playback was
+        // paused or resumed, if that happened in between.
+        let updated_at = self.updated_at.unwrap_or(now);
+        let elapsed = (now - updated_at).max(TimeDelta::zero());
+        let changed_at = Some(current.timestamp)
+            .filter(|changed_at| *changed_at > updated_at && *changed_at <= now);
+
+        // Otherwise, it's unknown for how long it was playing
+        let (shortest, longest) = match (previous.is_playing, current.is_playing) {
+            (true, true) => (elapsed, elapsed),
+            (false, false) => (TimeDelta::zero(), TimeDelta::zero()),
+            (true, false) => match changed_at {
+                Some(changed_at) => (changed_at - updated_at, changed_at - updated_at),
+                None => (TimeDelta::zero(), elapsed),
+            },
+            (false, true) => match changed_at {
+                Some(changed_at) => (now - changed_at, now - changed_at),
+                None => (TimeDelta::zero(), elapsed),
+            },
+        };
+        let earliest = from + shortest;
+        let latest = from + longest;
+
+        // Out of range tolerances never consider anything a seek
+        let tolerance = TimeDelta::from_std(self.options.seek_tolerance).unwrap_or(TimeDelta::MAX);
+        let before = earliest
+            .checked_sub(&tolerance)
+            .map_or(false, |earliest| to < earliest);
+        let after = latest
+            .checked_add(&tolerance)
+            .map_or(false, |latest| to > latest);
+        if before || after {
+            Some(to)
+        } else {
+            None
+        }
+    }
+}
+
+/// Compares the items by their ID, since their metadata may change between
+/// requests. Local tracks, which have no ID, are compared by their contents.
+fn same_item(previous: Option<&PlayableItem>, current: Option<&PlayableItem>) -> bool {
+    match (previous, current) {
+        (Some(previous), Some(current)) => match (previous.id(), current.id()) {
+            (Some(previous_id), Some(current_id)) => previous_id.uri() == current_id.uri(),
+            _ => previous == current,
+        },
+        (previous, current) => previous.is_none() && current.is_none(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{PlaybackEvent, PlaybackEventsOptions, PlaybackTracker};
+    use crate::model::{CurrentPlaybackContext, RepeatState};
+
+    use std::time::Duration;
+
+    use chrono::{DateTime, TimeDelta, Utc};
+    use serde_json::json;
+
+    fn playback(track_id: &str, progress_ms: i64, is_playing: bool) -> CurrentPlaybackContext {
+        serde_json::from_value(json!({
+            "device": {
+                "id": "28d0f845293d03a2713392905c6d30b6442719b5",
+                "is_active": true,
+                "is_private_session": false,
+                "is_restricted": false,
+                "name": "Web Player (Firefox)",
+                "type": "Computer",
+                "volume_percent": 100
+            },
+            "shuffle_state": false,
+            "repeat_state": "off",
+            "timestamp": 1607774342714i64,
+            "context": null,
+            "progress_ms": progress_ms,
+            "is_playing": is_playing,
+            "item": {
+                "type": "track",
+                "id": track_id,
+                "name": "Playinwitme (feat. Kehlani)",
+                "uri": format!("spotify:track:{track_id}"),
+                "href": format!("https://api.spotify.com/v1/tracks/{track_id}"),
+                "album": {
+                    "type": "album",
+                    "id": "2lgOc40hhHqjUGAKMWqGxO",
+                    "name": "Playinwitme (feat. Kehlani)",
+                    "artists": [],
+                    "images": [],
+                    "external_urls": {},
+                    "href": "https://api.spotify.com/v1/albums/2lgOc40hhHqjUGAKMWqGxO",
+                    "uri": "spotify:album:2lgOc40hhHqjUGAKMWqGxO"
+                },
+                "artists": [],
+                "available_markets": [],
+                "disc_number": 1,
+                "duration_ms": 193093,
+                "explicit": false,
+                "external_ids": {},
+                "external_urls": {},
+                "is_local": false,
+                "popularity": 70,
+                "preview_url": null,
+                "track_number": 1
+            },
+            "currently_playing_type": "track",
+            "actions": {"disallows": {}}
+        }))
+        .unwrap()
+    }
+
+    fn at(seconds: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp(1_607_774_342 + seconds, 0).unwrap()
+    }
+
+    #[test]
+    fn test_update() {
+        let mut tracker = PlaybackTracker::default();
+        assert!(tracker.update(None, at(0)).is_empty());
+
+        let events = tracker.update(Some(playback("43cFjTTCD9Cni4aSL0sORz", 0, true)), at(0));
+        assert!(matches!(
+            &events[..],
+            [
+                PlaybackEvent::DeviceChanged(Some(_)),
+                PlaybackEvent::ContextChanged(None),
+                PlaybackEvent::TrackChanged(Some(_)),
+                PlaybackEvent::Resumed,
+            ]
+        ));
+
+        // Normal progress
+        let current = playback("43cFjTTCD9Cni4aSL0sORz", 10_000, true);
+        assert!(tracker.update(Some(current), at(10)).is_empty());
+
+        let mut current = playback("43cFjTTCD9Cni4aSL0sORz", 12_000, false);
+        current.shuffle_state = true;
+        current.repeat_state = RepeatState::Track;
+        current.device.volume_percent = Some(50);
+        assert_eq!(
+            tracker.update(Some(current), at(12)),
+            [
+                PlaybackEvent::Paused,
+                PlaybackEvent::ShuffleChanged(true),
+                PlaybackEvent::RepeatChanged(RepeatState::Track),
+                PlaybackEvent::VolumeChanged(50),
+            ]
+        );
+
+        let events = tracker.update(Some(playback("4iV5W9uYEdYUVa79Axb7Rh", 0, false)), at(13));
+        assert!(matches!(
+            &events[..],
+            [PlaybackEvent::TrackChanged(Some(_)), ..]
+        ));
+
+        let events = tracker.update(None, at(14));
+        assert_eq!(
+            events,
+            [
+                PlaybackEvent::DeviceChanged(None),
+                PlaybackEvent::ContextChanged(None),
+                PlaybackEvent::TrackChanged(None),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_seek() {
+        let mut tracker = PlaybackTracker::default();
+        tracker.update(Some(playback("43cFjTTCD9Cni4aSL0sORz", 0, true)), at(0));
+
+        let position = TimeDelta::try_seconds(90).unwrap();
+        let current = playback("43cFjTTCD9Cni4aSL0sORz", 90_000, true);
+        assert_eq!(
+            tracker.update(Some(current), at(5)),
+            [PlaybackEvent::Seeked(position)]
+        );
+
+        // Resuming after a while isn't a seek, but going back is
+        tracker.update(
+            Some(playback("43cFjTTCD9Cni4aSL0sORz", 91_000, false)),
+            at(6),
+        );
+        let current = playback("43cFjTTCD9Cni4aSL0sORz", 95_000, true);
+        assert_eq!(
+            tracker.update(Some(current), at(20)),
+            [PlaybackEvent::Resumed]
+        );
+        let current = playback("43cFjTTCD9Cni4aSL0sORz", 30_000, true);
+        assert_eq!(
+            tracker.update(Some(current), at(21)),
+            [PlaybackEvent::Seeked(TimeDelta::try_seconds(30).unwrap())]
+        );
+    }
+
+    #[test]
+    fn test_seek_after_resume() {
+        // The playback was resumed long before it was first obtained, which
+        // is when its timestamp was last updated
+        let mut tracker = PlaybackTracker::default();
+        let mut previous = playback("43cFjTTCD9Cni4aSL0sORz", 95_000, true);
+        previous.timestamp = at(0);
+        tracker.update(Some(previous), at(95));
+
+        // Only five seconds were played since then, so this is a seek
+        let mut current = playback("43cFjTTCD9Cni4aSL0sORz", 195_000, true);
+        current.timestamp = at(99);
+        assert_eq!(
+            tracker.update(Some(current), at(100)),
+            [PlaybackEvent::Seeked(TimeDelta::try_seconds(195).unwrap())]
+        );
+
+        // Paused at 100 and resumed at 106, so it played for four seconds
+        let mut current = playback("43cFjTTCD9Cni4aSL0sORz", 195_000, false);
+        current.timestamp = at(100);
+        tracker.update(Some(current), at(100));
+        let mut current = playback("43cFjTTCD9Cni4aSL0sORz", 199_000, true);
+        current.timestamp = at(106);
+        assert_eq!(
+            tracker.update(Some(current), at(110)),
+            [PlaybackEvent::Resumed]
+        );
+
+        // Which would be too much if it had been resumed right after that
+        let mut current = playback("43cFjTTCD9Cni4aSL0sORz", 199_000, false);
+        current.timestamp = at(110);
+        tracker.update(Some(current), at(110));
+        let mut current = playback("43cFjTTCD9Cni4aSL0sORz", 210_000, true);
+        current.timestamp = at(118);
+        assert_eq!(
+            tracker.update(Some(current), at(120)),
+            [
+                PlaybackEvent::Resumed,
+                PlaybackEvent::Seeked(TimeDelta::try_seconds(210).unwrap())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_seek_tolerance_overflow() {
+        let options = PlaybackEventsOptions {
+            seek_tolerance: Duration::MAX,
+            ..Default::default()
+        };
+        let mut tracker = PlaybackTracker::new(options);
+        tracker.update(Some(playback("43cFjTTCD9Cni4aSL0sORz", 0, true)), at(0));
+
+        // Nothing is far enough to be considered a seek
+        let current = playback("43cFjTTCD9Cni4aSL0sORz", 90_000, true);
+        assert!(tracker.update(Some(current), at(5)).is_empty());
+    }
+
+    #[test]
+    fn test_next_interval() {
+        let options = PlaybackEventsOptions::default();
+        let mut tracker = PlaybackTracker::new(options);
+        assert_eq!(tracker.next_interval(), options.min_interval);
+
+        tracker.update(Some(playback("43cFjTTCD9Cni4aSL0sORz", 0, false)), at(0));
+        assert_eq!(tracker.next_interval(), options.min_interval);
+
+        // Backs off while nothing changes
+        tracker.update(Some(playback("43cFjTTCD9Cni4aSL0sORz", 0, false)), at(1));
+        assert_eq!(tracker.next_interval(), Duration::from_secs(2));
+        for second in 2..10 {
+            tracker.update(
+                Some(playback("43cFjTTCD9Cni4aSL0sORz", 0, false)),
+                at(second),
+            );
+        }
+        assert_eq!(tracker.next_interval(), options.max_interval);
+
+        // Polls again when the track is expected to end
+        for _ in 0..4 {
+            tracker.update(
+                Some(playback("43cFjTTCD9Cni4aSL0sORz", 190_093, true)),
+                at(10),
+            );
+        }
+        assert_eq!(tracker.next_interval(), Duration::from_secs(3));
+    }
+}
@@ -0,0 +1,53 @@
+//! Synchronous implementation of the playback events.
+
+use super::{PlaybackEvent, PlaybackEvents, PlaybackEventsOptions, PlaybackTracker};
+use crate::{model::CurrentPlaybackContext, sync::sleep, ClientResult};
+
+use std::collections::VecDeque;
+
+use chrono::Utc;
+
+/// The underlying iterator of [`PlaybackEvents`], since sync mode is enabled.
+pub type Inner<'a> = Box<dyn Iterator<Item = ClientResult<PlaybackEvent>> + 'a>;
+
+impl Iterator for PlaybackEvents<'_> {
+    type Item = ClientResult<PlaybackEvent>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next()
+    }
+}
+
+/// Requests the playback state over and over with `request`, yielding what
+/// changed every time. Failed requests are yielded as well, but the polling
+/// goes on, so the iterator never ends.
+pub fn watch_playback<'a, Request>(
+    request: Request,
+    options: PlaybackEventsOptions,
+) -> PlaybackEvents<'a>
+where
+    Request: 'a + Fn() -> ClientResult<Option<CurrentPlaybackContext>>,
+{
+    let mut tracker = PlaybackTracker::new(options);
+    let mut pending = VecDeque::new();
+    let mut polled = false;
+
+    PlaybackEvents::new(Box::new(std::iter::from_fn(move || loop {
+        if let Some(event) = pending.pop_front() {
+            return Some(Ok(event));
+        }
+
+        if polled {
+            sleep(tracker.next_interval());
+        }
+        polled = true;
+
+        match request() {
+            Ok(playback) => pending.extend(tracker.update(playback, Utc::now())),
+            Err(err) => {
+                tracker.record_failure();
+                return Some(Err(err));
+            }
+        }
+    })))
+}
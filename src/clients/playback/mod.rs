@@ -0,0 +1,57 @@
+//! Utilities to follow the playback of the user.
+//!
+//! Spotify doesn't notify about changes in the playback, so
+//! [`OAuthClient::playback_events`] requests the playback state periodically
+//! and turns the differences between consecutive states into
+//! [`PlaybackEvent`]s, e.g. when the track changes or the user seeks. If the
+//! configured client is asynchronous, they're yielded by a
+//! `futures::stream::Stream`; if it's synchronous, by an
+//! `std::iter::Iterator`. The logic to compare the states is available as
+//! [`PlaybackTracker`] as well.
+//!
+//! Between requests, [`PlaybackPosition`] estimates where the playback is
+//! with the local clock, e.g. to display a progress bar.
+//!
+//! The playback on a specific device can be controlled with a [`Player`],
+//! and [`DeviceSelector`] helps to choose that device.
+//!
+//! [`OAuthClient::playback_events`]: crate::clients::OAuthClient::playback_events
+
+mod events;
+#[cfg(feature = "__sync")]
+mod iter;
+mod player;
+mod position;
+mod selector;
+#[cfg(feature = "__async")]
+mod stream;
+
+pub use events::{PlaybackEvent, PlaybackEventsOptions, PlaybackTracker};
+pub use player::Player;
+pub use position::PlaybackPosition;
+pub use selector::{DeviceFallback, DeviceMatcher, DeviceSelector, NameMatch};
+
+#[cfg(feature = "__sync")]
+use self::iter as imp;
+#[cfg(feature = "__async")]
+use self::stream as imp;
+
+#[cfg(feature = "__sync")]
+pub use iter::watch_playback;
+
+#[cfg(feature = "__async")]
+pub use stream::{watch_playback, MaybeSend};
+
+/// Wraps the events yielded while watching the playback. It implements
+/// `Iterator` if the client is synchronous, and `Stream` if it's
+/// asynchronous. Either way, it never ends, since the playback state keeps
+/// being requested.
+pub struct PlaybackEvents<'a> {
+    inner: imp::Inner<'a>,
+}
+
+impl<'a> PlaybackEvents<'a> {
+    fn new(inner: imp::Inner<'a>) -> Self {
+        Self { inner }
+    }
+}
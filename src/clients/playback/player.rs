@@ -0,0 +1,209 @@
+//! Control of the playback on a specific device.
+
+use crate::{
+    clients::OAuthClient,
+    model::{
+        AdditionalType, CurrentPlaybackContext, Device, DeviceId, DisallowKey, PlayContextId,
+        PlayableId,
+    },
+    ClientError, ClientResult,
+};
+
+use chrono::TimeDelta;
+use maybe_async::maybe_async;
+
+/// A handle to control the playback of the user on a single device, so that
+/// its ID doesn't have to be passed around.
+///
+/// Before every action, the current playback state is requested in order to
+/// make sure that it's allowed, according to its
+/// [`Actions`](crate::model::Actions), as long as it's on this device. If it
+/// isn't, [`ClientError::Disallowed`] is returned without sending the command,
+/// which would fail otherwise. The exceptions are [`Self::set_volume`] and
+/// [`Self::queue`], which aren't covered by the actions of the playback, so
+/// they're sent right away.
+///
+/// ```ignore
+/// let devices = spotify.device().await?;
+/// let player = Player::new(&spotify, devices[0].clone()).unwrap();
+/// player.transfer_here(true).await?;
+/// player.queue(TrackId::from_id("4iV5W9uYEdYUVa79Axb7Rh")?.into()).await?;
+/// player.next().await?;
+/// ```
+#[derive(Clone, Debug)]
+pub struct Player<'a, C> {
+    client: &'a C,
+    device: Device,
+    device_id: DeviceId,
+}
+
+impl<'a, C: OAuthClient> Player<'a, C> {
+    /// Binds the client to the device. Returns `None` if the device doesn't
+    /// have a valid ID, in which case it can't be controlled.
+    #[must_use]
+    pub fn new(client: &'a C, device: Device) -> Option<Self> {
+        let device_id = device.device_id()?;
+        Some(Self {
+            client,
+            device,
+            device_id,
+        })
+    }
+
+    /// The device controlled by this player.
+    #[must_use]
+    pub fn device(&self) -> &Device {
+        &self.device
+    }
+
+    /// The ID of the device controlled by this player.
+    #[must_use]
+    pub fn device_id(&self) -> &DeviceId {
+        &self.device_id
+    }
+
+    /// The client used to control the device.
+    #[must_use]
+    pub fn client(&self) -> &'a C {
+        self.client
+    }
+
+    /// Returns the current playback state, but only if it's on this device.
+    #[maybe_async]
+    pub async fn playback(&self) -> ClientResult<Option<CurrentPlaybackContext>> {
+        let playback = self.current_playback().await?;
+        Ok(playback.filter(|playback| self.is_this_device(playback)))
+    }
+
+    /// Starts playing the given album, artist, playlist or show from the
+    /// beginning.
+    #[maybe_async]
+    pub async fn play(&self, context: PlayContextId<'_>) -> ClientResult<()> {
+        self.check(DisallowKey::InterruptingPlayback).await?;
+        self.client
+            .start_context_playback(context, Some(&self.device_id), None, None)
+            .await
+    }
+
+    /// Starts playing the given tracks or episodes from the beginning.
+    #[maybe_async]
+    pub async fn play_items<'b>(
+        &self,
+        items: impl IntoIterator<Item = PlayableId<'b>> + Send + 'b,
+    ) -> ClientResult<()> {
+        self.check(DisallowKey::InterruptingPlayback).await?;
+        self.client
+            .start_uris_playback(items, Some(&self.device_id), None, None)
+            .await
+    }
+
+    /// Pauses the playback if it's playing on this device, and resumes it
+    /// otherwise. Returns whether it's playing afterwards.
+    #[maybe_async]
+    pub async fn toggle_pause(&self) -> ClientResult<bool> {
+        let playback = self.current_playback().await?;
+        let is_playing = playback.as_ref().map_or(false, |playback| {
+            playback.is_playing && self.is_this_device(playback)
+        });
+
+        if is_playing {
+            self.check_playback(playback.as_ref(), DisallowKey::Pausing)?;
+            self.client.pause_playback(Some(&self.device_id)).await?;
+        } else {
+            self.check_playback(playback.as_ref(), DisallowKey::Resuming)?;
+            self.client
+                .resume_playback(Some(&self.device_id), None)
+                .await?;
+        }
+
+        Ok(!is_playing)
+    }
+
+    /// Skips to the next item in the queue.
+    #[maybe_async]
+    pub async fn next(&self) -> ClientResult<()> {
+        self.check(DisallowKey::SkippingNext).await?;
+        self.client.next_track(Some(&self.device_id)).await
+    }
+
+    /// Jumps to the given position of the current item.
+    #[maybe_async]
+    pub async fn seek(&self, position: TimeDelta) -> ClientResult<()> {
+        self.check(DisallowKey::Seeking).await?;
+        self.client
+            .seek_track(position, Some(&self.device_id))
+            .await
+    }
+
+    /// Sets the volume of the device, from 0 to 100.
+    ///
+    /// Unlike the rest of the actions, it isn't checked beforehand, since
+    /// there's no [`DisallowKey`] for it.
+    #[maybe_async]
+    pub async fn set_volume(&self, volume_percent: u8) -> ClientResult<()> {
+        self.client
+            .volume(volume_percent, Some(&self.device_id))
+            .await
+    }
+
+    /// Adds the given track or episode to the end of the queue.
+    ///
+    /// Just like [`Self::set_volume`], it isn't checked beforehand.
+    #[maybe_async]
+    pub async fn queue(&self, item: PlayableId<'_>) -> ClientResult<()> {
+        self.client
+            .add_item_to_queue(item, Some(&self.device_id))
+            .await
+    }
+
+    /// Moves the playback to this device, playing it afterwards if `play` is
+    /// true, or keeping its current state otherwise.
+    #[maybe_async]
+    pub async fn transfer_here(&self, play: bool) -> ClientResult<()> {
+        self.check(DisallowKey::TransferringPlayback).await?;
+        self.client
+            .transfer_playback(&self.device_id, Some(play))
+            .await
+    }
+
+    /// Fails with [`ClientError::Disallowed`] if the current playback doesn't
+    /// allow the given action.
+    #[maybe_async]
+    async fn check(&self, action: DisallowKey) -> ClientResult<()> {
+        let playback = self.current_playback().await?;
+        self.check_playback(playback.as_ref(), action)
+    }
+
+    /// The actions of a playback only apply to its own device, so the ones
+    /// of other devices are ignored. For example, `resuming` is disallowed
+    /// while they're playing, but it may still be resumed on this one.
+    fn check_playback(
+        &self,
+        playback: Option<&CurrentPlaybackContext>,
+        action: DisallowKey,
+    ) -> ClientResult<()> {
+        match playback {
+            Some(playback)
+                if self.is_this_device(playback)
+                    && playback.actions.disallows.contains(&action) =>
+            {
+                Err(ClientError::Disallowed(action))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    #[maybe_async]
+    async fn current_playback(&self) -> ClientResult<Option<CurrentPlaybackContext>> {
+        self.client
+            .current_playback(
+                None,
+                Some(&[AdditionalType::Track, AdditionalType::Episode]),
+            )
+            .await
+    }
+
+    fn is_this_device(&self, playback: &CurrentPlaybackContext) -> bool {
+        playback.device.id.as_deref() == Some(self.device_id.id())
+    }
+}
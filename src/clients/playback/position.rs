@@ -0,0 +1,149 @@
+//! Estimation of the playback position between requests.
+
+use crate::model::{CurrentPlaybackContext, PlayableItem};
+
+use chrono::{DateTime, TimeDelta, Utc};
+
+/// A snapshot of the playback progress, which can be used to estimate the
+/// current position without requesting the playback state again, e.g. to
+/// display a progress bar.
+///
+/// The estimations are based on the local time at which the playback state
+/// was obtained, rather than its `timestamp`, since Spotify doesn't always
+/// update the latter along with the progress.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PlaybackPosition {
+    /// The position when the playback state was obtained.
+    pub progress: TimeDelta,
+    /// The duration of the item being played, if any.
+    pub duration: Option<TimeDelta>,
+    /// Whether the item was being played, or it was paused otherwise.
+    pub is_playing: bool,
+    /// When the playback state was obtained, according to the local clock.
+    pub fetched_at: DateTime<Utc>,
+}
+
+impl PlaybackPosition {
+    /// Takes a snapshot of the given playback state, which was obtained at
+    /// `fetched_at`.
+    #[must_use]
+    pub fn new(playback: &CurrentPlaybackContext, fetched_at: DateTime<Utc>) -> Self {
+        let duration = playback.item.as_ref().map(|item| match item {
+            PlayableItem::Track(track) => track.duration,
+            PlayableItem::Episode(episode) => episode.duration,
+        });
+
+        Self {
+            progress: playback.progress.unwrap_or_else(TimeDelta::zero),
+            duration,
+            is_playing: playback.is_playing,
+            fetched_at,
+        }
+    }
+
+    /// Same as [`Self::new`], for a playback state that was just obtained.
+    #[must_use]
+    pub fn fetched_now(playback: &CurrentPlaybackContext) -> Self {
+        Self::new(playback, Utc::now())
+    }
+
+    /// Estimates the position at the given time, which never goes past the
+    /// end of the item.
+    #[must_use]
+    pub fn position_at(&self, now: DateTime<Utc>) -> TimeDelta {
+        let mut position = self.progress;
+        if self.is_playing {
+            position += (now - self.fetched_at).max(TimeDelta::zero());
+        }
+
+        match self.duration {
+            Some(duration) => position.min(duration),
+            None => position,
+        }
+    }
+
+    /// Estimates the current position.
+    #[must_use]
+    pub fn position(&self) -> TimeDelta {
+        self.position_at(Utc::now())
+    }
+
+    /// Estimates how much is left to play at the given time, or `None` if the
+    /// duration of the item is unknown.
+    #[must_use]
+    pub fn remaining_at(&self, now: DateTime<Utc>) -> Option<TimeDelta> {
+        Some(self.duration? - self.position_at(now))
+    }
+
+    /// Estimates how much is left to play.
+    #[must_use]
+    pub fn remaining(&self) -> Option<TimeDelta> {
+        self.remaining_at(Utc::now())
+    }
+
+    /// Returns whether the item has probably ended at the given time, in
+    /// which case the estimations aren't valid anymore and the playback state
+    /// should be requested again.
+    #[must_use]
+    pub fn needs_poll_at(&self, now: DateTime<Utc>) -> bool {
+        self.is_playing && self.remaining_at(now) == Some(TimeDelta::zero())
+    }
+
+    /// Returns whether the item has probably ended, as in
+    /// [`Self::needs_poll_at`].
+    #[must_use]
+    pub fn needs_poll(&self) -> bool {
+        self.needs_poll_at(Utc::now())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::PlaybackPosition;
+
+    use chrono::{DateTime, TimeDelta};
+
+    fn seconds(seconds: i64) -> TimeDelta {
+        TimeDelta::try_seconds(seconds).unwrap()
+    }
+
+    #[test]
+    fn test_position() {
+        let fetched_at = DateTime::from_timestamp(1_607_774_342, 0).unwrap();
+        let mut position = PlaybackPosition {
+            progress: seconds(60),
+            duration: Some(seconds(180)),
+            is_playing: true,
+            fetched_at,
+        };
+
+        assert_eq!(position.position_at(fetched_at + seconds(30)), seconds(90));
+        assert_eq!(
+            position.remaining_at(fetched_at + seconds(30)),
+            Some(seconds(90))
+        );
+        assert!(!position.needs_poll_at(fetched_at + seconds(119)));
+
+        // It never goes past the end of the item
+        assert_eq!(
+            position.position_at(fetched_at + seconds(200)),
+            seconds(180)
+        );
+        assert_eq!(
+            position.remaining_at(fetched_at + seconds(200)),
+            Some(TimeDelta::zero())
+        );
+        assert!(position.needs_poll_at(fetched_at + seconds(120)));
+
+        // Or before the snapshot, e.g. if the clock goes backwards
+        assert_eq!(position.position_at(fetched_at - seconds(10)), seconds(60));
+
+        position.is_playing = false;
+        assert_eq!(position.position_at(fetched_at + seconds(200)), seconds(60));
+        assert!(!position.needs_poll_at(fetched_at + seconds(200)));
+
+        position.duration = None;
+        assert_eq!(position.remaining_at(fetched_at), None);
+        assert!(!position.needs_poll_at(fetched_at));
+    }
+}
@@ -0,0 +1,310 @@
+//! Selection of the device to play on, given a set of preferences.
+
+use crate::{
+    clients::OAuthClient,
+    model::{Device, DeviceType},
+    ClientResult,
+};
+
+use maybe_async::maybe_async;
+
+/// How a [`DeviceMatcher`] compares the name of the devices.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NameMatch {
+    /// The name must be the same, ignoring the case.
+    Exact(String),
+    /// The name must contain the given one, ignoring the case, whitespace and
+    /// punctuation. For example, `"kitchen"` matches `"Kitchen Speaker"` and
+    /// `"kitchen-speaker"` matches `"Kitchen Speaker"`.
+    Fuzzy(String),
+}
+
+impl NameMatch {
+    /// Returns whether the given device name matches.
+    #[must_use]
+    pub fn matches(&self, name: &str) -> bool {
+        match self {
+            Self::Exact(expected) => name.to_lowercase() == expected.to_lowercase(),
+            Self::Fuzzy(expected) => normalize(name).contains(&normalize(expected)),
+        }
+    }
+}
+
+fn normalize(name: &str) -> String {
+    name.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// The conditions a device must meet, all of them optional. An empty matcher
+/// matches any device.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DeviceMatcher {
+    pub name: Option<NameMatch>,
+    pub device_type: Option<DeviceType>,
+    pub is_active: Option<bool>,
+    pub is_restricted: Option<bool>,
+}
+
+impl DeviceMatcher {
+    /// Creates a matcher for any device.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a matcher for the currently active device.
+    #[must_use]
+    pub fn active() -> Self {
+        Self::new().is_active(true)
+    }
+
+    /// Only matches the devices with exactly this name, ignoring the case.
+    #[must_use]
+    pub fn name<S: Into<String>>(mut self, name: S) -> Self {
+        self.name = Some(NameMatch::Exact(name.into()));
+        self
+    }
+
+    /// Only matches the devices whose name contains this one, as explained
+    /// in [`NameMatch::Fuzzy`].
+    #[must_use]
+    pub fn fuzzy_name<S: Into<String>>(mut self, name: S) -> Self {
+        self.name = Some(NameMatch::Fuzzy(name.into()));
+        self
+    }
+
+    /// Only matches the devices of this type.
+    #[must_use]
+    pub fn device_type(mut self, device_type: DeviceType) -> Self {
+        self.device_type = Some(device_type);
+        self
+    }
+
+    /// Only matches the devices that are active or not.
+    #[must_use]
+    pub fn is_active(mut self, is_active: bool) -> Self {
+        self.is_active = Some(is_active);
+        self
+    }
+
+    /// Only matches the devices that are restricted or not.
+    #[must_use]
+    pub fn is_restricted(mut self, is_restricted: bool) -> Self {
+        self.is_restricted = Some(is_restricted);
+        self
+    }
+
+    /// Returns whether the device meets all the conditions.
+    #[must_use]
+    pub fn matches(&self, device: &Device) -> bool {
+        self.name
+            .as_ref()
+            .map_or(true, |name| name.matches(&device.name))
+            && self
+                .device_type
+                .as_ref()
+                .map_or(true, |device_type| &device._type == device_type)
+            && self
+                .is_active
+                .map_or(true, |is_active| device.is_active == is_active)
+            && self
+                .is_restricted
+                .map_or(true, |is_restricted| device.is_restricted == is_restricted)
+    }
+}
+
+/// What to do when none of the preferences of a [`DeviceSelector`] match.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeviceFallback {
+    /// Don't select any device.
+    None,
+    /// Select the active device, if any.
+    Active,
+    /// Select any device, preferring the active one.
+    Any,
+}
+
+impl Default for DeviceFallback {
+    fn default() -> Self {
+        Self::None
+    }
+}
+
+/// Chooses a device given an ordered list of preferences, e.g. "the kitchen
+/// speaker, else the active device, else any computer":
+///
+/// ```
+/// use rspotify::clients::playback::{DeviceMatcher, DeviceSelector};
+/// use rspotify::model::DeviceType;
+///
+/// let selector = DeviceSelector::new()
+///     .prefer(DeviceMatcher::new().fuzzy_name("kitchen"))
+///     .prefer(DeviceMatcher::active())
+///     .prefer(DeviceMatcher::new().device_type(DeviceType::Computer));
+/// ```
+///
+/// The first preference that matches any of the devices is used. If it
+/// matches more than one, the active device is chosen, or the first one
+/// otherwise. Devices without a valid ID are never selected, and neither are
+/// restricted devices, which can't be controlled through the API, unless
+/// [`Self::allow_restricted`] is set.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DeviceSelector {
+    preferences: Vec<DeviceMatcher>,
+    fallback: DeviceFallback,
+    allow_restricted: bool,
+}
+
+impl DeviceSelector {
+    /// Creates a selector without preferences, which selects nothing unless
+    /// a fallback is configured.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a preference, which is tried after the previous ones.
+    #[must_use]
+    pub fn prefer(mut self, matcher: DeviceMatcher) -> Self {
+        self.preferences.push(matcher);
+        self
+    }
+
+    /// Configures what happens when no preference matches. By default, no
+    /// device is selected.
+    #[must_use]
+    pub fn fallback(mut self, fallback: DeviceFallback) -> Self {
+        self.fallback = fallback;
+        self
+    }
+
+    /// Whether restricted devices can be selected. By default they can't.
+    #[must_use]
+    pub fn allow_restricted(mut self, allow_restricted: bool) -> Self {
+        self.allow_restricted = allow_restricted;
+        self
+    }
+
+    /// Chooses one of the given devices, or returns `None` if none are
+    /// suitable.
+    #[must_use]
+    pub fn select<'a>(&self, devices: &'a [Device]) -> Option<&'a Device> {
+        let candidates = devices
+            .iter()
+            .filter(|device| device.device_id().is_some())
+            .filter(|device| self.allow_restricted || !device.is_restricted)
+            .collect::<Vec<_>>();
+
+        let best = |matcher: &DeviceMatcher| {
+            let mut matching = candidates
+                .iter()
+                .copied()
+                .filter(|device| matcher.matches(device))
+                .peekable();
+            let first = *matching.peek()?;
+            Some(matching.find(|device| device.is_active).unwrap_or(first))
+        };
+
+        self.preferences
+            .iter()
+            .find_map(best)
+            .or_else(|| match self.fallback {
+                DeviceFallback::None => None,
+                DeviceFallback::Active => best(&DeviceMatcher::active()),
+                DeviceFallback::Any => best(&DeviceMatcher::new()),
+            })
+    }
+
+    /// Requests the available devices with [`OAuthClient::device`] and
+    /// chooses one of them, as in [`Self::select`].
+    #[maybe_async]
+    pub async fn resolve<C: OAuthClient>(&self, client: &C) -> ClientResult<Option<Device>> {
+        let devices = client.device().await?;
+        Ok(self.select(&devices).cloned())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{DeviceFallback, DeviceMatcher, DeviceSelector, NameMatch};
+    use crate::model::{Device, DeviceType};
+
+    fn device(id: &str, name: &str, device_type: DeviceType, is_active: bool) -> Device {
+        Device {
+            id: Some(id.to_owned()),
+            is_active,
+            is_private_session: false,
+            is_restricted: false,
+            name: name.to_owned(),
+            _type: device_type,
+            volume_percent: Some(100),
+        }
+    }
+
+    #[test]
+    fn test_name_match() {
+        assert!(NameMatch::Exact("kitchen".to_owned()).matches("Kitchen"));
+        assert!(!NameMatch::Exact("kitchen".to_owned()).matches("Kitchen Speaker"));
+        assert!(NameMatch::Fuzzy("kitchen".to_owned()).matches("Kitchen Speaker"));
+        assert!(NameMatch::Fuzzy("kitchen-speaker".to_owned()).matches("The Kitchen Speaker"));
+        assert!(!NameMatch::Fuzzy("bedroom".to_owned()).matches("Kitchen Speaker"));
+    }
+
+    #[test]
+    fn test_select() {
+        let mut devices = vec![
+            device("1", "Living Room", DeviceType::Speaker, false),
+            device("2", "Laptop", DeviceType::Computer, false),
+            device("3", "Phone", DeviceType::Smartphone, true),
+            device("4", "Kitchen Speaker", DeviceType::Speaker, false),
+        ];
+        let selector = DeviceSelector::new()
+            .prefer(DeviceMatcher::new().fuzzy_name("kitchen"))
+            .prefer(DeviceMatcher::active())
+            .prefer(DeviceMatcher::new().device_type(DeviceType::Computer));
+
+        let id = |device: Option<&Device>| device.and_then(|device| device.id.clone());
+        assert_eq!(id(selector.select(&devices)), Some("4".to_owned()));
+
+        // Restricted devices are skipped
+        devices[3].is_restricted = true;
+        assert_eq!(id(selector.select(&devices)), Some("3".to_owned()));
+        let unrestricted = selector.clone().allow_restricted(true);
+        assert_eq!(id(unrestricted.select(&devices)), Some("4".to_owned()));
+
+        devices[2].is_active = false;
+        assert_eq!(id(selector.select(&devices)), Some("2".to_owned()));
+
+        // The active device is preferred among several matches
+        let speakers = DeviceSelector::new()
+            .prefer(DeviceMatcher::new().device_type(DeviceType::Speaker))
+            .allow_restricted(true);
+        assert_eq!(id(speakers.select(&devices)), Some("1".to_owned()));
+        devices[3].is_active = true;
+        assert_eq!(id(speakers.select(&devices)), Some("4".to_owned()));
+    }
+
+    #[test]
+    fn test_fallback() {
+        let devices = vec![
+            device("1", "Living Room", DeviceType::Speaker, false),
+            device("2", "Phone", DeviceType::Smartphone, true),
+        ];
+        let selector = DeviceSelector::new().prefer(DeviceMatcher::new().name("Kitchen"));
+        assert!(selector.select(&devices).is_none());
+
+        let selector = selector.fallback(DeviceFallback::Active);
+        assert_eq!(selector.select(&devices), Some(&devices[1]));
+        assert!(selector.select(&devices[..1]).is_none());
+
+        let selector = selector.fallback(DeviceFallback::Any);
+        assert_eq!(selector.select(&devices[..1]), Some(&devices[0]));
+
+        // Devices without a valid ID can't be selected
+        let mut devices = devices;
+        devices[1].id = None;
+        assert_eq!(selector.select(&devices), Some(&devices[0]));
+    }
+}
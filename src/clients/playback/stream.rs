@@ -0,0 +1,114 @@
+//! Asynchronous implementation of the playback events.
+
+use super::{PlaybackEvent, PlaybackEvents, PlaybackEventsOptions, PlaybackTracker};
+use crate::{model::CurrentPlaybackContext, sync::sleep, ClientResult};
+
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use chrono::Utc;
+use futures::{future::Future, stream::Stream};
+
+/// The underlying stream of [`PlaybackEvents`], since async mode is enabled.
+#[cfg(not(target_arch = "wasm32"))]
+pub type Inner<'a> = Pin<Box<dyn Stream<Item = ClientResult<PlaybackEvent>> + 'a + Send>>;
+/// The underlying stream of [`PlaybackEvents`], since async mode is enabled.
+#[cfg(target_arch = "wasm32")]
+pub type Inner<'a> = Pin<Box<dyn Stream<Item = ClientResult<PlaybackEvent>> + 'a>>;
+
+impl Stream for PlaybackEvents<'_> {
+    type Item = ClientResult<PlaybackEvent>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.inner.as_mut().poll_next(cx)
+    }
+}
+
+/// Implemented by every type on WebAssembly, where the futures aren't
+/// required to be [`Send`], and by the [`Send`] types elsewhere.
+#[doc(hidden)]
+#[cfg(not(target_arch = "wasm32"))]
+pub trait MaybeSend: Send {}
+#[cfg(not(target_arch = "wasm32"))]
+impl<T: Send> MaybeSend for T {}
+
+#[doc(hidden)]
+#[cfg(target_arch = "wasm32")]
+pub trait MaybeSend {}
+#[cfg(target_arch = "wasm32")]
+impl<T> MaybeSend for T {}
+
+/// Requests the playback state over and over with `request`, yielding what
+/// changed every time. Failed requests are yielded as well, but the polling
+/// goes on, so the stream never ends.
+pub fn watch_playback<'a, Fut, Request>(
+    request: Request,
+    options: PlaybackEventsOptions,
+) -> PlaybackEvents<'a>
+where
+    Fut: Future<Output = ClientResult<Option<CurrentPlaybackContext>>> + MaybeSend,
+    Request: 'a + Fn() -> Fut + MaybeSend,
+{
+    use async_stream::stream;
+
+    PlaybackEvents::new(Box::pin(stream! {
+        let mut tracker = PlaybackTracker::new(options);
+        let mut polled = false;
+        loop {
+            if polled {
+                sleep(tracker.next_interval()).await;
+            }
+            polled = true;
+
+            match request().await {
+                Ok(playback) => {
+                    for event in tracker.update(playback, Utc::now()) {
+                        yield Ok(event);
+                    }
+                }
+                Err(err) => {
+                    tracker.record_failure();
+                    yield Err(err);
+                }
+            }
+        }
+    }))
+}
+
+#[cfg(test)]
+mod test {
+    use super::watch_playback;
+    use crate::{clients::playback::PlaybackEventsOptions, ClientError};
+
+    use std::{
+        sync::atomic::{AtomicU32, Ordering},
+        time::Duration,
+    };
+
+    use futures::{future, StreamExt};
+
+    #[test]
+    fn test_errors_keep_polling() {
+        let requests = AtomicU32::new(0);
+        let options = PlaybackEventsOptions {
+            min_interval: Duration::from_millis(1),
+            max_interval: Duration::from_millis(1),
+            ..Default::default()
+        };
+        let events = watch_playback(
+            || match requests.fetch_add(1, Ordering::SeqCst) {
+                1 | 3 => future::err(ClientError::InvalidToken),
+                _ => future::ok(None),
+            },
+            options,
+        );
+
+        let errors = futures::executor::block_on(events.take(2).collect::<Vec<_>>());
+        assert!(errors
+            .iter()
+            .all(|error| matches!(error, Err(ClientError::InvalidToken))));
+        assert_eq!(requests.load(Ordering::SeqCst), 4);
+    }
+}
@@ -0,0 +1,251 @@
+//! Recording of the listening history of the user.
+//!
+//! Spotify only exposes the last 50 plays of the user, through
+//! [`OAuthClient::current_user_recently_played`]. [`HistoryRecorder`]
+//! requests the plays after the last one it saw and appends them into a
+//! [`HistorySink`], so that a complete listening history can be built by
+//! running it periodically, e.g. in a scheduled job. A few sinks are
+//! included:
+//!
+//! * [`JsonlHistorySink`], which appends the plays as JSON lines to a file.
+//! * [`MemoryHistorySink`], which keeps the plays in memory, mostly useful
+//!   for testing.
+//!
+//! Other backends, like a SQL database, can be supported by implementing
+//! [`HistorySink`]. Just like the clients, its methods are asynchronous or
+//! synchronous depending on the HTTP client in use.
+
+use crate::{
+    clients::OAuthClient,
+    model::{PlayHistory, TimeLimits},
+    ClientResult,
+};
+
+use std::{
+    fmt,
+    fs::{File, OpenOptions},
+    io::{BufRead, BufReader, ErrorKind, Write},
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use chrono::{DateTime, Utc};
+use maybe_async::maybe_async;
+use serde::Deserialize;
+
+/// The maximum number of plays Spotify returns per request.
+const PAGE_SIZE: u32 = 50;
+
+/// Keeps the plays recorded by a [`HistoryRecorder`].
+#[cfg_attr(target_arch = "wasm32", maybe_async(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), maybe_async)]
+pub trait HistorySink: fmt::Debug + Send + Sync {
+    /// Returns when the latest play that was recorded was played, or `None`
+    /// if nothing was recorded yet.
+    async fn last_played_at(&self) -> ClientResult<Option<DateTime<Utc>>>;
+
+    /// Records the given plays, which are sorted from oldest to newest.
+    async fn append(&self, plays: &[PlayHistory]) -> ClientResult<()>;
+}
+
+/// Appends the plays to a file, as one JSON object per line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JsonlHistorySink {
+    path: PathBuf,
+}
+
+impl JsonlHistorySink {
+    #[must_use]
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// The path of the file the plays are appended to.
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", maybe_async(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), maybe_async)]
+impl HistorySink for JsonlHistorySink {
+    async fn last_played_at(&self) -> ClientResult<Option<DateTime<Utc>>> {
+        /// Only the date is needed, so the rest of the play isn't parsed.
+        #[derive(Deserialize)]
+        struct Play {
+            played_at: DateTime<Utc>,
+        }
+
+        let file = match File::open(&self.path) {
+            Ok(file) => file,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+
+        let mut last_played_at = None;
+        for line in BufReader::new(file).lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let play = serde_json::from_str::<Play>(&line)?;
+            last_played_at = last_played_at.max(Some(play.played_at));
+        }
+
+        Ok(last_played_at)
+    }
+
+    async fn append(&self, plays: &[PlayHistory]) -> ClientResult<()> {
+        // Written all at once, so that an interrupted run is less likely to
+        // leave half a batch behind.
+        let mut lines = String::new();
+        for play in plays {
+            lines.push_str(&serde_json::to_string(play)?);
+            lines.push('\n');
+        }
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        file.write_all(lines.as_bytes())?;
+        Ok(())
+    }
+}
+
+/// Keeps the plays in memory, so they won't outlive the process.
+#[derive(Debug, Default)]
+pub struct MemoryHistorySink {
+    plays: Mutex<Vec<PlayHistory>>,
+}
+
+impl MemoryHistorySink {
+    #[must_use]
+    pub fn new(plays: Vec<PlayHistory>) -> Self {
+        Self {
+            plays: Mutex::new(plays),
+        }
+    }
+
+    /// The plays recorded so far, from oldest to newest.
+    #[must_use]
+    pub fn plays(&self) -> Vec<PlayHistory> {
+        self.plays.lock().unwrap().clone()
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", maybe_async(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), maybe_async)]
+impl HistorySink for MemoryHistorySink {
+    async fn last_played_at(&self) -> ClientResult<Option<DateTime<Utc>>> {
+        let plays = self.plays.lock().unwrap();
+        Ok(plays.iter().map(|play| play.played_at).max())
+    }
+
+    async fn append(&self, plays: &[PlayHistory]) -> ClientResult<()> {
+        self.plays.lock().unwrap().extend_from_slice(plays);
+        Ok(())
+    }
+}
+
+/// Records the plays of the user into a [`HistorySink`], picking up where the
+/// previous run left off.
+///
+/// Every call to [`Self::record`] requests the plays after the latest one in
+/// the sink with [`TimeLimits::After`], and appends the new ones sorted from
+/// oldest to newest. Plays are told apart by their `played_at`, so the same
+/// play is never recorded twice. Since Spotify doesn't keep more than the
+/// last 50 plays, it should run often enough that no more than that are
+/// played in between, e.g. every hour:
+///
+/// ```ignore
+/// let mut recorder = HistoryRecorder::new(JsonlHistorySink::new("history.jsonl"));
+/// loop {
+///     let plays = recorder.record(&spotify).await?;
+///     println!("Recorded {} plays", plays.len());
+///     tokio::time::sleep(Duration::from_secs(60 * 60)).await;
+/// }
+/// ```
+///
+/// Note that the token must have been granted the
+/// [`Scope::UserReadRecentlyPlayed`](crate::model::Scope::UserReadRecentlyPlayed)
+/// scope.
+#[derive(Debug)]
+pub struct HistoryRecorder<S> {
+    sink: S,
+    last_played_at: Option<DateTime<Utc>>,
+    resumed: bool,
+}
+
+impl<S: HistorySink> HistoryRecorder<S> {
+    /// Creates a recorder that appends the plays to the given sink. It's
+    /// only read the first time the plays are recorded.
+    #[must_use]
+    pub fn new(sink: S) -> Self {
+        Self {
+            sink,
+            last_played_at: None,
+            resumed: false,
+        }
+    }
+
+    /// The sink the plays are appended to.
+    #[must_use]
+    pub fn sink(&self) -> &S {
+        &self.sink
+    }
+
+    /// When the latest recorded play was played, or `None` if nothing was
+    /// recorded yet or the sink hasn't been read.
+    #[must_use]
+    pub fn last_played_at(&self) -> Option<DateTime<Utc>> {
+        self.last_played_at
+    }
+
+    /// Requests the plays since the last time and appends them into the
+    /// sink. Returns the newly recorded plays, from oldest to newest.
+    #[maybe_async]
+    pub async fn record<C: OAuthClient>(&mut self, client: &C) -> ClientResult<Vec<PlayHistory>> {
+        if !self.resumed {
+            self.last_played_at = self.sink.last_played_at().await?;
+            self.resumed = true;
+        }
+
+        let mut recorded = Vec::new();
+        loop {
+            let last_played_at = self.last_played_at;
+            let page = client
+                .current_user_recently_played(
+                    Some(PAGE_SIZE),
+                    last_played_at.map(TimeLimits::After),
+                )
+                .await?;
+            let is_full = page.items.len() >= PAGE_SIZE as usize;
+
+            let mut plays = page
+                .items
+                .into_iter()
+                .filter(|play| last_played_at.map_or(true, |last| play.played_at > last))
+                .collect::<Vec<_>>();
+            plays.sort_by_key(|play| play.played_at);
+            plays.dedup_by_key(|play| play.played_at);
+
+            let latest = match plays.last() {
+                Some(play) => play.played_at,
+                None => break,
+            };
+            self.sink.append(&plays).await?;
+            self.last_played_at = Some(latest);
+            recorded.extend(plays);
+
+            // A full page means there may be more plays after it
+            if !is_full {
+                break;
+            }
+        }
+
+        Ok(recorded)
+    }
+}
@@ -73,8 +73,16 @@
 //! ### CLI utilities
 //!
 //! RSpotify includes basic support for Cli apps to obtain access tokens by
-//! prompting the user, after enabling the `cli` feature. See the
-//! [Authorization](#authorization) section for more information.
+//! prompting the user, after enabling the `cli` feature. If the redirect URI
+//! points to the local machine, like `http://127.0.0.1:8888/callback`, the
+//! code is captured automatically by a temporary HTTP listener on that port.
+//! See the [Authorization](#authorization) section for more information.
+//!
+//! ### Encrypted token cache
+//!
+//! The `encrypted-cache` feature enables an encrypted format for the token
+//! cache, keyed by a secret or a key file, which can be used with
+//! `token_store::EncryptedFileTokenStore`. This is useful on shared machines.
 //!
 //! ## Getting Started
 //!
@@ -123,6 +131,11 @@
 //! embedded at compile time with the [`std::env!`] or
 //! [`dotenv!`](https://crates.io/crates/dotenvy) macros.
 //!
+//! The `wasm` feature includes the `wasm` module, with utilities to follow the
+//! PKCE flow in the browser: redirecting the user to Spotify, completing the
+//! authorization once they're sent back, and storing the token in
+//! `localStorage` or `sessionStorage`.
+//!
 //! ### Examples
 //!
 //! There are some [available examples on the GitHub
@@ -146,8 +159,13 @@ mod auth_code;
 mod auth_code_pkce;
 mod client_creds;
 pub mod clients;
+pub mod history;
+pub mod sessions;
 pub mod sync;
+pub mod token_store;
 mod util;
+#[cfg(all(feature = "wasm", target_arch = "wasm32"))]
+pub mod wasm;
 
 // Subcrate re-exports
 pub use rspotify_http as http;
@@ -159,18 +177,22 @@ pub use auth_code_pkce::AuthCodePkceSpotify;
 pub use client_creds::ClientCredsSpotify;
 pub use macros::scopes;
 pub use model::Token;
+pub use sessions::UserSessions;
+pub use token_store::TokenStore;
 
-use crate::{http::HttpError, model::Id};
+use crate::{http::HttpError, model::Id, token_store::FileTokenStore};
 
 use std::{
     collections::{HashMap, HashSet},
     env, fmt,
     path::PathBuf,
     sync::Arc,
+    time::Duration,
 };
 
 use base64::{engine::general_purpose, Engine as _};
 use getrandom::getrandom;
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
 pub mod prelude {
@@ -237,6 +259,10 @@ pub enum ClientError {
     #[error("cache file error: {0}")]
     CacheFile(String),
 
+    /// Only returned by the `wasm` module.
+    #[error("browser error: {0}")]
+    Browser(String),
+
     #[error("token callback function error: {0}")]
     TokenCallbackFn(#[from] CallbackError),
 
@@ -245,6 +271,18 @@ pub enum ClientError {
 
     #[error("Token is not valid")]
     InvalidToken,
+
+    #[error("the token wasn't granted the `{0}` scope")]
+    MissingScope(model::Scope),
+
+    #[error("the current playback disallows `{0}`")]
+    Disallowed(model::DisallowKey),
+
+    #[error("the URL `{0}` isn't part of the API")]
+    ForeignUrl(String),
+
+    #[error("authorization error: {0}")]
+    Auth(#[from] AuthError),
 }
 
 // The conversion has to be done manually because it's in a `Box<T>`
@@ -256,15 +294,137 @@ impl From<HttpError> for ClientError {
 
 pub type ClientResult<T> = Result<T, ClientError>;
 
+/// Errors in the authorization process, either when the user is redirected
+/// back to the app or when requesting a token to Spotify.
+///
+/// Only some of them can be solved by asking the user to authorize the app
+/// again, which can be checked with [`Self::needs_reauthorization`].
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum AuthError {
+    /// The user didn't authorize the app.
+    #[error("the user denied the authorization")]
+    AccessDenied,
+
+    /// The authorization failed for a different reason, with the `error`
+    /// parameter of the redirect URI.
+    #[error("the authorization failed: {0}")]
+    Authorization(String),
+
+    /// The state of the redirect URI doesn't match the one of the
+    /// authorization request, which may be a CSRF attack, or a redirect from
+    /// an older authorization.
+    #[error("the state of the redirect doesn't match the one of the request")]
+    StateMismatch,
+
+    /// The redirect URI doesn't include a code, or it's not a valid URL.
+    #[error("the redirect doesn't include a code")]
+    MissingCode,
+
+    /// The PKCE code verifier of the authorization request is missing.
+    #[error("the code verifier of the authorization request is missing")]
+    MissingVerifier,
+
+    /// The authorization code is invalid, e.g. because it expired or was
+    /// already used.
+    #[error("invalid authorization code: {0}")]
+    InvalidCode(String),
+
+    /// The refresh token was revoked by the user or has expired, so a new
+    /// authorization is needed.
+    #[error("invalid refresh token: {0}")]
+    InvalidRefreshToken(String),
+
+    /// The client ID or secret are wrong.
+    #[error("invalid client credentials: {0}")]
+    InvalidCredentials(String),
+
+    /// Any other error returned by the token endpoint, with its OAuth error
+    /// code and description.
+    #[error("token request failed: {error}")]
+    TokenRequest {
+        error: String,
+        description: Option<String>,
+    },
+}
+
+impl AuthError {
+    /// Returns whether the error may be solved by asking the user to
+    /// authorize the app again, as opposed to, for example, the user refusing
+    /// to do so or the credentials being wrong.
+    #[must_use]
+    pub fn needs_reauthorization(&self) -> bool {
+        matches!(
+            self,
+            Self::StateMismatch
+                | Self::MissingCode
+                | Self::MissingVerifier
+                | Self::InvalidCode(_)
+                | Self::InvalidRefreshToken(_)
+        )
+    }
+
+    /// Builds the error from the body of an unsuccessful response of the
+    /// token endpoint, which is `{"error": ..., "error_description": ...}`.
+    pub(crate) fn from_token_response(body: &str, grant_type: Option<&str>) -> Self {
+        #[derive(Deserialize)]
+        struct TokenError {
+            error: String,
+            error_description: Option<String>,
+        }
+
+        let TokenError {
+            error,
+            error_description: description,
+        } = match serde_json::from_str(body) {
+            Ok(err) => err,
+            Err(_) => {
+                return Self::TokenRequest {
+                    error: body.to_owned(),
+                    description: None,
+                }
+            }
+        };
+
+        match (error.as_str(), grant_type) {
+            ("invalid_client", _) => Self::InvalidCredentials(description.unwrap_or(error)),
+            ("invalid_grant", Some(params::GRANT_TYPE_REFRESH_TOKEN)) => {
+                Self::InvalidRefreshToken(description.unwrap_or(error))
+            }
+            ("invalid_grant", Some(params::GRANT_TYPE_AUTH_CODE)) => {
+                Self::InvalidCode(description.unwrap_or(error))
+            }
+            _ => Self::TokenRequest { error, description },
+        }
+    }
+}
+
 pub const DEFAULT_API_BASE_URL: &str = "https://api.spotify.com/v1/";
 pub const DEFAULT_AUTH_BASE_URL: &str = "https://accounts.spotify.com/";
 pub const DEFAULT_CACHE_PATH: &str = ".spotify_token_cache.json";
 pub const DEFAULT_PAGINATION_CHUNKS: u32 = 50;
+pub const DEFAULT_TOKEN_REFRESH_MARGIN: Duration = Duration::from_secs(10);
+pub const DEFAULT_REDIRECT_TIMEOUT: Duration = Duration::from_secs(300);
 
 #[derive(Error, Debug)]
 pub enum CallbackError {
     #[error("The callback function raises an error: `{0}`")]
     CustomizedError(String),
+
+    /// Any other error, which is kept as the source of this one.
+    #[error("The callback function raises an error: `{0}`")]
+    Other(#[source] Box<dyn std::error::Error + Send + Sync>),
+}
+
+impl CallbackError {
+    /// Wraps any error raised by the callback function, like a database
+    /// error, so that it can be obtained later on with
+    /// [`Error::source`](std::error::Error::source).
+    pub fn other<E>(err: E) -> Self
+    where
+        E: Into<Box<dyn std::error::Error + Send + Sync>>,
+    {
+        Self::Other(err.into())
+    }
 }
 
 /// A callback function is invokved whenever successfully request or refetch a new token.
@@ -276,6 +436,81 @@ impl fmt::Debug for TokenCallback {
     }
 }
 
+/// A callback function invoked whenever the refresh token changes after
+/// refreshing the access token, with the previous refresh token and the new
+/// one, in that order.
+///
+/// Spotify may rotate the refresh token, in which case the previous one may
+/// stop working, so it's a good place to replace it wherever it's stored.
+///
+/// If it returns an error, it's logged, but the refresh still succeeds and the
+/// client keeps the new refresh token, since the previous one may have been
+/// revoked already.
+#[allow(clippy::type_complexity)]
+pub struct RefreshTokenCallback(
+    pub Box<dyn Fn(String, String) -> Result<(), CallbackError> + Send + Sync>,
+);
+
+impl fmt::Debug for RefreshTokenCallback {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("RefreshTokenCallback")
+    }
+}
+
+/// The future returned by an [`AsyncTokenCallback`].
+#[cfg(all(feature = "__async", not(target_arch = "wasm32")))]
+pub type TokenCallbackFuture = futures::future::BoxFuture<'static, Result<(), CallbackError>>;
+/// The future returned by an [`AsyncTokenCallback`].
+#[cfg(all(feature = "__async", target_arch = "wasm32"))]
+pub type TokenCallbackFuture = futures::future::LocalBoxFuture<'static, Result<(), CallbackError>>;
+
+/// The asynchronous version of [`TokenCallback`], which is awaited whenever
+/// the client successfully requests or refetches a new token. It's useful to
+/// save the token into an asynchronous database, for example, without blocking
+/// the client.
+///
+/// Note: this type is only available for asynchronous clients.
+#[cfg(feature = "__async")]
+pub struct AsyncTokenCallback(pub Box<dyn Fn(Token) -> TokenCallbackFuture + Send + Sync>);
+
+#[cfg(feature = "__async")]
+impl AsyncTokenCallback {
+    /// Boxes the given asynchronous function or closure.
+    ///
+    /// ```
+    /// # use rspotify::AsyncTokenCallback;
+    /// let callback = AsyncTokenCallback::new(|token| async move {
+    ///     println!("new token: {:?}", token);
+    ///     Ok(())
+    /// });
+    /// ```
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn new<F, Fut>(callback: F) -> Self
+    where
+        F: Fn(Token) -> Fut + Send + Sync + 'static,
+        Fut: std::future::Future<Output = Result<(), CallbackError>> + Send + 'static,
+    {
+        Self(Box::new(move |token| Box::pin(callback(token))))
+    }
+
+    /// Boxes the given asynchronous function or closure.
+    #[cfg(target_arch = "wasm32")]
+    pub fn new<F, Fut>(callback: F) -> Self
+    where
+        F: Fn(Token) -> Fut + Send + Sync + 'static,
+        Fut: std::future::Future<Output = Result<(), CallbackError>> + 'static,
+    {
+        Self(Box::new(move |token| Box::pin(callback(token))))
+    }
+}
+
+#[cfg(feature = "__async")]
+impl fmt::Debug for AsyncTokenCallback {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("AsyncTokenCallback")
+    }
+}
+
 /// Struct to configure the Spotify client.
 #[derive(Debug, Clone)]
 pub struct Config {
@@ -295,21 +530,81 @@ pub struct Config {
     /// By default this is [`DEFAULT_PAGINATION_CHUNKS`].
     ///
     /// Note that most endpoints set a maximum to the number of items per
-    /// request, which most times is 50.
+    /// request, which most times is 50. The automatic paginators clamp this
+    /// value to the maximum of each endpoint, and it can be overridden for a
+    /// single paginator with
+    /// [`Paginator::page_size`](crate::clients::pagination::Paginator::page_size).
     pub pagination_chunks: u32,
 
     /// Whether or not to save the authentication token into a JSON file,
     /// then reread the token from JSON file when launching the program without
-    /// following the full auth process again
+    /// following the full auth process again.
+    ///
+    /// This is a shorthand for a [`FileTokenStore`] at `cache_path`, which is
+    /// only used if [`Self::token_store`] isn't set.
     pub token_cached: bool,
 
+    /// Where the authentication token is saved to and read from, in case a
+    /// custom [`TokenStore`] is used. It takes precedence over
+    /// [`Self::token_cached`].
+    pub token_store: Option<Arc<dyn TokenStore>>,
+
     /// Whether or not to check if the token has expired when sending a
     /// request with credentials, and in that case, automatically refresh it.
     pub token_refreshing: bool,
 
+    /// Whether or not to obtain a token automatically when sending a request
+    /// with credentials and there isn't one yet, instead of failing with
+    /// [`ClientError::InvalidToken`]. It's read from the token store first, if
+    /// configured, and requested to Spotify otherwise. Expired tokens are
+    /// refreshed as well, even if [`Self::token_refreshing`] is disabled.
+    ///
+    /// Only [`ClientCredsSpotify`] supports it, since the rest of the flows
+    /// require the user to authorize the app. It's disabled by default.
+    pub token_requesting: bool,
+
+    /// Whether or not to check that the token was granted the scopes required
+    /// by an endpoint before sending the request, failing with
+    /// [`ClientError::MissingScope`] otherwise. Note that the token must have
+    /// been obtained with the scopes included in its response, which is the
+    /// case for tokens requested or refreshed by RSpotify.
+    pub check_scopes: bool,
+
+    /// How long before its expiration the token is considered expired and
+    /// refreshed, both automatically when sending a request and by
+    /// [`BaseClient::keep_token_fresh`](crate::clients::BaseClient::keep_token_fresh).
+    /// By default it's [`DEFAULT_TOKEN_REFRESH_MARGIN`].
+    pub token_refresh_margin: Duration,
+
     /// Whenever client succeeds to request or refresh a token, the callback function
     /// will be invoked
+    ///
+    /// Automatic refreshes hold
+    /// [`BaseClient::get_refresh_lock`](crate::clients::BaseClient::get_refresh_lock)
+    /// while the callback runs, so it mustn't send requests with the same
+    /// client, or else they'll deadlock.
     pub token_callback_fn: Arc<Option<TokenCallback>>,
+
+    /// Whenever the refresh token changes after refreshing the access token,
+    /// this callback function will be invoked with the previous and the new
+    /// refresh tokens. Note that [`Self::token_callback_fn`] is invoked as
+    /// well.
+    pub refresh_token_callback_fn: Arc<Option<RefreshTokenCallback>>,
+
+    /// Same as [`Self::token_callback_fn`], but the callback is asynchronous
+    /// and awaited by the client. If both are configured, the synchronous one
+    /// is invoked first.
+    ///
+    /// Note: this setting is only available for asynchronous clients.
+    #[cfg(feature = "__async")]
+    pub async_token_callback_fn: Arc<Option<AsyncTokenCallback>>,
+
+    /// How long to wait for the browser to be redirected when the redirect
+    /// URI points to the local machine and the code is captured by a
+    /// temporary HTTP listener. By default it's [`DEFAULT_REDIRECT_TIMEOUT`].
+    ///
+    /// Note: this setting is only used with the `cli` feature.
+    pub redirect_timeout: Duration,
 }
 
 impl Default for Config {
@@ -320,8 +615,30 @@ impl Default for Config {
             cache_path: PathBuf::from(DEFAULT_CACHE_PATH),
             pagination_chunks: DEFAULT_PAGINATION_CHUNKS,
             token_cached: false,
+            token_store: None,
             token_refreshing: true,
+            token_requesting: false,
+            check_scopes: false,
+            token_refresh_margin: DEFAULT_TOKEN_REFRESH_MARGIN,
             token_callback_fn: Arc::new(None),
+            refresh_token_callback_fn: Arc::new(None),
+            #[cfg(feature = "__async")]
+            async_token_callback_fn: Arc::new(None),
+            redirect_timeout: DEFAULT_REDIRECT_TIMEOUT,
+        }
+    }
+}
+
+impl Config {
+    /// Returns the store used to persist the token, which is either
+    /// [`Self::token_store`] or a [`FileTokenStore`] if
+    /// [`Self::token_cached`] is enabled.
+    #[must_use]
+    pub fn get_token_store(&self) -> Option<Arc<dyn TokenStore>> {
+        match &self.token_store {
+            Some(store) => Some(Arc::clone(store)),
+            None if self.token_cached => Some(Arc::new(FileTokenStore::new(&self.cache_path))),
+            None => None,
         }
     }
 }
@@ -456,6 +773,36 @@ impl OAuth {
     }
 }
 
+/// The state of an authorization in progress, as returned by
+/// [`AuthCodeSpotify::authorization_request`] and
+/// [`AuthCodePkceSpotify::authorization_request`].
+///
+/// Unlike [`OAuth::state`] and [`AuthCodePkceSpotify::verifier`], it's not kept
+/// inside the client, and a new state is generated for every authorization.
+/// It can be serialized and stored somewhere (e.g. in the user's session or a
+/// database) until the user is redirected back to the app, so that any process
+/// is able to complete the flow with `request_token_with`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuthorizationRequest {
+    /// The URL the user has to open in order to authorize the app.
+    pub url: String,
+    /// The random state included in the URL, which is sent back by Spotify
+    /// when redirecting the user.
+    pub state: String,
+    /// The code verifier, only used by the PKCE flow.
+    pub verifier: Option<String>,
+}
+
+impl AuthorizationRequest {
+    /// Parses the code from the URL the user was redirected to, making sure
+    /// that its state is the one of this request. See
+    /// [`OAuthClient::parse_response_code`](clients::OAuthClient::parse_response_code)
+    /// for more information.
+    pub fn parse_response_code(&self, url: &str) -> Result<String, AuthError> {
+        clients::parse_response_code(url, &self.state)
+    }
+}
+
 #[cfg(test)]
 pub mod test {
     use crate::{alphabets, generate_random_string, Credentials};
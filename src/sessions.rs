@@ -0,0 +1,256 @@
+//! Management of the tokens of multiple users, e.g. in a web backend.
+
+use crate::{
+    alphabets, generate_random_string, http::HttpClient, sync::Mutex, AuthCodeSpotify,
+    ClientResult, Config, Credentials, OAuth, Token, TokenStore,
+};
+
+use std::{
+    collections::HashMap,
+    fmt,
+    hash::Hash,
+    sync::{Arc, Mutex as StdMutex},
+};
+
+use maybe_async::maybe_async;
+
+type StoreFactory<K> = dyn Fn(&K) -> Arc<dyn TokenStore> + Send + Sync;
+
+/// The token of a user, shared by all of their clients along with the lock
+/// that deduplicates its refreshes and the state of their authorization.
+#[derive(Debug, Clone)]
+struct TokenSlot {
+    token: Arc<Mutex<Option<Token>>>,
+    refresh_lock: Arc<Mutex<()>>,
+    state: String,
+}
+
+impl TokenSlot {
+    fn new(token: Option<Token>) -> Self {
+        Self {
+            token: Arc::new(Mutex::new(token)),
+            refresh_lock: Arc::default(),
+            state: generate_random_string(16, alphabets::ALPHANUM),
+        }
+    }
+}
+
+/// Keeps the tokens of multiple users of an app that follows the
+/// [Authorization Code Flow][crate::AuthCodeSpotify], keyed by their user or
+/// session ID.
+///
+/// All the users share the same credentials, OAuth information, configuration
+/// and HTTP client. [`Self::client`] hands out lightweight clients for a
+/// single user, which refresh their token independently of the rest. Since
+/// they share the token with the manager, any change to it (e.g. after
+/// calling [`request_token`] or after it's refreshed automatically) is visible
+/// from the manager and from other clients of the same user.
+///
+/// The [`OAuth::state`] is ignored as well, since sharing it between users
+/// would defeat its purpose. Instead, every user gets their own random state,
+/// which is shared by all of their clients, so that the code can be parsed
+/// with any of them after the redirect. Alternatively,
+/// [`AuthCodeSpotify::authorization_request`] generates a new state for every
+/// authorization.
+///
+/// The configured [`Config::token_store`] and [`Config::token_cached`]
+/// settings are ignored, since all the users would overwrite the same token.
+/// Use [`Self::with_token_stores`] to persist the token of each user
+/// separately instead.
+///
+/// ```ignore
+/// let sessions = UserSessions::new(creds, oauth);
+///
+/// // When a user logs in
+/// let spotify = sessions.client(&session_id).await?;
+/// let url = spotify.get_authorize_url(false)?;
+///
+/// // When they're redirected back to the app
+/// let spotify = sessions.client(&session_id).await?;
+/// let code = spotify.parse_response_code(&redirected_url)?;
+/// spotify.request_token(&code).await?;
+///
+/// // In later requests
+/// if let Some(spotify) = sessions.get(&session_id).await? {
+///     let me = spotify.me().await?;
+/// }
+/// ```
+///
+/// [`request_token`]: crate::clients::OAuthClient::request_token
+#[derive(Clone)]
+pub struct UserSessions<K> {
+    creds: Credentials,
+    oauth: OAuth,
+    config: Config,
+    http: HttpClient,
+    stores: Option<Arc<StoreFactory<K>>>,
+    tokens: Arc<StdMutex<HashMap<K, TokenSlot>>>,
+}
+
+impl<K: Eq + Hash + Clone> UserSessions<K> {
+    /// Creates an empty manager given the credentials and OAuth information
+    /// of the app.
+    #[must_use]
+    pub fn new(creds: Credentials, oauth: OAuth) -> Self {
+        Self::with_config(creds, oauth, Config::default())
+    }
+
+    /// Same as [`Self::new`] but with an extra parameter to configure the
+    /// clients.
+    #[must_use]
+    pub fn with_config(creds: Credentials, oauth: OAuth, config: Config) -> Self {
+        Self {
+            creds,
+            oauth,
+            config: Config {
+                token_cached: false,
+                token_store: None,
+                ..config
+            },
+            http: HttpClient::default(),
+            stores: None,
+            tokens: Arc::default(),
+        }
+    }
+
+    /// Persists the token of each user in the [`TokenStore`] returned by
+    /// `stores`, e.g. a file named after their ID. It's called every time a
+    /// client is built, so it should always return the same backend for the
+    /// same user.
+    ///
+    /// Users that aren't registered yet are loaded from their store the
+    /// first time they're requested with [`Self::client`] or [`Self::get`].
+    #[must_use]
+    pub fn with_token_stores<F>(mut self, stores: F) -> Self
+    where
+        F: Fn(&K) -> Arc<dyn TokenStore> + Send + Sync + 'static,
+    {
+        self.stores = Some(Arc::new(stores));
+        self
+    }
+
+    /// Returns a client for the given user, which is registered if it's the
+    /// first time, with the token in their store if any. Otherwise, the token
+    /// can be obtained by following the authorization process with the
+    /// returned client.
+    #[maybe_async]
+    pub async fn client(&self, key: &K) -> ClientResult<AuthCodeSpotify> {
+        let slot = match self.slot(key) {
+            Some(slot) => slot,
+            None => {
+                let token = self.load_token(key).await?;
+                self.register(key, TokenSlot::new(token))
+            }
+        };
+        Ok(self.build_client(key, slot))
+    }
+
+    /// Returns a client for the given user, or `None` if they aren't
+    /// registered and there's no token in their store.
+    #[maybe_async]
+    pub async fn get(&self, key: &K) -> ClientResult<Option<AuthCodeSpotify>> {
+        let slot = match self.slot(key) {
+            Some(slot) => slot,
+            None => match self.load_token(key).await? {
+                Some(token) => self.register(key, TokenSlot::new(Some(token))),
+                None => return Ok(None),
+            },
+        };
+        Ok(Some(self.build_client(key, slot)))
+    }
+
+    /// Registers the user with the given token, replacing the previous one.
+    /// Returns their client.
+    pub fn insert(&self, key: K, token: Token) -> AuthCodeSpotify {
+        let slot = TokenSlot::new(Some(token));
+        self.tokens
+            .lock()
+            .unwrap()
+            .insert(key.clone(), slot.clone());
+        self.build_client(&key, slot)
+    }
+
+    /// Unregisters the user. Their existing clients will keep working, but
+    /// they won't be returned by [`Self::get`] anymore. Note that their
+    /// token store isn't cleared.
+    ///
+    /// Returns whether the user was registered.
+    pub fn remove(&self, key: &K) -> bool {
+        self.tokens.lock().unwrap().remove(key).is_some()
+    }
+
+    /// Returns whether the user is registered.
+    #[must_use]
+    pub fn contains(&self, key: &K) -> bool {
+        self.tokens.lock().unwrap().contains_key(key)
+    }
+
+    /// Returns the IDs of the registered users.
+    #[must_use]
+    pub fn keys(&self) -> Vec<K> {
+        self.tokens.lock().unwrap().keys().cloned().collect()
+    }
+
+    /// Returns the number of registered users.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.tokens.lock().unwrap().len()
+    }
+
+    /// Returns whether there are no registered users.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn slot(&self, key: &K) -> Option<TokenSlot> {
+        self.tokens.lock().unwrap().get(key).cloned()
+    }
+
+    /// Registers the user unless they were registered concurrently, in which
+    /// case the existing slot is kept. Returns the registered slot.
+    fn register(&self, key: &K, slot: TokenSlot) -> TokenSlot {
+        self.tokens
+            .lock()
+            .unwrap()
+            .entry(key.clone())
+            .or_insert(slot)
+            .clone()
+    }
+
+    #[maybe_async]
+    async fn load_token(&self, key: &K) -> ClientResult<Option<Token>> {
+        match &self.stores {
+            Some(stores) => stores(key).load().await,
+            None => Ok(None),
+        }
+    }
+
+    fn build_client(&self, key: &K, slot: TokenSlot) -> AuthCodeSpotify {
+        let mut config = self.config.clone();
+        config.token_store = self.stores.as_ref().map(|stores| stores(key));
+
+        AuthCodeSpotify {
+            creds: self.creds.clone(),
+            oauth: OAuth {
+                state: slot.state,
+                ..self.oauth.clone()
+            },
+            config,
+            token: slot.token,
+            http: self.http.clone(),
+            refresh_lock: slot.refresh_lock,
+        }
+    }
+}
+
+impl<K: fmt::Debug> fmt::Debug for UserSessions<K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("UserSessions")
+            .field("creds", &self.creds)
+            .field("oauth", &self.oauth)
+            .field("config", &self.config)
+            .field("tokens", &self.tokens)
+            .finish_non_exhaustive()
+    }
+}
@@ -1 +1,6 @@
 pub use std::sync::Mutex;
+
+/// Blocks the current thread for the given duration.
+pub fn sleep(duration: std::time::Duration) {
+    std::thread::sleep(duration);
+}
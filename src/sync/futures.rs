@@ -14,3 +14,8 @@ impl<T> Mutex<T> {
         Ok(val)
     }
 }
+
+/// Waits for the given duration without blocking the thread.
+pub async fn sleep(duration: std::time::Duration) {
+    futures_timer::Delay::new(duration).await;
+}
@@ -14,3 +14,8 @@ use self::futures as imp;
 /// A type alias for either an asynchronous mutex or [`std::sync::Mutex`], depending on whether
 /// this library is compiled in asynchronous or synchronous mode.
 pub type Mutex<T> = imp::Mutex<T>;
+
+/// Waits for the given duration, either asynchronously or by blocking the
+/// current thread, depending on whether this library is compiled in
+/// asynchronous or synchronous mode.
+pub use self::imp::sleep;
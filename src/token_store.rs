@@ -0,0 +1,228 @@
+//! Persistence of the authentication token.
+//!
+//! The clients save their token into the [`TokenStore`] configured in
+//! [`Config::token_store`] whenever it's requested or refreshed, and they may
+//! load it from there in order to avoid following the full authorization
+//! process again. A few implementations are included:
+//!
+//! * [`FileTokenStore`], which saves the token as JSON into a file. This is
+//!   what's used when [`Config::token_cached`] is enabled.
+//! * [`MemoryTokenStore`], which keeps the token in memory, mostly useful for
+//!   testing.
+//! * [`EnvTokenStore`], which reads the token as JSON from an environment
+//!   variable, e.g. one set by a parent process or by a CI secret. It's
+//!   read-only.
+//! * `EncryptedFileTokenStore`, which saves the token into an encrypted file.
+//!   It requires the `encrypted-cache` feature.
+//!
+//! Other backends, like Redis or a SQL database, can be supported by
+//! implementing [`TokenStore`]. Just like the clients, its methods are
+//! asynchronous or synchronous depending on the HTTP client in use.
+//!
+//! [`Config::token_store`]: crate::Config::token_store
+//! [`Config::token_cached`]: crate::Config::token_cached
+
+use crate::{model::ModelError, ClientError, ClientResult, Token};
+
+use std::{
+    env, fmt,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use maybe_async::maybe_async;
+
+/// Loads, saves and clears the token of a client.
+#[cfg_attr(target_arch = "wasm32", maybe_async(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), maybe_async)]
+pub trait TokenStore: fmt::Debug + Send + Sync {
+    /// Returns the stored token, or `None` if there isn't one.
+    async fn load(&self) -> ClientResult<Option<Token>>;
+
+    /// Stores the given token, replacing the previous one.
+    async fn save(&self, token: &Token) -> ClientResult<()>;
+
+    /// Removes the stored token, if any.
+    async fn clear(&self) -> ClientResult<()>;
+}
+
+/// Stores the token as JSON in a file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileTokenStore {
+    path: PathBuf,
+}
+
+impl FileTokenStore {
+    #[must_use]
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// The path of the file the token is stored in.
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", maybe_async(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), maybe_async)]
+impl TokenStore for FileTokenStore {
+    async fn load(&self) -> ClientResult<Option<Token>> {
+        match Token::from_cache(&self.path) {
+            Ok(token) => Ok(Some(token)),
+            Err(ModelError::Io(err)) if err.kind() == ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    async fn save(&self, token: &Token) -> ClientResult<()> {
+        Ok(token.write_cache(&self.path)?)
+    }
+
+    async fn clear(&self) -> ClientResult<()> {
+        match std::fs::remove_file(&self.path) {
+            Err(err) if err.kind() != ErrorKind::NotFound => Err(err.into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Stores the token in a file encrypted with a [`CacheKey`], so that it can't
+/// be read by other users on shared machines.
+///
+/// Note: this type requires the `encrypted-cache` feature.
+///
+/// [`CacheKey`]: crate::model::CacheKey
+#[cfg(feature = "encrypted-cache")]
+#[derive(Debug, Clone)]
+pub struct EncryptedFileTokenStore {
+    path: PathBuf,
+    key: crate::model::CacheKey,
+}
+
+#[cfg(feature = "encrypted-cache")]
+impl EncryptedFileTokenStore {
+    #[must_use]
+    pub fn new<P: Into<PathBuf>>(path: P, key: crate::model::CacheKey) -> Self {
+        Self {
+            path: path.into(),
+            key,
+        }
+    }
+
+    /// The path of the file the token is stored in.
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+#[cfg(feature = "encrypted-cache")]
+#[cfg_attr(target_arch = "wasm32", maybe_async(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), maybe_async)]
+impl TokenStore for EncryptedFileTokenStore {
+    async fn load(&self) -> ClientResult<Option<Token>> {
+        match Token::from_encrypted_cache(&self.path, &self.key) {
+            Ok(token) => Ok(Some(token)),
+            Err(ModelError::Io(err)) if err.kind() == ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    async fn save(&self, token: &Token) -> ClientResult<()> {
+        Ok(token.write_encrypted_cache(&self.path, &self.key)?)
+    }
+
+    async fn clear(&self) -> ClientResult<()> {
+        match std::fs::remove_file(&self.path) {
+            Err(err) if err.kind() != ErrorKind::NotFound => Err(err.into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Keeps the token in memory, so it won't outlive the process.
+#[derive(Debug, Default)]
+pub struct MemoryTokenStore {
+    token: Mutex<Option<Token>>,
+}
+
+impl MemoryTokenStore {
+    #[must_use]
+    pub fn new(token: Option<Token>) -> Self {
+        Self {
+            token: Mutex::new(token),
+        }
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", maybe_async(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), maybe_async)]
+impl TokenStore for MemoryTokenStore {
+    async fn load(&self) -> ClientResult<Option<Token>> {
+        Ok(self.token.lock().unwrap().clone())
+    }
+
+    async fn save(&self, token: &Token) -> ClientResult<()> {
+        *self.token.lock().unwrap() = Some(token.clone());
+        Ok(())
+    }
+
+    async fn clear(&self) -> ClientResult<()> {
+        *self.token.lock().unwrap() = None;
+        Ok(())
+    }
+}
+
+/// Reads the token as JSON from an environment variable, e.g. one set by a
+/// parent process or by a CI secret.
+///
+/// This store is read-only: saving or clearing the token does nothing, since
+/// modifying the environment while other threads may be running is unsound.
+/// Refreshed tokens are only kept in memory by the client, so use
+/// [`Config::token_callback_fn`](crate::Config::token_callback_fn) to persist
+/// them elsewhere if needed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EnvTokenStore {
+    var: String,
+}
+
+impl EnvTokenStore {
+    #[must_use]
+    pub fn new<S: Into<String>>(var: S) -> Self {
+        Self { var: var.into() }
+    }
+
+    /// The name of the environment variable the token is read from.
+    #[must_use]
+    pub fn var(&self) -> &str {
+        &self.var
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", maybe_async(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), maybe_async)]
+impl TokenStore for EnvTokenStore {
+    async fn load(&self) -> ClientResult<Option<Token>> {
+        match env::var(&self.var) {
+            Ok(json) => Ok(Some(serde_json::from_str(&json)?)),
+            Err(env::VarError::NotPresent) => Ok(None),
+            Err(err) => Err(ClientError::CacheFile(format!(
+                "invalid token in `{}`: {}",
+                self.var, err
+            ))),
+        }
+    }
+
+    async fn save(&self, _token: &Token) -> ClientResult<()> {
+        log::info!("Token save ignored (`{}` is read-only)", self.var);
+        Ok(())
+    }
+
+    async fn clear(&self) -> ClientResult<()> {
+        log::info!("Token clear ignored (`{}` is read-only)", self.var);
+        Ok(())
+    }
+}
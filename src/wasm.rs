@@ -0,0 +1,268 @@
+//! Utilities to follow the [PKCE flow][crate::AuthCodePkceSpotify] from the
+//! browser, when compiling to WebAssembly.
+//!
+//! The flow requires redirecting the user to Spotify, which reloads the page,
+//! so the state and the code verifier have to be kept somewhere in the
+//! meantime. [`redirect_to_authorize_url`] starts a new
+//! [`AuthorizationRequest`], saves it into the session storage and redirects
+//! the user. Once they're sent back to the app, [`complete_authorization`]
+//! reads the code and the state from `window.location` and requests the
+//! token:
+//!
+//! ```ignore
+//! let spotify = AuthCodePkceSpotify::with_config(creds, oauth, Config {
+//!     token_store: Some(Arc::new(WebStorageTokenStore::local("spotify_token"))),
+//!     ..Default::default()
+//! });
+//!
+//! match spotify.read_token_cache(false).await? {
+//!     Some(token) => *spotify.token.lock().await.unwrap() = Some(token),
+//!     None => {
+//!         if !wasm::complete_authorization(&spotify).await? {
+//!             // Reloads the page, so nothing else should be done afterwards
+//!             wasm::redirect_to_authorize_url(&spotify, None)?;
+//!             return Ok(());
+//!         }
+//!     }
+//! }
+//! ```
+//!
+//! Tokens can be persisted in the browser with [`WebStorageTokenStore`].
+//!
+//! Note: this module requires the `wasm` feature, and it's only available for
+//! the `wasm32` target.
+
+use crate::{
+    AuthCodePkceSpotify, AuthorizationRequest, ClientError, ClientResult, Token, TokenStore,
+};
+
+use maybe_async::maybe_async;
+use web_sys::{wasm_bindgen::JsValue, Storage, Window};
+
+/// The key of the session storage under which the authorization request is
+/// kept during the redirect.
+pub const AUTHORIZATION_REQUEST_KEY: &str = "rspotify_authorization_request";
+
+fn browser_error(context: &str, err: JsValue) -> ClientError {
+    ClientError::Browser(format!("{}: {:?}", context, err))
+}
+
+fn window() -> ClientResult<Window> {
+    web_sys::window().ok_or_else(|| ClientError::Browser("there is no window".to_owned()))
+}
+
+/// The storage areas of the browser, which keep data across page loads.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WebStorage {
+    /// `window.localStorage`, which is kept until it's cleared.
+    Local,
+    /// `window.sessionStorage`, which is cleared when the tab is closed.
+    Session,
+}
+
+impl WebStorage {
+    fn get(self) -> ClientResult<Storage> {
+        let window = window()?;
+        let storage = match self {
+            Self::Local => window.local_storage(),
+            Self::Session => window.session_storage(),
+        };
+
+        storage
+            .map_err(|err| browser_error("couldn't access the storage", err))?
+            .ok_or_else(|| ClientError::Browser("the storage isn't available".to_owned()))
+    }
+
+    /// Returns the value stored under `key`, or `None` if there isn't one.
+    pub fn get_item(self, key: &str) -> ClientResult<Option<String>> {
+        self.get()?
+            .get_item(key)
+            .map_err(|err| browser_error("couldn't read from the storage", err))
+    }
+
+    /// Stores `value` under `key`, replacing the previous one.
+    pub fn set_item(self, key: &str, value: &str) -> ClientResult<()> {
+        self.get()?
+            .set_item(key, value)
+            .map_err(|err| browser_error("couldn't write to the storage", err))
+    }
+
+    /// Removes the value stored under `key`, if any.
+    pub fn remove_item(self, key: &str) -> ClientResult<()> {
+        self.get()?
+            .remove_item(key)
+            .map_err(|err| browser_error("couldn't write to the storage", err))
+    }
+}
+
+/// Stores the token as JSON in the local or session storage of the browser.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WebStorageTokenStore {
+    storage: WebStorage,
+    key: String,
+}
+
+impl WebStorageTokenStore {
+    #[must_use]
+    pub fn new<S: Into<String>>(storage: WebStorage, key: S) -> Self {
+        Self {
+            storage,
+            key: key.into(),
+        }
+    }
+
+    /// Stores the token in `window.localStorage`, so that the user doesn't
+    /// have to log in again after closing the tab.
+    #[must_use]
+    pub fn local<S: Into<String>>(key: S) -> Self {
+        Self::new(WebStorage::Local, key)
+    }
+
+    /// Stores the token in `window.sessionStorage`, so that it's forgotten
+    /// after closing the tab.
+    #[must_use]
+    pub fn session<S: Into<String>>(key: S) -> Self {
+        Self::new(WebStorage::Session, key)
+    }
+
+    /// The storage the token is kept in.
+    #[must_use]
+    pub fn storage(&self) -> WebStorage {
+        self.storage
+    }
+
+    /// The key the token is stored under.
+    #[must_use]
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+}
+
+#[maybe_async(?Send)]
+impl TokenStore for WebStorageTokenStore {
+    async fn load(&self) -> ClientResult<Option<Token>> {
+        match self.storage.get_item(&self.key)? {
+            Some(json) => Ok(Some(serde_json::from_str(&json)?)),
+            None => Ok(None),
+        }
+    }
+
+    async fn save(&self, token: &Token) -> ClientResult<()> {
+        self.storage
+            .set_item(&self.key, &serde_json::to_string(token)?)
+    }
+
+    async fn clear(&self) -> ClientResult<()> {
+        self.storage.remove_item(&self.key)
+    }
+}
+
+/// Returns the URL of the current page, i.e., `window.location.href`.
+pub fn current_url() -> ClientResult<String> {
+    window()?
+        .location()
+        .href()
+        .map_err(|err| browser_error("couldn't read the location", err))
+}
+
+/// Starts a new authorization with
+/// [`AuthCodePkceSpotify::authorization_request`] and redirects the user to
+/// its URL, after saving the request into the session storage under
+/// [`AUTHORIZATION_REQUEST_KEY`]. The page is unloaded afterwards.
+///
+/// See [`AuthCodePkceSpotify::get_authorize_url`] for more information about
+/// `verifier_bytes`.
+pub fn redirect_to_authorize_url(
+    spotify: &AuthCodePkceSpotify,
+    verifier_bytes: Option<usize>,
+) -> ClientResult<()> {
+    let request = spotify.authorization_request(verifier_bytes)?;
+    WebStorage::Session.set_item(AUTHORIZATION_REQUEST_KEY, &serde_json::to_string(&request)?)?;
+
+    log::info!("Redirecting to the authorization URL");
+    window()?
+        .location()
+        .set_href(&request.url)
+        .map_err(|err| browser_error("couldn't redirect to the authorization URL", err))
+}
+
+/// Completes the authorization started by [`redirect_to_authorize_url`] when
+/// the user is sent back to the app, by parsing the code and the state from
+/// `window.location` and requesting the token with
+/// [`AuthCodePkceSpotify::request_token_with`].
+///
+/// Returns `false` if there's no authorization in progress, i.e., the page
+/// wasn't loaded after a redirect from Spotify. Otherwise, the saved
+/// request is removed and the authorization parameters are removed from the
+/// address bar, so that reloading the page doesn't reuse them.
+pub async fn complete_authorization(spotify: &AuthCodePkceSpotify) -> ClientResult<bool> {
+    let url = current_url()?;
+    let redirected = url::Url::parse(&url)?
+        .query_pairs()
+        .any(|(key, _)| key == "code" || key == "error");
+    if !redirected {
+        return Ok(false);
+    }
+
+    let request = match WebStorage::Session.get_item(AUTHORIZATION_REQUEST_KEY)? {
+        Some(json) => serde_json::from_str::<AuthorizationRequest>(&json)?,
+        None => return Ok(false),
+    };
+    WebStorage::Session.remove_item(AUTHORIZATION_REQUEST_KEY)?;
+    forget_authorization_params(&url)?;
+
+    let code = request.parse_response_code(&url)?;
+    spotify.request_token_with(&code, &request).await?;
+    Ok(true)
+}
+
+/// Removes the query parameters sent by Spotify from the address bar, without
+/// reloading the page.
+fn forget_authorization_params(url: &str) -> ClientResult<()> {
+    let url = without_authorization_params(url)?;
+
+    window()?
+        .history()
+        .and_then(|history| history.replace_state_with_url(&JsValue::NULL, "", Some(&url)))
+        .map_err(|err| browser_error("couldn't update the location", err))
+}
+
+/// Returns the given URL without the `code`, `state` and `error` query
+/// parameters sent by Spotify, keeping the rest of them.
+fn without_authorization_params(url: &str) -> ClientResult<String> {
+    let mut url = url::Url::parse(url)?;
+    let params = url
+        .query_pairs()
+        .filter(|(key, _)| !matches!(&**key, "code" | "state" | "error"))
+        .map(|(key, value)| (key.into_owned(), value.into_owned()))
+        .collect::<Vec<_>>();
+
+    if params.is_empty() {
+        url.set_query(None);
+    } else {
+        url.query_pairs_mut().clear().extend_pairs(params);
+    }
+    Ok(url.into())
+}
+
+#[cfg(test)]
+mod test {
+    use super::without_authorization_params;
+    use wasm_bindgen_test::*;
+
+    #[wasm_bindgen_test]
+    fn test_without_authorization_params() {
+        let url = "https://example.com/callback?code=abc&state=xyz";
+        assert_eq!(
+            without_authorization_params(url).unwrap(),
+            "https://example.com/callback"
+        );
+
+        // The parameters of the app itself are kept
+        let url = "https://example.com/?page=2&error=access_denied&state=xyz&tab=top#top";
+        assert_eq!(
+            without_authorization_params(url).unwrap(),
+            "https://example.com/?page=2&tab=top#top"
+        );
+    }
+}
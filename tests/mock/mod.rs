@@ -0,0 +1,128 @@
+//! A local mock of the Spotify accounts service and API, shared by the tests
+//! that don't require real credentials.
+#![allow(dead_code)]
+
+use chrono::{Duration, Utc};
+use rspotify::{AuthCodeSpotify, Config, Token};
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    net::TcpListener,
+    sync::{Arc, Mutex},
+    thread,
+};
+
+/// A request received by the [`MockServer`].
+#[derive(Debug, Clone)]
+pub struct MockRequest {
+    pub method: String,
+    pub path: String,
+    pub authorization: Option<String>,
+    pub body: String,
+}
+
+/// A minimal HTTP server for both the accounts service and the API, which
+/// answers every request with `respond`. It returns the status code and the
+/// JSON body of the response.
+pub struct MockServer {
+    pub url: String,
+    requests: Arc<Mutex<Vec<MockRequest>>>,
+}
+
+impl MockServer {
+    pub fn start<F>(respond: F) -> Self
+    where
+        F: Fn(&MockRequest) -> (u16, String) + Send + 'static,
+    {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+
+        let server_requests = Arc::clone(&requests);
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(&stream);
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+                let mut request_line = request_line.split_whitespace();
+                let method = request_line.next().unwrap().to_owned();
+                let path = request_line.next().unwrap().to_owned();
+                let mut authorization = None;
+                let mut content_length = 0;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    let line = line.trim_end();
+                    if line.is_empty() {
+                        break;
+                    }
+                    if let Some((name, value)) = line.split_once(':') {
+                        if name.eq_ignore_ascii_case("content-length") {
+                            content_length = value.trim().parse().unwrap();
+                        } else if name.eq_ignore_ascii_case("authorization") {
+                            authorization = Some(value.trim().to_owned());
+                        }
+                    }
+                }
+                let mut body = vec![0; content_length];
+                reader.read_exact(&mut body).unwrap();
+                let request = MockRequest {
+                    method,
+                    path,
+                    authorization,
+                    body: String::from_utf8(body).unwrap(),
+                };
+
+                // Slow enough for concurrent requests to overlap
+                thread::sleep(std::time::Duration::from_millis(100));
+                let (status, response) = respond(&request);
+                server_requests.lock().unwrap().push(request);
+                // Rate limited requests may be retried right away
+                let retry_after = if status == 429 {
+                    "Retry-After: 0\r\n"
+                } else {
+                    ""
+                };
+                write!(
+                    stream,
+                    "HTTP/1.1 {} Mock\r\nContent-Type: application/json\r\n{}\
+                     Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    retry_after,
+                    response.len(),
+                    response
+                )
+                .unwrap();
+            }
+        });
+
+        Self { url, requests }
+    }
+
+    pub fn requests(&self) -> Vec<MockRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    /// The requests sent to the token endpoint.
+    pub fn token_requests(&self) -> Vec<MockRequest> {
+        self.requests()
+            .into_iter()
+            .filter(|request| request.path == "/api/token")
+            .collect()
+    }
+}
+
+/// A client for the API of the given server, with a valid access token.
+pub fn client(server: &MockServer) -> AuthCodeSpotify {
+    let token = Token {
+        access_token: "access".to_owned(),
+        expires_at: Some(Utc::now() + Duration::try_minutes(30).unwrap()),
+        ..Default::default()
+    };
+    let mut spotify = AuthCodeSpotify::from_token(token);
+    spotify.config = Config {
+        api_base_url: server.url.clone(),
+        ..Default::default()
+    };
+    spotify
+}
@@ -84,3 +84,23 @@ fn test_convert_country_from_str() {
     let zimbabwe = Country::Zimbabwe;
     assert_eq!(<&str>::from(zimbabwe), "ZW");
 }
+
+#[test]
+#[wasm_bindgen_test]
+fn test_scope() {
+    use rspotify::macros::KNOWN_SCOPES;
+    use std::str::FromStr;
+    use strum::IntoEnumIterator;
+
+    let playback = Scope::UserReadPlaybackState;
+    assert_eq!(<&str>::from(playback), "user-read-playback-state");
+    assert_eq!(
+        Scope::from_str("ugc-image-upload"),
+        Ok(Scope::UgcImageUpload)
+    );
+    assert!(Scope::from_str("user-read-playback-stat").is_err());
+
+    // The `scopes!` macro accepts exactly the same scopes
+    let scopes = Scope::iter().map(<&str>::from).collect::<Vec<_>>();
+    assert_eq!(scopes, KNOWN_SCOPES);
+}
@@ -0,0 +1,161 @@
+//! Tests for the listening history recorder, against a local mock of the
+//! Spotify API.
+#![cfg(not(target_arch = "wasm32"))]
+
+mod mock;
+
+use mock::{client, MockRequest, MockServer};
+
+use std::sync::{Arc, Mutex};
+
+use chrono::prelude::*;
+use rspotify::history::{HistoryRecorder, HistorySink, JsonlHistorySink, MemoryHistorySink};
+use serde_json::{json, Value};
+
+fn played_at(seconds: i64) -> DateTime<Utc> {
+    DateTime::from_timestamp(1_607_774_342 + seconds, 0).unwrap()
+}
+
+fn play(seconds: i64) -> Value {
+    json!({
+        "track": {
+            "album": {
+                "album_type": "album",
+                "artists": [],
+                "external_urls": {},
+                "href": null,
+                "id": null,
+                "images": [],
+                "name": "Album"
+            },
+            "artists": [],
+            "disc_number": 1,
+            "duration_ms": 180000,
+            "explicit": false,
+            "external_ids": {},
+            "external_urls": {},
+            "href": null,
+            "id": "4iV5W9uYEdYUVa79Axb7Rh",
+            "is_local": false,
+            "name": "Track",
+            "popularity": 0,
+            "preview_url": null,
+            "track_number": 1
+        },
+        "played_at": played_at(seconds),
+        "context": null
+    })
+}
+
+/// Answers the recently played endpoint with the plays at the given seconds,
+/// newest first, ignoring the ones before its `after` parameter.
+fn history_api(plays: Arc<Mutex<Vec<i64>>>) -> impl Fn(&MockRequest) -> (u16, String) {
+    move |request| {
+        let after = request
+            .path
+            .split(|c| c == '?' || c == '&')
+            .find_map(|param| param.strip_prefix("after="))
+            .map(|after| after.parse::<i64>().unwrap());
+
+        let mut plays = plays.lock().unwrap().clone();
+        plays.sort_unstable_by(|a, b| b.cmp(a));
+        let items = plays
+            .into_iter()
+            .filter(|seconds| {
+                after.map_or(true, |after| {
+                    played_at(*seconds).timestamp_millis() >= after
+                })
+            })
+            .map(play)
+            .collect::<Vec<_>>();
+        let page = json!({
+            "href": "https://api.spotify.com/v1/me/player/recently-played",
+            "items": items,
+            "limit": 50,
+            "next": null,
+            "cursors": null,
+            "total": null
+        });
+        (200, page.to_string())
+    }
+}
+
+fn played_ats(plays: &[rspotify::model::PlayHistory]) -> Vec<DateTime<Utc>> {
+    plays.iter().map(|play| play.played_at).collect()
+}
+
+#[maybe_async::test(feature = "__sync", async(feature = "__async", tokio::test))]
+async fn test_history_recorder() {
+    // The same play may be returned twice
+    let plays = Arc::new(Mutex::new(vec![10, 30, 20, 20]));
+    let server = MockServer::start(history_api(Arc::clone(&plays)));
+    let spotify = client(&server);
+
+    let mut recorder = HistoryRecorder::new(MemoryHistorySink::default());
+    let recorded = recorder.record(&spotify).await.unwrap();
+    assert_eq!(
+        played_ats(&recorded),
+        [played_at(10), played_at(20), played_at(30)]
+    );
+    assert_eq!(recorder.last_played_at(), Some(played_at(30)));
+
+    // Only the new plays are recorded, even if the API includes the last one
+    plays.lock().unwrap().push(40);
+    let recorded = recorder.record(&spotify).await.unwrap();
+    assert_eq!(played_ats(&recorded), [played_at(40)]);
+    let recorded = recorder.record(&spotify).await.unwrap();
+    assert!(recorded.is_empty());
+
+    assert_eq!(
+        played_ats(&recorder.sink().plays()),
+        [played_at(10), played_at(20), played_at(30), played_at(40)]
+    );
+    let after = format!("after={}", played_at(30).timestamp_millis());
+    let requests = server.requests();
+    assert!(!requests[0].path.contains("after="));
+    assert!(requests[1].path.contains(&after));
+}
+
+#[maybe_async::test(feature = "__sync", async(feature = "__async", tokio::test))]
+async fn test_history_jsonl_sink() {
+    let path = std::env::temp_dir().join(format!(
+        "rspotify-test-history-{}.jsonl",
+        std::process::id()
+    ));
+    let _ = std::fs::remove_file(&path);
+
+    let plays = Arc::new(Mutex::new(vec![10, 20]));
+    let server = MockServer::start(history_api(Arc::clone(&plays)));
+    let spotify = client(&server);
+
+    let sink = JsonlHistorySink::new(&path);
+    let last_played_at = sink.last_played_at().await.unwrap();
+    assert_eq!(last_played_at, None);
+    let mut recorder = HistoryRecorder::new(sink);
+    let recorded = recorder.record(&spotify).await.unwrap();
+    assert_eq!(recorded.len(), 2);
+
+    // A new recorder resumes from the file, as in a later run of a job
+    plays.lock().unwrap().push(30);
+    let mut recorder = HistoryRecorder::new(JsonlHistorySink::new(&path));
+    let recorded = recorder.record(&spotify).await.unwrap();
+    assert_eq!(played_ats(&recorded), [played_at(30)]);
+
+    let contents = std::fs::read_to_string(&path).unwrap();
+    let lines = contents
+        .lines()
+        .map(|line| serde_json::from_str::<Value>(line).unwrap()["played_at"].clone())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        lines,
+        [
+            json!(played_at(10)),
+            json!(played_at(20)),
+            json!(played_at(30))
+        ]
+    );
+    let last_played_at = recorder.sink().last_played_at().await.unwrap();
+    assert_eq!(last_played_at, Some(played_at(30)));
+
+    std::fs::remove_file(&path).unwrap();
+}
@@ -1,9 +1,14 @@
 use chrono::prelude::*;
 use chrono::Duration;
 use rspotify::{
-    prelude::*, scopes, AuthCodeSpotify, ClientCredsSpotify, Config, Credentials, OAuth, Token,
+    model::Scope,
+    prelude::*,
+    scopes,
+    token_store::{EnvTokenStore, FileTokenStore, MemoryTokenStore},
+    AuthCodePkceSpotify, AuthCodeSpotify, AuthError, AuthorizationRequest, ClientCredsSpotify,
+    ClientError, Config, Credentials, OAuth, Token, TokenStore, UserSessions,
 };
-use std::{collections::HashMap, fs, io::Read, path::PathBuf};
+use std::{collections::HashMap, fs, io::Read, path::PathBuf, sync::Arc};
 use url::Url;
 use wasm_bindgen_test::*;
 
@@ -34,6 +39,52 @@ fn test_get_authorize_url() {
     assert_eq!(hash_query.get("state").unwrap(), "fdsafdsfa");
 }
 
+#[test]
+#[wasm_bindgen_test]
+fn test_authorization_request() {
+    let oauth = OAuth {
+        state: "fdsafdsfa".to_owned(),
+        redirect_uri: "http://localhost/callback".to_owned(),
+        scopes: scopes!("playlist-read-private"),
+        ..Default::default()
+    };
+    let creds = Credentials::new("this-is-my-client-id", "this-is-my-client-secret");
+    let spotify = AuthCodePkceSpotify::new(creds.clone(), oauth.clone());
+
+    let request = spotify.authorization_request(None).unwrap();
+    let hash_query: HashMap<_, _> = Url::parse(&request.url)
+        .unwrap()
+        .query_pairs()
+        .into_owned()
+        .collect();
+
+    // Each request has its own state, and the client isn't modified
+    assert_eq!(hash_query.get("state").unwrap(), &request.state);
+    assert_ne!(request.state, "fdsafdsfa");
+    assert_ne!(spotify.authorization_request(None).unwrap(), request);
+    assert!(spotify.verifier.is_none());
+    assert_eq!(request.verifier.as_ref().unwrap().len(), 43);
+    assert!(hash_query.contains_key("code_challenge"));
+
+    // It can be stored and restored later on
+    let json = serde_json::to_string(&request).unwrap();
+    let restored: AuthorizationRequest = serde_json::from_str(&json).unwrap();
+    assert_eq!(restored, request);
+
+    let redirect = format!("http://localhost/callback?code=abc&state={}", request.state);
+    assert_eq!(restored.parse_response_code(&redirect).unwrap(), "abc");
+    let forged = "http://localhost/callback?code=abc&state=fdsafdsfa";
+    assert_eq!(
+        restored.parse_response_code(forged),
+        Err(AuthError::StateMismatch)
+    );
+
+    let spotify = AuthCodeSpotify::new(creds, oauth);
+    let request = spotify.authorization_request(true).unwrap();
+    assert!(request.verifier.is_none());
+    assert!(request.url.contains(&format!("state={}", request.state)));
+}
+
 #[maybe_async::test(feature = "__sync", async(feature = "__async", tokio::test))]
 async fn test_read_token_cache() {
     let expires_in = Duration::try_seconds(3600).unwrap();
@@ -118,6 +169,153 @@ async fn test_write_token() {
     fs::remove_file(&spotify.config.cache_path).unwrap();
 }
 
+#[maybe_async::test(feature = "__sync", async(feature = "__async", tokio::test))]
+async fn test_token_store() {
+    let tok = Token {
+        access_token: "test-access_token".to_owned(),
+        expires_in: Duration::try_seconds(3600).unwrap(),
+        expires_at: Some(Utc::now() + Duration::try_seconds(3600).unwrap()),
+        scopes: scopes!("playlist-read-private"),
+        refresh_token: None,
+    };
+
+    let stores: Vec<Arc<dyn TokenStore>> = vec![
+        Arc::new(MemoryTokenStore::default()),
+        Arc::new(FileTokenStore::new(".test_token_store.json")),
+    ];
+    for store in stores {
+        let config = Config {
+            token_store: Some(Arc::clone(&store)),
+            ..Default::default()
+        };
+
+        // Nothing is stored at first
+        let mut spotify = ClientCredsSpotify::default();
+        spotify.config = config.clone();
+        let cached = spotify.read_token_cache().await.unwrap();
+        assert!(cached.is_none());
+
+        let mut predefined_spotify = ClientCredsSpotify::from_token(tok.clone());
+        predefined_spotify.config = config;
+        predefined_spotify.write_token_cache().await.unwrap();
+        let stored = store.load().await.unwrap();
+        assert_eq!(stored.as_ref(), Some(&tok));
+        let cached = spotify.read_token_cache().await.unwrap();
+        assert_eq!(cached.as_ref(), Some(&tok));
+
+        store.clear().await.unwrap();
+        let stored = store.load().await.unwrap();
+        assert!(stored.is_none());
+    }
+}
+
+#[maybe_async::test(feature = "__sync", async(feature = "__async", tokio::test))]
+async fn test_env_token_store() {
+    // Read-only, so that the environment is never modified
+    let store = EnvTokenStore::new("RSPOTIFY_TEST_UNSET_TOKEN");
+    let stored = store.load().await.unwrap();
+    assert!(stored.is_none());
+
+    store.save(&Token::default()).await.unwrap();
+    let stored = store.load().await.unwrap();
+    assert!(stored.is_none());
+    assert!(std::env::var_os("RSPOTIFY_TEST_UNSET_TOKEN").is_none());
+}
+
+#[maybe_async::test(feature = "__sync", async(feature = "__async", tokio::test))]
+async fn test_user_sessions() {
+    let token = |access_token: &str| Token {
+        access_token: access_token.to_owned(),
+        ..Default::default()
+    };
+
+    let stores = Arc::new(std::sync::Mutex::new(
+        HashMap::<_, Arc<MemoryTokenStore>>::new(),
+    ));
+    let sessions_stores = Arc::clone(&stores);
+    let sessions = UserSessions::new(Credentials::default(), OAuth::default()).with_token_stores(
+        move |user: &String| -> Arc<dyn TokenStore> {
+            let mut stores = sessions_stores.lock().unwrap();
+            let store = stores
+                .entry(user.clone())
+                .or_insert_with(|| Arc::new(MemoryTokenStore::default()));
+            Arc::clone(store) as Arc<dyn TokenStore>
+        },
+    );
+    let alice = sessions.get(&"alice".to_owned()).await.unwrap();
+    assert!(alice.is_none());
+
+    let alice = sessions.insert("alice".to_owned(), token("alice-token"));
+    let bob = sessions.client(&"bob".to_owned()).await.unwrap();
+    assert_eq!(sessions.len(), 2);
+
+    // The clients of the same user share the token and the state, which is
+    // different for each user
+    *bob.token.lock().await.unwrap() = Some(token("bob-token"));
+    let bob_again = sessions.get(&"bob".to_owned()).await.unwrap().unwrap();
+    assert_eq!(bob_again.oauth.state, bob.oauth.state);
+    assert_ne!(alice.oauth.state, bob.oauth.state);
+    assert_ne!(bob.oauth.state, OAuth::default().state);
+    let bob_token = bob_again.token.lock().await.unwrap().clone();
+    assert_eq!(bob_token.unwrap().access_token, "bob-token");
+    let alice_token = alice.token.lock().await.unwrap().clone();
+    assert_eq!(alice_token.unwrap().access_token, "alice-token");
+
+    // And each user has their own store
+    alice.write_token_cache().await.unwrap();
+    bob.write_token_cache().await.unwrap();
+    let alice_store = stores.lock().unwrap()["alice"].clone();
+    let bob_store = stores.lock().unwrap()["bob"].clone();
+    let alice_stored = alice_store.load().await.unwrap().unwrap();
+    let bob_stored = bob_store.load().await.unwrap().unwrap();
+    assert_eq!(alice_stored.access_token, "alice-token");
+    assert_eq!(bob_stored.access_token, "bob-token");
+
+    assert!(sessions.remove(&"alice".to_owned()));
+    assert_eq!(sessions.keys(), ["bob".to_owned()]);
+
+    // Unregistered users are loaded from their store
+    let alice = sessions.get(&"alice".to_owned()).await.unwrap().unwrap();
+    let alice_token = alice.token.lock().await.unwrap().clone();
+    assert_eq!(alice_token.unwrap().access_token, "alice-token");
+    assert!(sessions.remove(&"alice".to_owned()));
+    let alice = sessions.client(&"alice".to_owned()).await.unwrap();
+    let alice_token = alice.token.lock().await.unwrap().clone();
+    assert_eq!(alice_token.unwrap().access_token, "alice-token");
+}
+
+#[maybe_async::test(feature = "__sync", async(feature = "__async", tokio::test))]
+async fn test_check_scopes() {
+    let tok = Token {
+        access_token: "test-access_token".to_owned(),
+        expires_at: Some(Utc::now() + Duration::try_minutes(30).unwrap()),
+        scopes: scopes!("user-library-read"),
+        ..Default::default()
+    };
+    let config = Config {
+        check_scopes: true,
+        ..Default::default()
+    };
+    let spotify = AuthCodeSpotify::from_token_with_config(
+        tok,
+        Credentials::default(),
+        OAuth::default(),
+        config,
+    );
+
+    // Fails before sending the request
+    let result = spotify.current_user_saved_tracks_add([]).await;
+    assert!(matches!(
+        result,
+        Err(ClientError::MissingScope(Scope::UserLibraryModify))
+    ));
+    let result = spotify.pause_playback(None).await;
+    assert!(matches!(
+        result,
+        Err(ClientError::MissingScope(Scope::UserModifyPlaybackState))
+    ));
+}
+
 #[test]
 #[wasm_bindgen_test]
 fn test_token_is_expired() {
@@ -149,19 +347,35 @@ fn test_parse_response_code() {
     let spotify = AuthCodeSpotify::default();
 
     // No `code` parameter
-    let url = "http://localhost:8888/callback";
-    let code = spotify.parse_response_code(url);
-    assert_eq!(code, None);
+    let url = format!(
+        "http://localhost:8888/callback?state={}",
+        spotify.oauth.state
+    );
+    let code = spotify.parse_response_code(&url);
+    assert_eq!(code, Err(AuthError::MissingCode));
+
+    // Not a URL
+    let code = spotify.parse_response_code("AQD0yXvFEOvw");
+    assert_eq!(code, Err(AuthError::MissingCode));
 
     // No `state` parameter
     let url = "http://localhost:8888/callback?code=AQD0yXvFEOvw";
     let code = spotify.parse_response_code(url);
-    assert_eq!(code, None);
+    assert_eq!(code, Err(AuthError::StateMismatch));
 
     // The `state` is not the expected one
     let url = "http://localhost:8888/callback?code=AQD0yXvFEOvw?state=abc";
     let code = spotify.parse_response_code(url);
-    assert_eq!(code, None);
+    assert_eq!(code, Err(AuthError::StateMismatch));
+
+    // The user denied the authorization
+    let url = format!(
+        "http://localhost:8888/callback?error=access_denied&state={}",
+        spotify.oauth.state
+    );
+    let code = spotify.parse_response_code(&url);
+    assert_eq!(code, Err(AuthError::AccessDenied));
+    assert!(!code.unwrap_err().needs_reauthorization());
 
     // Both parameters, and the state is the same, so it should work
     let url = format!(
@@ -169,7 +383,7 @@ fn test_parse_response_code() {
         spotify.oauth.state
     );
     let code = spotify.parse_response_code(&url);
-    assert_eq!(code, Some("AQD0yXvFEOvw".to_string()));
+    assert_eq!(code, Ok("AQD0yXvFEOvw".to_string()));
 
     // Works both ways
     let url = format!(
@@ -177,5 +391,5 @@ fn test_parse_response_code() {
         spotify.oauth.state
     );
     let code = spotify.parse_response_code(&url);
-    assert_eq!(code, Some("AQD0yXvFEOvw".to_string()));
+    assert_eq!(code, Ok("AQD0yXvFEOvw".to_string()));
 }
@@ -0,0 +1,314 @@
+//! Tests for the pagination utilities that don't require real credentials.
+
+use rspotify::{
+    model::{ArtistId, PlaylistId},
+    prelude::*,
+    AuthCodeSpotify,
+};
+use wasm_bindgen_test::*;
+
+#[cfg(not(target_arch = "wasm32"))]
+mod mock;
+
+#[test]
+#[wasm_bindgen_test]
+fn test_page_size_clamped_per_endpoint() {
+    let spotify = AuthCodeSpotify::default();
+    let playlist_id = PlaylistId::from_id("37i9dQZF1DXcBWIGoYBM5M").unwrap();
+    let artist_id = ArtistId::from_id("0OdUWJ0sBjDrqHygGUXeCF").unwrap();
+
+    let playlist_items = spotify.playlist_items(playlist_id.clone(), None, None);
+    assert_eq!(playlist_items.options().page_size, Some(50));
+    let playlist_items = playlist_items.page_size(500);
+    assert_eq!(playlist_items.options().page_size, Some(100));
+
+    let artist_albums = spotify.artist_albums(artist_id, None, None).page_size(500);
+    assert_eq!(artist_albums.options().page_size, Some(50));
+    let saved_tracks = spotify.current_user_saved_tracks(None).page_size(500);
+    assert_eq!(saved_tracks.options().page_size, Some(50));
+
+    // Sizes under the maximum are kept as they are
+    let playlist_items = spotify
+        .playlist_items(playlist_id, None, None)
+        .page_size(75);
+    assert_eq!(playlist_items.options().page_size, Some(75));
+}
+
+#[cfg(all(feature = "__async", not(target_arch = "wasm32")))]
+#[tokio::test]
+async fn test_concurrent_rate_limited() {
+    use futures_util::StreamExt;
+    use rspotify::{clients::pagination::paginate_concurrent, http::Method, model::Page};
+    use serde_json::json;
+    use std::{
+        collections::HashMap,
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc,
+        },
+    };
+
+    // The page at offset 4 is rate limited the first time it's requested
+    let limited = Arc::new(AtomicBool::new(false));
+    let server_limited = Arc::clone(&limited);
+    let server = mock::MockServer::start(move |request| {
+        let offset = request
+            .path
+            .split(|c| c == '?' || c == '&')
+            .find_map(|param| param.strip_prefix("offset="))
+            .unwrap()
+            .parse::<u32>()
+            .unwrap();
+        if offset == 4 && !server_limited.swap(true, Ordering::SeqCst) {
+            return (429, json!({"error": {"status": 429}}).to_string());
+        }
+
+        let end = (offset + 2).min(10);
+        let page = json!({
+            "href": "",
+            "items": (offset..end).collect::<Vec<_>>(),
+            "limit": 2,
+            "next": (end < 10).then(|| "next"),
+            "offset": offset,
+            "previous": null,
+            "total": 10
+        });
+        (200, page.to_string())
+    });
+    let spotify = mock::client(&server);
+
+    let paginator = paginate_concurrent(
+        |limit, offset| {
+            let spotify = &spotify;
+            async move {
+                let (limit, offset) = (limit.to_string(), offset.to_string());
+                let query = HashMap::from([("limit", limit.as_str()), ("offset", offset.as_str())]);
+                spotify
+                    .request::<Page<u32>>(Method::Get, "items", &query, None)
+                    .await
+            }
+        },
+        2,
+        3,
+        true,
+    );
+    let items = paginator.map(Result::unwrap).collect::<Vec<_>>().await;
+    assert_eq!(items, (0..10).collect::<Vec<_>>());
+    assert!(limited.load(Ordering::SeqCst));
+    assert_eq!(server.requests().len(), 6);
+}
+
+/// Starts a server that answers with the page at the offset in the path,
+/// whose `next` and `previous` URLs point to the server too. For the search
+/// endpoint, it's wrapped in an object with a `tracks` field, like Spotify
+/// does.
+#[cfg(not(target_arch = "wasm32"))]
+fn start_page_api() -> mock::MockServer {
+    use serde_json::json;
+    use std::sync::{Arc, Mutex};
+
+    let server_url = Arc::new(Mutex::new(String::new()));
+    let respond_url = Arc::clone(&server_url);
+    let server = mock::MockServer::start(move |request| {
+        let server_url = respond_url.lock().unwrap().clone();
+        let (path, offset) = request.path.split_once("?offset=").unwrap();
+        let offset = offset.parse::<u32>().unwrap();
+        let url = |offset: u32| format!("{}{}?offset={}", server_url, &path[1..], offset);
+        let end = (offset + 2).min(4);
+        let page = json!({
+            "href": url(offset),
+            "items": (offset..end).collect::<Vec<_>>(),
+            "limit": 2,
+            "next": (end < 4).then(|| url(end)),
+            "offset": offset,
+            "previous": offset.checked_sub(2).map(url),
+            "total": 4
+        });
+        let page = if path == "/search" {
+            json!({ "tracks": page })
+        } else {
+            page
+        };
+        (200, page.to_string())
+    });
+    *server_url.lock().unwrap() = server.url.clone();
+    server
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[maybe_async::test(feature = "__sync", async(feature = "__async", tokio::test))]
+async fn test_next_page() {
+    use rspotify::model::Page;
+
+    let server = start_page_api();
+    let spotify = mock::client(&server);
+
+    // A page nested in another object, like `FullPlaylist::tracks`, whose
+    // `next` URL returns the page at the top level
+    let first = Page::<u32> {
+        items: vec![0, 1],
+        next: Some(format!("{}playlists/id/tracks?offset=2", server.url)),
+        ..Default::default()
+    };
+    let second = spotify.next_page(&first).await.unwrap().unwrap();
+    assert_eq!(second.items, [2, 3]);
+    assert_eq!(second.offset, 2);
+    assert_eq!(second.next, None);
+
+    let previous = spotify.previous_page(&second).await.unwrap().unwrap();
+    assert_eq!(previous.items, [0, 1]);
+
+    // The last page has nothing to follow, so nothing is requested
+    let last = spotify.next_page(&second).await.unwrap();
+    assert!(last.is_none());
+
+    let requests = server.requests();
+    assert_eq!(requests.len(), 2);
+    assert_eq!(requests[0].path, "/playlists/id/tracks?offset=2");
+    assert_eq!(requests[0].authorization.as_deref(), Some("Bearer access"));
+    assert_eq!(requests[1].path, "/playlists/id/tracks?offset=0");
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[maybe_async::test(feature = "__sync", async(feature = "__async", tokio::test))]
+async fn test_next_page_wrapped() {
+    use rspotify::model::Page;
+
+    let server = start_page_api();
+    let spotify = mock::client(&server);
+
+    // Endpoints like `search` wrap the page in an object with a single
+    // field, and so do their `next` URLs
+    let first = Page::<u32> {
+        items: vec![0, 1],
+        next: Some(format!("{}search?offset=2", server.url)),
+        ..Default::default()
+    };
+    let second = spotify.next_page(&first).await.unwrap().unwrap();
+    assert_eq!(second.items, [2, 3]);
+    assert_eq!(second.total, 4);
+    assert!(second.next.is_none());
+    assert_eq!(server.requests().len(), 1);
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[maybe_async::test(feature = "__sync", async(feature = "__async", tokio::test))]
+async fn test_next_page_foreign_url() {
+    use rspotify::{model::Page, ClientError};
+
+    let server = start_page_api();
+    let spotify = mock::client(&server);
+
+    // The token is never sent outside of the API
+    let first = Page::<u32> {
+        next: Some("https://example.com/search?offset=2".to_owned()),
+        ..Default::default()
+    };
+    let second = spotify.next_page(&first).await;
+    assert!(matches!(second, Err(ClientError::ForeignUrl(_))));
+    assert!(server.requests().is_empty());
+}
+
+/// Answers with the page of playlist items at the offset in the path, with up
+/// to two local items each.
+#[cfg(not(target_arch = "wasm32"))]
+fn playlist_items_page(request: &mock::MockRequest, total: u32) -> String {
+    use serde_json::json;
+
+    let offset = request
+        .path
+        .split(|c| c == '?' || c == '&')
+        .find_map(|param| param.strip_prefix("offset="))
+        .unwrap()
+        .parse::<u32>()
+        .unwrap();
+    let end = (offset + 2).min(total);
+    let item = json!({"added_at": null, "added_by": null, "is_local": true, "track": null});
+    let page = json!({
+        "href": "",
+        "items": vec![item; (end - offset) as usize],
+        "limit": 2,
+        "next": (end < total).then(|| "next"),
+        "offset": offset,
+        "previous": null,
+        "total": total
+    });
+    page.to_string()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[maybe_async::test(feature = "__sync", async(feature = "__async", tokio::test))]
+async fn test_playlist_items_resumable() {
+    #[cfg(feature = "__async")]
+    use futures_util::StreamExt;
+    use rspotify::{
+        clients::pagination::{PagePosition, PaginationState},
+        model::{Market, PlaylistId},
+    };
+    use serde_json::json;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    // The second page is rate limited the first time it's requested
+    let limited = AtomicBool::new(false);
+    let server = mock::MockServer::start(move |request| {
+        if request.path.contains("offset=2") && !limited.swap(true, Ordering::SeqCst) {
+            return (429, json!({"error": {"status": 429}}).to_string());
+        }
+
+        (200, playlist_items_page(request, 3))
+    });
+    let spotify = mock::client(&server);
+    let playlist_id = PlaylistId::from_id("37i9dQZF1DXcBWIGoYBM5M").unwrap();
+    let state = PaginationState::new((playlist_id, Some(Market::FromToken)));
+
+    let mut paginator = spotify.playlist_items_resumable(state, 1).page_size(2);
+    let (page, state) = paginator.next().await.unwrap().unwrap();
+    assert_eq!(page.items.len(), 2);
+    assert_eq!(state.position, Some(PagePosition::Offset(2)));
+    let limited = paginator.next().await.unwrap();
+    assert!(limited.is_err());
+
+    // The parameters are kept in the state, so it can be resumed with them
+    let state = serde_json::from_str(&serde_json::to_string(&state).unwrap()).unwrap();
+    let paginator = spotify.playlist_items_resumable(state, 1).page_size(2);
+    let steps = paginator.collect::<Vec<_>>().await;
+    assert_eq!(steps.len(), 1);
+    let (page, state) = steps.into_iter().next().unwrap().unwrap();
+    assert_eq!(page.items.len(), 1);
+    assert!(state.is_finished());
+
+    let requests = server.requests();
+    assert_eq!(requests.len(), 3);
+    assert!(requests[2]
+        .path
+        .starts_with("/playlists/37i9dQZF1DXcBWIGoYBM5M/tracks?"));
+    assert!(requests[2].path.contains("market=from_token"));
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[maybe_async::test(feature = "__sync", async(feature = "__async", tokio::test))]
+async fn test_playlist_items_prefetch() {
+    #[cfg(feature = "__async")]
+    use futures_util::StreamExt;
+    use rspotify::{clients::pagination::PrefetchTask, model::PlaylistId};
+
+    let server = mock::MockServer::start(|request| (200, playlist_items_page(request, 5)));
+    let spotify = mock::client(&server);
+    let playlist_id = PlaylistId::from_id("37i9dQZF1DXcBWIGoYBM5M").unwrap();
+
+    #[cfg(feature = "__async")]
+    let spawn = |task: PrefetchTask| {
+        tokio::spawn(task);
+    };
+    #[cfg(feature = "__sync")]
+    let spawn = |task: PrefetchTask| {
+        std::thread::spawn(task);
+    };
+    let paginator = spotify
+        .playlist_items_prefetch(playlist_id, None, spawn)
+        .page_size(2);
+    let items = paginator.collect::<Vec<_>>().await;
+    assert_eq!(items.len(), 5);
+    assert!(items.iter().all(Result::is_ok));
+    assert_eq!(server.requests().len(), 3);
+}
@@ -0,0 +1,155 @@
+//! Tests for the playback utilities, against a local mock of the Spotify API.
+#![cfg(not(target_arch = "wasm32"))]
+
+mod mock;
+
+use mock::{client, MockRequest, MockServer};
+
+use chrono::Duration;
+use rspotify::{
+    clients::playback::Player,
+    model::{Device, DeviceType, DisallowKey},
+    AuthCodeSpotify, ClientError,
+};
+use serde_json::json;
+
+const DEVICE_ID: &str = "28d0f845293d03a2713392905c6d30b6442719b5";
+
+fn device(id: &str) -> Device {
+    Device {
+        id: Some(id.to_owned()),
+        is_active: true,
+        is_private_session: false,
+        is_restricted: false,
+        name: "Web Player (Firefox)".to_owned(),
+        _type: DeviceType::Computer,
+        volume_percent: Some(100),
+    }
+}
+
+/// Answers the current playback state with the given device and disallowed
+/// actions, and accepts every command.
+fn playback_api(
+    device_id: &'static str,
+    is_playing: bool,
+    disallows: &'static [&'static str],
+) -> impl Fn(&MockRequest) -> (u16, String) {
+    move |request| {
+        if request.method != "GET" {
+            return (204, String::new());
+        }
+
+        let disallows = disallows
+            .iter()
+            .map(|action| (action.to_string(), json!(true)))
+            .collect::<serde_json::Map<_, _>>();
+        let playback = json!({
+            "device": device(device_id),
+            "shuffle_state": false,
+            "repeat_state": "off",
+            "timestamp": 1607774342714i64,
+            "context": null,
+            "progress_ms": 1000,
+            "is_playing": is_playing,
+            "item": null,
+            "currently_playing_type": "track",
+            "actions": {"disallows": disallows}
+        });
+        (200, playback.to_string())
+    }
+}
+
+/// The method and path of the commands received, ignoring the requests for
+/// the playback state.
+fn commands(server: &MockServer) -> Vec<String> {
+    server
+        .requests()
+        .into_iter()
+        .filter(|request| request.method != "GET")
+        .map(|request| format!("{} {}", request.method, request.path))
+        .collect()
+}
+
+#[test]
+fn test_player_without_id() {
+    let spotify = AuthCodeSpotify::default();
+    let device = Device {
+        id: None,
+        ..device(DEVICE_ID)
+    };
+    assert!(Player::new(&spotify, device).is_none());
+}
+
+#[maybe_async::test(feature = "__sync", async(feature = "__async", tokio::test))]
+async fn test_player_commands() {
+    let server = MockServer::start(playback_api(DEVICE_ID, true, &["resuming"]));
+    let spotify = client(&server);
+    let player = Player::new(&spotify, device(DEVICE_ID)).unwrap();
+
+    let playing = player.toggle_pause().await.unwrap();
+    assert!(!playing);
+    player.next().await.unwrap();
+    player.set_volume(50).await.unwrap();
+    player.transfer_here(true).await.unwrap();
+
+    assert_eq!(
+        commands(&server),
+        [
+            format!("PUT /me/player/pause?device_id={DEVICE_ID}"),
+            format!("POST /me/player/next?device_id={DEVICE_ID}"),
+            format!("PUT /me/player/volume?volume_percent=50&device_id={DEVICE_ID}"),
+            "PUT /me/player".to_owned(),
+        ]
+    );
+    let transfer = server.requests().pop().unwrap();
+    assert!(transfer.body.contains(DEVICE_ID));
+}
+
+#[maybe_async::test(feature = "__sync", async(feature = "__async", tokio::test))]
+async fn test_player_toggle_other_device() {
+    // Playing on a different device, so it's resumed on this one. Its
+    // actions don't apply here, even if resuming is disallowed over there.
+    let server = MockServer::start(playback_api("other", true, &["resuming"]));
+    let spotify = client(&server);
+    let player = Player::new(&spotify, device(DEVICE_ID)).unwrap();
+
+    let playing = player.toggle_pause().await.unwrap();
+    assert!(playing);
+    let playback = player.playback().await.unwrap();
+    assert!(playback.is_none());
+    assert_eq!(
+        commands(&server),
+        [format!("PUT /me/player/play?device_id={DEVICE_ID}")]
+    );
+}
+
+#[maybe_async::test(feature = "__sync", async(feature = "__async", tokio::test))]
+async fn test_player_disallowed() {
+    let server = MockServer::start(playback_api(DEVICE_ID, true, &["skipping_next", "pausing"]));
+    let spotify = client(&server);
+    let player = Player::new(&spotify, device(DEVICE_ID)).unwrap();
+
+    let result = player.next().await;
+    assert!(matches!(
+        result,
+        Err(ClientError::Disallowed(DisallowKey::SkippingNext))
+    ));
+    let result = player.toggle_pause().await;
+    assert!(matches!(
+        result,
+        Err(ClientError::Disallowed(DisallowKey::Pausing))
+    ));
+    assert!(commands(&server).is_empty());
+
+    // Actions without restrictions still work
+    player
+        .seek(Duration::try_seconds(30).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(
+        commands(&server),
+        [format!(
+            "PUT /me/player/seek?position_ms=30000&device_id={DEVICE_ID}"
+        )]
+    );
+}
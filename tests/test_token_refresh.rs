@@ -0,0 +1,574 @@
+//! Tests for requesting and automatically refreshing tokens, against a local
+//! mock of the Spotify accounts service and API.
+#![cfg(not(target_arch = "wasm32"))]
+
+mod mock;
+
+use mock::{MockRequest, MockServer};
+
+use chrono::{prelude::*, Duration};
+use rspotify::{
+    http::{Method, Query},
+    prelude::*,
+    AuthCodePkceSpotify, AuthCodeSpotify, AuthError, ClientCredsSpotify, ClientError, Config,
+    Credentials, OAuth, Token,
+};
+use serde_json::{json, Value};
+use std::sync::{Arc, Mutex};
+
+fn token_response(access_token: &str) -> (u16, String) {
+    let body = format!(
+        r#"{{"access_token":"{}","token_type":"Bearer","expires_in":3600}}"#,
+        access_token
+    );
+    (200, body)
+}
+
+fn expired_token() -> Token {
+    Token {
+        access_token: "expired".to_owned(),
+        expires_at: Some(Utc::now() - Duration::try_seconds(1).unwrap()),
+        ..Default::default()
+    }
+}
+
+fn client_creds(server: &MockServer, token: Option<Token>) -> ClientCredsSpotify {
+    let config = Config {
+        api_base_url: server.url.clone(),
+        auth_base_url: server.url.clone(),
+        token_refreshing: true,
+        ..Default::default()
+    };
+    let mut spotify = match token {
+        Some(token) => ClientCredsSpotify::from_token(token),
+        None => ClientCredsSpotify::default(),
+    };
+    spotify.creds = Credentials::new("id", "secret");
+    spotify.config = config;
+    spotify
+}
+
+#[test]
+fn test_expires_within() {
+    let token = Token {
+        expires_at: Some(Utc::now() + Duration::try_seconds(60).unwrap()),
+        ..Default::default()
+    };
+    assert!(!token.expires_within(Duration::try_seconds(30).unwrap()));
+    assert!(token.expires_within(Duration::try_seconds(90).unwrap()));
+
+    let token = Token {
+        expires_at: None,
+        ..Default::default()
+    };
+    assert!(token.expires_within(Duration::zero()));
+}
+
+#[cfg(feature = "__async")]
+#[tokio::test]
+async fn test_single_flight_refresh() {
+    let server = MockServer::start(|_| token_response("fresh"));
+    let spotify = client_creds(&server, Some(expired_token()));
+
+    let refreshes = (0..10).map(|_| spotify.refresh_token_if_expiring());
+    for result in futures_util::future::join_all(refreshes).await {
+        result.unwrap();
+    }
+
+    assert_eq!(server.token_requests().len(), 1);
+    let token = spotify.token.lock().await.unwrap().clone().unwrap();
+    assert_eq!(token.access_token, "fresh");
+}
+
+#[cfg(feature = "__sync")]
+#[test]
+fn test_single_flight_refresh() {
+    let server = MockServer::start(|_| token_response("fresh"));
+    let spotify = client_creds(&server, Some(expired_token()));
+
+    // The clones share both the token and the refresh lock
+    let threads = (0..10)
+        .map(|_| {
+            let spotify = spotify.clone();
+            std::thread::spawn(move || spotify.refresh_token_if_expiring())
+        })
+        .collect::<Vec<_>>();
+    for thread in threads {
+        thread.join().unwrap().unwrap();
+    }
+
+    assert_eq!(server.token_requests().len(), 1);
+    let token = spotify.token.lock().unwrap().clone().unwrap();
+    assert_eq!(token.access_token, "fresh");
+}
+
+#[maybe_async::test(feature = "__sync", async(feature = "__async", tokio::test))]
+async fn test_refresh_margin() {
+    let server = MockServer::start(|_| token_response("fresh"));
+    let token = Token {
+        access_token: "valid".to_owned(),
+        expires_at: Some(Utc::now() + Duration::try_minutes(5).unwrap()),
+        ..Default::default()
+    };
+
+    // Not within the default margin of 10 seconds
+    let spotify = client_creds(&server, Some(token.clone()));
+    spotify.refresh_token_if_expiring().await.unwrap();
+    assert!(server.requests().is_empty());
+
+    // But within a margin of 10 minutes
+    let mut spotify = client_creds(&server, Some(token));
+    spotify.config.token_refresh_margin = std::time::Duration::from_secs(600);
+    spotify.refresh_token_if_expiring().await.unwrap();
+    assert_eq!(server.requests().len(), 1);
+
+    // Without a token there's nothing to keep fresh
+    let spotify = client_creds(&server, None);
+    spotify.keep_token_fresh().await.unwrap();
+    assert_eq!(server.requests().len(), 1);
+}
+
+/// Answers the token endpoint with a `fresh` token, and only accepts that
+/// token in the API.
+fn revoking_api(request: &MockRequest) -> (u16, String) {
+    if request.path == "/api/token" {
+        token_response("fresh")
+    } else if request.authorization.as_deref() == Some("Bearer fresh") {
+        (200, r#"{"ok":true}"#.to_owned())
+    } else {
+        let error = r#"{"error":{"status":401,"message":"The access token expired"}}"#;
+        (401, error.to_owned())
+    }
+}
+
+#[maybe_async::test(feature = "__sync", async(feature = "__async", tokio::test))]
+async fn test_reauth_on_unauthorized() {
+    let server = MockServer::start(revoking_api);
+    let token = Token {
+        access_token: "revoked".to_owned(),
+        expires_at: Some(Utc::now() + Duration::try_minutes(30).unwrap()),
+        ..Default::default()
+    };
+    let spotify = client_creds(&server, Some(token));
+
+    let response: Value = spotify
+        .request(Method::Get, "me", &Query::new(), None)
+        .await
+        .unwrap();
+    assert_eq!(response, json!({"ok": true}));
+    let token_requests = server.token_requests();
+    assert_eq!(token_requests.len(), 1);
+    assert!(token_requests[0]
+        .body
+        .contains("grant_type=client_credentials"));
+    let token = spotify.token.lock().await.unwrap().clone().unwrap();
+    assert_eq!(token.access_token, "fresh");
+
+    // The new token is used from now on
+    let _: Value = spotify
+        .request(Method::Put, "me", &Query::new(), Some(&json!({})))
+        .await
+        .unwrap();
+    assert_eq!(server.requests().len(), 4);
+}
+
+#[maybe_async::test(feature = "__sync", async(feature = "__async", tokio::test))]
+async fn test_reauth_on_unauthorized_fails() {
+    let server = MockServer::start(|request| {
+        if request.path == "/api/token" {
+            token_response("also-revoked")
+        } else {
+            (401, String::new())
+        }
+    });
+    let spotify = client_creds(&server, Some(expired_token()));
+
+    let response = spotify
+        .request::<Value>(Method::Get, "me", &Query::new(), None)
+        .await;
+    assert!(matches!(response, Err(ClientError::InvalidToken)));
+    // Refreshed once because it expired and once after being rejected
+    assert_eq!(server.token_requests().len(), 2);
+
+    // Without automatic reauthentication the original error is returned
+    let mut spotify = client_creds(&server, Some(expired_token()));
+    spotify.config.token_refreshing = false;
+    let response = spotify
+        .request::<Value>(Method::Get, "me", &Query::new(), None)
+        .await;
+    assert!(matches!(response, Err(ClientError::Http(_))));
+    assert_eq!(server.token_requests().len(), 2);
+}
+
+#[maybe_async::test(feature = "__sync", async(feature = "__async", tokio::test))]
+async fn test_reauth_on_unauthorized_requesting() {
+    let server = MockServer::start(revoking_api);
+    let token = Token {
+        access_token: "revoked".to_owned(),
+        expires_at: Some(Utc::now() + Duration::try_minutes(30).unwrap()),
+        ..Default::default()
+    };
+    let mut spotify = client_creds(&server, Some(token));
+    spotify.config.token_refreshing = false;
+    spotify.config.token_requesting = true;
+
+    // Requesting tokens automatically is enough to retry rejected requests
+    let response: Value = spotify
+        .request(Method::Get, "me", &Query::new(), None)
+        .await
+        .unwrap();
+    assert_eq!(response, json!({"ok": true}));
+    assert_eq!(server.token_requests().len(), 1);
+    let token = spotify.token.lock().await.unwrap().clone().unwrap();
+    assert_eq!(token.access_token, "fresh");
+}
+
+fn oauth_error(status: u16, error: &str, description: &str) -> (u16, String) {
+    let body = format!(
+        r#"{{"error":"{}","error_description":"{}"}}"#,
+        error, description
+    );
+    (status, body)
+}
+
+#[maybe_async::test(feature = "__sync", async(feature = "__async", tokio::test))]
+async fn test_token_endpoint_errors() {
+    let server = MockServer::start(|request| {
+        if request.body.contains("grant_type=client_credentials") {
+            oauth_error(400, "invalid_client", "Invalid client secret")
+        } else if request.body.contains("grant_type=refresh_token") {
+            oauth_error(400, "invalid_grant", "Refresh token revoked")
+        } else {
+            oauth_error(400, "invalid_grant", "Invalid authorization code")
+        }
+    });
+
+    let spotify = client_creds(&server, None);
+    let result = spotify.request_token().await;
+    assert!(matches!(
+        result,
+        Err(ClientError::Auth(AuthError::InvalidCredentials(description)))
+            if description == "Invalid client secret"
+    ));
+
+    let token = Token {
+        refresh_token: Some("revoked".to_owned()),
+        ..expired_token()
+    };
+    let config = Config {
+        auth_base_url: server.url.clone(),
+        ..Default::default()
+    };
+    let spotify = AuthCodeSpotify::from_token_with_config(
+        token,
+        Credentials::new("id", "secret"),
+        OAuth::default(),
+        config,
+    );
+    let err = match spotify.refresh_token().await {
+        Err(ClientError::Auth(err)) => err,
+        result => panic!("unexpected result: {:?}", result),
+    };
+    assert_eq!(
+        err,
+        AuthError::InvalidRefreshToken("Refresh token revoked".to_owned())
+    );
+    assert!(err.needs_reauthorization());
+
+    let request = spotify.authorization_request(false).unwrap();
+    let result = spotify.request_token_with("expired", &request).await;
+    assert!(matches!(
+        result,
+        Err(ClientError::Auth(AuthError::InvalidCode(_)))
+    ));
+}
+
+#[maybe_async::test(feature = "__sync", async(feature = "__async", tokio::test))]
+async fn test_pkce_missing_verifier() {
+    let server = MockServer::start(|_| token_response("fresh"));
+    let config = Config {
+        auth_base_url: server.url.clone(),
+        ..Default::default()
+    };
+    let spotify =
+        AuthCodePkceSpotify::with_config(Credentials::new_pkce("id"), OAuth::default(), config);
+
+    // The request is expected to include the verifier, but e.g. it may have
+    // been created by a non-PKCE client
+    let mut request = spotify.authorization_request(None).unwrap();
+    request.verifier = None;
+    let result = spotify.request_token_with("code", &request).await;
+    assert!(matches!(
+        result,
+        Err(ClientError::Auth(AuthError::MissingVerifier))
+    ));
+    assert!(server.token_requests().is_empty());
+}
+
+#[cfg(feature = "__async")]
+#[tokio::test]
+async fn test_async_token_callback() {
+    use rspotify::{AsyncTokenCallback, CallbackError};
+    use std::error::Error;
+
+    let server = MockServer::start(|request| {
+        if request.body.contains("grant_type=refresh_token") {
+            token_response("refreshed")
+        } else {
+            token_response("fresh")
+        }
+    });
+
+    let saved = Arc::new(Mutex::new(Vec::new()));
+    let callback_saved = Arc::clone(&saved);
+    let callback = AsyncTokenCallback::new(move |token: Token| {
+        let saved = Arc::clone(&callback_saved);
+        async move {
+            saved.lock().unwrap().push(token.access_token);
+            Ok(())
+        }
+    });
+    let config = Config {
+        auth_base_url: server.url.clone(),
+        async_token_callback_fn: Arc::new(Some(callback)),
+        ..Default::default()
+    };
+
+    let spotify = ClientCredsSpotify::with_config(Credentials::new("id", "secret"), config.clone());
+    spotify.request_token().await.unwrap();
+    assert_eq!(*saved.lock().unwrap(), ["fresh"]);
+
+    let token = Token {
+        refresh_token: Some("refresh".to_owned()),
+        ..expired_token()
+    };
+    let spotify = AuthCodeSpotify::from_token_with_config(
+        token,
+        Credentials::new("id", "secret"),
+        OAuth::default(),
+        config,
+    );
+    spotify.refresh_token().await.unwrap();
+    assert_eq!(*saved.lock().unwrap(), ["fresh", "refreshed"]);
+
+    // The errors of the callback are kept as the source
+    let callback = AsyncTokenCallback::new(|_| async {
+        let err = std::io::Error::new(std::io::ErrorKind::Other, "database is down");
+        Err(CallbackError::other(err))
+    });
+    let config = Config {
+        auth_base_url: server.url.clone(),
+        async_token_callback_fn: Arc::new(Some(callback)),
+        ..Default::default()
+    };
+    let spotify = ClientCredsSpotify::with_config(Credentials::new("id", "secret"), config);
+    let err = match spotify.request_token().await {
+        Err(ClientError::TokenCallbackFn(err)) => err,
+        result => panic!("unexpected result: {:?}", result),
+    };
+    assert_eq!(err.source().unwrap().to_string(), "database is down");
+}
+
+#[maybe_async::test(feature = "__sync", async(feature = "__async", tokio::test))]
+async fn test_refresh_token_rotation() {
+    use rspotify::RefreshTokenCallback;
+
+    // Only the `rotating` refresh token is replaced by the server
+    let server = MockServer::start(|request| {
+        let (status, body) = token_response("refreshed");
+        if request.body.contains("refresh_token=rotating") {
+            let mut body: Value = serde_json::from_str(&body).unwrap();
+            body["refresh_token"] = json!("rotated");
+            (status, body.to_string())
+        } else {
+            (status, body)
+        }
+    });
+
+    let rotations = Arc::new(Mutex::new(Vec::new()));
+    let callback_rotations = Arc::clone(&rotations);
+    let callback = RefreshTokenCallback(Box::new(move |previous, new| {
+        callback_rotations.lock().unwrap().push((previous, new));
+        Ok(())
+    }));
+    let config = Config {
+        auth_base_url: server.url.clone(),
+        refresh_token_callback_fn: Arc::new(Some(callback)),
+        ..Default::default()
+    };
+    let token = |refresh_token: &str| Token {
+        refresh_token: Some(refresh_token.to_owned()),
+        ..expired_token()
+    };
+
+    for refresh_token in ["rotating", "static"] {
+        let spotify = AuthCodeSpotify::from_token_with_config(
+            token(refresh_token),
+            Credentials::new("id", "secret"),
+            OAuth::default(),
+            config.clone(),
+        );
+        spotify.refresh_token().await.unwrap();
+        let refreshed = spotify.get_token().lock().await.unwrap().clone().unwrap();
+        assert_eq!(refreshed.access_token, "refreshed");
+
+        let mut spotify = AuthCodePkceSpotify::from_token(token(refresh_token));
+        spotify.creds = Credentials::new("id", "secret");
+        spotify.config = config.clone();
+        spotify.refresh_token().await.unwrap();
+        let pkce_refreshed = spotify.get_token().lock().await.unwrap().clone().unwrap();
+
+        let expected = match refresh_token {
+            "rotating" => "rotated",
+            _ => refresh_token,
+        };
+        assert_eq!(refreshed.refresh_token.as_deref(), Some(expected));
+        assert_eq!(pkce_refreshed.refresh_token.as_deref(), Some(expected));
+    }
+
+    // The callback only runs when the refresh token changes
+    let rotation = ("rotating".to_owned(), "rotated".to_owned());
+    assert_eq!(*rotations.lock().unwrap(), [rotation.clone(), rotation]);
+}
+
+#[maybe_async::test(feature = "__sync", async(feature = "__async", tokio::test))]
+async fn test_refresh_token_callback_error() {
+    use rspotify::{CallbackError, RefreshTokenCallback};
+
+    let server = MockServer::start(|_| {
+        let (status, body) = token_response("refreshed");
+        let mut body: Value = serde_json::from_str(&body).unwrap();
+        body["refresh_token"] = json!("rotated");
+        (status, body.to_string())
+    });
+    let callback = RefreshTokenCallback(Box::new(|_, _| {
+        Err(CallbackError::CustomizedError(
+            "database is down".to_owned(),
+        ))
+    }));
+    let spotify = AuthCodeSpotify::from_token_with_config(
+        Token {
+            refresh_token: Some("rotating".to_owned()),
+            ..expired_token()
+        },
+        Credentials::new("id", "secret"),
+        OAuth::default(),
+        Config {
+            auth_base_url: server.url.clone(),
+            refresh_token_callback_fn: Arc::new(Some(callback)),
+            ..Default::default()
+        },
+    );
+
+    // The rotated refresh token is kept even if the callback fails
+    spotify.refresh_token().await.unwrap();
+    let refreshed = spotify.get_token().lock().await.unwrap().clone().unwrap();
+    assert_eq!(refreshed.access_token, "refreshed");
+    assert_eq!(refreshed.refresh_token.as_deref(), Some("rotated"));
+}
+
+#[maybe_async::test(feature = "__sync", async(feature = "__async", tokio::test))]
+async fn test_token_callback_error_rotated() {
+    use rspotify::{CallbackError, TokenCallback};
+
+    let server = MockServer::start(|_| {
+        let (status, body) = token_response("refreshed");
+        let mut body: Value = serde_json::from_str(&body).unwrap();
+        body["refresh_token"] = json!("rotated");
+        (status, body.to_string())
+    });
+    let callback = TokenCallback(Box::new(|_| {
+        Err(CallbackError::CustomizedError(
+            "database is down".to_owned(),
+        ))
+    }));
+    let spotify = AuthCodeSpotify::from_token_with_config(
+        Token {
+            refresh_token: Some("rotating".to_owned()),
+            ..expired_token()
+        },
+        Credentials::new("id", "secret"),
+        OAuth::default(),
+        Config {
+            auth_base_url: server.url.clone(),
+            token_callback_fn: Arc::new(Some(callback)),
+            ..Default::default()
+        },
+    );
+
+    // The previous refresh token may not work anymore, so the rotated one is
+    // kept even if the token callback fails
+    spotify.refresh_token().await.unwrap();
+    let refreshed = spotify.get_token().lock().await.unwrap().clone().unwrap();
+    assert_eq!(refreshed.access_token, "refreshed");
+    assert_eq!(refreshed.refresh_token.as_deref(), Some("rotated"));
+}
+
+#[maybe_async::test(feature = "__sync", async(feature = "__async", tokio::test))]
+async fn test_token_requesting() {
+    use rspotify::token_store::MemoryTokenStore;
+
+    let server = MockServer::start(revoking_api);
+
+    // Without a token, requests fail unless it's requested automatically
+    let mut spotify = client_creds(&server, None);
+    let response = spotify
+        .request::<Value>(Method::Get, "me", &Query::new(), None)
+        .await;
+    assert!(matches!(response, Err(ClientError::InvalidToken)));
+    assert!(server.requests().is_empty());
+
+    spotify.config.token_requesting = true;
+    let response: Value = spotify
+        .request(Method::Get, "me", &Query::new(), None)
+        .await
+        .unwrap();
+    assert_eq!(response, json!({"ok": true}));
+    assert_eq!(server.token_requests().len(), 1);
+
+    // The token is only requested once
+    let _: Value = spotify
+        .request(Method::Get, "me", &Query::new(), None)
+        .await
+        .unwrap();
+    assert_eq!(server.token_requests().len(), 1);
+
+    // Expired tokens are refreshed even if refreshing isn't enabled
+    *spotify.token.lock().await.unwrap() = Some(expired_token());
+    spotify.config.token_refreshing = false;
+    let _: Value = spotify
+        .request(Method::Get, "me", &Query::new(), None)
+        .await
+        .unwrap();
+    assert_eq!(server.token_requests().len(), 2);
+
+    // The token store is read first
+    let cached = Token {
+        access_token: "fresh".to_owned(),
+        expires_at: Some(Utc::now() + Duration::try_minutes(30).unwrap()),
+        ..Default::default()
+    };
+    let mut spotify = client_creds(&server, None);
+    spotify.config.token_requesting = true;
+    spotify.config.token_store = Some(Arc::new(MemoryTokenStore::new(Some(cached))));
+    let _: Value = spotify
+        .request(Method::Get, "me", &Query::new(), None)
+        .await
+        .unwrap();
+    assert_eq!(server.token_requests().len(), 2);
+}
+
+#[cfg(feature = "__async")]
+#[tokio::test]
+async fn test_token_requesting_single_flight() {
+    let server = MockServer::start(revoking_api);
+    let mut spotify = client_creds(&server, None);
+    spotify.config.token_requesting = true;
+
+    let query = Query::new();
+    let requests = (0..5).map(|_| spotify.request::<Value>(Method::Get, "me", &query, None));
+    for response in futures_util::future::join_all(requests).await {
+        assert_eq!(response.unwrap(), json!({"ok": true}));
+    }
+    assert_eq!(server.token_requests().len(), 1);
+}
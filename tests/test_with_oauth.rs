@@ -411,12 +411,11 @@ async fn test_playback() {
     let backup = client.current_playback(None, None::<&[_]>).await.unwrap();
 
     for (i, device) in devices.iter().enumerate() {
-        let device_id = device.id.as_ref().unwrap();
-        let next_device_id = devices
+        let device_id = &device.device_id().unwrap();
+        let next_device_id = &devices
             .get(i + 1)
             .unwrap_or(&devices[0])
-            .id
-            .as_ref()
+            .device_id()
             .unwrap();
 
         // Starting playback of some songs
@@ -463,10 +462,10 @@ async fn test_playback() {
         let uri = backup.item.as_ref().map(|item| item.id());
         if let Some(uri) = uri {
             let offset = None;
-            let device = backup.device.id.as_deref();
+            let device = backup.device.device_id();
             let position = backup.progress;
             client
-                .start_uris_playback(uri, device, offset, position)
+                .start_uris_playback(uri, device.as_ref(), offset, position)
                 .await
                 .unwrap();
         }